@@ -1,4 +1,5 @@
-use crate::services::{config, metadata, mirror, release, update};
+use crate::database::DbPool;
+use crate::services::{backup, config, health, http, import, metadata, mirror, release, reset, shutdown, update};
 use tauri::{AppHandle, Emitter, State};
 
 #[tauri::command]
@@ -17,8 +18,8 @@ pub fn greet(name: &str) -> String {
 }
 
 #[tauri::command]
-pub fn quit(app_handle: AppHandle) {
-    app_handle.exit(0);
+pub async fn quit(app_handle: AppHandle) {
+    shutdown::shutdown(&app_handle).await;
 }
 
 fn exe_dir() -> Result<std::path::PathBuf, String> {
@@ -27,12 +28,103 @@ fn exe_dir() -> Result<std::path::PathBuf, String> {
     Ok(exe_path)
 }
 
+/// `check_online` 为 true 时先探测网络，离线则直接返回 `"offline"`，避免底层连接错误
+async fn ensure_online(client: &reqwest::Client, check_online: Option<bool>) -> Result<(), String> {
+    if check_online.unwrap_or(false) && !http::is_online(client).await {
+        return Err(http::OFFLINE_ERROR.to_string());
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn is_online(client: State<'_, reqwest::Client>) -> Result<bool, String> {
+    Ok(http::is_online(&client).await)
+}
+
+/// 汇总数据库、元数据、账号与路径状态，供诊断页一次性展示
+#[tauri::command]
+pub async fn app_health(app: AppHandle, pool: State<'_, DbPool>) -> Result<health::AppHealth, String> {
+    let exe_dir = exe_dir()?;
+    let app_version = get_app_version(app)?;
+    Ok(health::app_health(pool.inner(), &exe_dir, &app_version).await)
+}
+
 #[tauri::command]
 pub fn get_storage_paths() -> Result<config::StoragePaths, String> {
     let exe_dir = exe_dir()?;
     config::ensure_paths(&exe_dir)
 }
 
+/// 迁移数据目录；先合并 WAL 以保证复制的数据库完整，新目录在重启后启用
+#[tauri::command]
+pub async fn relocate_data_dir(
+    pool: State<'_, DbPool>,
+    new_path: String,
+    overwrite: Option<bool>,
+) -> Result<config::StoragePaths, String> {
+    let exe_dir = exe_dir()?;
+    sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)")
+        .execute(pool.inner())
+        .await
+        .map_err(|e| e.to_string())?;
+    config::relocate_data_dir(&exe_dir, std::path::Path::new(new_path.trim()), overwrite.unwrap_or(false))
+}
+
+/// 获取恢复出厂设置用的一次性确认令牌（60 秒内有效）
+#[tauri::command]
+pub fn factory_reset_token(tokens: State<'_, reset::ResetTokens>) -> Result<String, String> {
+    tokens.issue()
+}
+
+/// 清空数据库、元数据与配置，返回被删除的路径；完成后需重启应用
+#[tauri::command]
+pub async fn factory_reset(
+    pool: State<'_, DbPool>,
+    tasks: State<'_, shutdown::ActiveTasks>,
+    tokens: State<'_, reset::ResetTokens>,
+    confirm_token: String,
+) -> Result<Vec<String>, String> {
+    tokens.consume(&confirm_token)?;
+    let exe_dir = exe_dir()?;
+    if !tasks.wait_idle(std::time::Duration::from_secs(5)).await {
+        return Err("同步进行中，请稍后再试".to_string());
+    }
+    reset::factory_reset(pool.inner(), &exe_dir).await
+}
+
+/// 导出数据库、配置与版本清单为 zip 备份包
+#[tauri::command]
+pub async fn export_backup_bundle(
+    app: AppHandle,
+    pool: State<'_, DbPool>,
+    dest_zip: String,
+) -> Result<backup::BackupManifest, String> {
+    let exe_dir = exe_dir()?;
+    let app_version = get_app_version(app)?;
+    backup::export_backup_bundle(pool.inner(), &exe_dir, &app_version, std::path::Path::new(&dest_zip)).await
+}
+
+/// 从 zip 备份包恢复数据库与配置
+#[tauri::command]
+pub async fn import_backup_bundle(
+    pool: State<'_, DbPool>,
+    src_zip: String,
+) -> Result<backup::BackupManifest, String> {
+    let exe_dir = exe_dir()?;
+    backup::import_backup_bundle(pool.inner(), &exe_dir, std::path::Path::new(&src_zip)).await
+}
+
+/// 按列映射从任意 CSV 导入抽卡记录，经由同步的保存路径去重
+#[tauri::command]
+pub async fn import_gacha_generic(
+    pool: State<'_, DbPool>,
+    uid: String,
+    path: String,
+    mapping: import::ColumnMapping,
+) -> Result<import::GenericImportResult, String> {
+    import::import_gacha_generic(pool.inner(), &uid, std::path::Path::new(&path), &mapping).await
+}
+
 #[tauri::command]
 pub fn read_config() -> Result<serde_json::Value, String> {
     let exe_dir = exe_dir()?;
@@ -45,29 +137,84 @@ pub fn save_config(config: serde_json::Value) -> Result<(), String> {
     config::save_config(&exe_dir, config)
 }
 
+#[tauri::command]
+pub fn reset_config() -> Result<serde_json::Value, String> {
+    let exe_dir = exe_dir()?;
+    config::reset_config(&exe_dir)
+}
+
 #[tauri::command]
 pub fn check_metadata() -> Result<metadata::MetadataStatus, String> {
     let exe_dir = exe_dir()?;
     metadata::check_metadata_status(&exe_dir)
 }
 
+/// 计算单个文件的 SHA256（相对路径按元数据目录解析），用于排查个别文件是否与清单一致
+#[tauri::command]
+pub fn compute_file_checksum(path: String) -> Result<metadata::FileChecksum, String> {
+    let exe_dir = exe_dir()?;
+    metadata::file_checksum(&exe_dir, &path)
+}
+
 #[tauri::command]
 pub async fn fetch_metadata_manifest(
     client: State<'_, reqwest::Client>,
     base_url: String,
     version: Option<String>,
+    check_online: Option<bool>,
 ) -> Result<metadata::RemoteManifest, String> {
+    ensure_online(&client, check_online).await?;
     let ver = version.unwrap_or_else(|| "latest".to_string());
     metadata::fetch_manifest(&client, &base_url, &ver).await
 }
 
+/// 列出远端清单中的文件（路径 / 大小 / 校验和）及总大小，供更新前预览
+#[tauri::command]
+pub async fn fetch_metadata_entries(
+    client: State<'_, reqwest::Client>,
+    base_url: String,
+    version: Option<String>,
+    check_online: Option<bool>,
+) -> Result<metadata::MetadataEntries, String> {
+    ensure_online(&client, check_online).await?;
+    let ver = version.unwrap_or_else(|| "latest".to_string());
+    metadata::fetch_metadata_entries(&client, &base_url, &ver).await
+}
+
+#[tauri::command]
+pub async fn metadata_update_available(
+    client: State<'_, reqwest::Client>,
+    base_url: String,
+    version: Option<String>,
+    check_online: Option<bool>,
+) -> Result<metadata::MetadataUpdateCheck, String> {
+    ensure_online(&client, check_online).await?;
+    let exe_dir = exe_dir()?;
+    let ver = version.unwrap_or_else(|| "latest".to_string());
+    metadata::metadata_update_available(&exe_dir, &client, &base_url, &ver).await
+}
+
+/// 对比两个元数据版本的清单，列出新增 / 删除 / 变更的文件
+#[tauri::command]
+pub async fn metadata_diff(
+    client: State<'_, reqwest::Client>,
+    base_url: Option<String>,
+    old_version: String,
+    new_version: String,
+) -> Result<metadata::MetadataDiff, String> {
+    let exe_dir = exe_dir()?;
+    metadata::metadata_diff(&exe_dir, &client, base_url, &old_version, &new_version).await
+}
+
 #[tauri::command]
 pub async fn reset_metadata(
     window: tauri::Window,
     client: State<'_, reqwest::Client>,
     base_url: Option<String>,
     version: Option<String>,
+    check_online: Option<bool>,
 ) -> Result<metadata::MetadataStatus, String> {
+    ensure_online(&client, check_online).await?;
     let exe_dir = exe_dir()?;
 
     metadata::reset_metadata(
@@ -88,7 +235,9 @@ pub async fn update_metadata(
     _app: AppHandle,
     client: State<'_, reqwest::Client>,
     base_url: Option<String>,
+    check_online: Option<bool>,
 ) -> Result<metadata::MetadataStatus, String> {
+    ensure_online(&client, check_online).await?;
     let exe_dir = exe_dir()?;
 
     metadata::update_metadata(
@@ -104,13 +253,63 @@ pub async fn update_metadata(
 }
 
 #[tauri::command]
-pub async fn fetch_latest_release(client: State<'_, reqwest::Client>) -> Result<release::LatestRelease, String> {
-    release::fetch_latest_release(&client).await
+pub async fn fetch_latest_release(
+    client: State<'_, reqwest::Client>,
+    check_online: Option<bool>,
+) -> Result<release::LatestRelease, release::ReleaseError> {
+    if check_online.unwrap_or(false) && !http::is_online(&client).await {
+        return Err(release::ReleaseError::offline());
+    }
+    let repo = exe_dir().map(|dir| release::ReleaseRepo::from_config(&dir)).unwrap_or_default();
+    release::fetch_latest_release(&client, &repo).await
 }
 
 #[tauri::command]
-pub async fn fetch_latest_prerelease(client: State<'_, reqwest::Client>) -> Result<release::LatestRelease, String> {
-    release::fetch_latest_prerelease(&client).await
+pub async fn fetch_latest_prerelease(client: State<'_, reqwest::Client>) -> Result<release::LatestRelease, release::ReleaseError> {
+    let repo = exe_dir().map(|dir| release::ReleaseRepo::from_config(&dir)).unwrap_or_default();
+    release::fetch_latest_prerelease(&client, &repo).await
+}
+
+/// 从配置的更新通道与仓库获取最新版本（`check_online` 含义同 [`fetch_latest_release`]）
+async fn fetch_configured_channel_release(
+    client: &reqwest::Client,
+    check_online: Option<bool>,
+) -> Result<release::LatestRelease, release::ReleaseError> {
+    if check_online.unwrap_or(false) && !http::is_online(client).await {
+        return Err(release::ReleaseError::offline());
+    }
+    let (channel, repo) = exe_dir()
+        .map(|dir| (release::read_update_channel(&dir), release::ReleaseRepo::from_config(&dir)))
+        .unwrap_or_default();
+    release::fetch_release_for_channel(client, &repo, channel).await
+}
+
+/// 按配置中的 `updateChannel`（stable / beta）获取最新版本
+#[tauri::command]
+pub async fn fetch_channel_release(
+    client: State<'_, reqwest::Client>,
+    check_online: Option<bool>,
+) -> Result<release::LatestRelease, release::ReleaseError> {
+    fetch_configured_channel_release(&client, check_online).await
+}
+
+/// 将当前版本与配置通道上的最新版本比较
+#[tauri::command]
+pub async fn check_for_update(
+    app: AppHandle,
+    client: State<'_, reqwest::Client>,
+    check_online: Option<bool>,
+) -> Result<release::UpdateCheck, release::ReleaseError> {
+    let current = get_app_version(app).map_err(|message| release::ReleaseError::Network { message })?;
+    let latest = fetch_configured_channel_release(&client, check_online).await?;
+
+    Ok(release::UpdateCheck {
+        update_available: release::is_update_available(&current, &latest.tag_name),
+        current,
+        latest: latest.tag_name,
+        download_url: latest.download_url,
+        checksum: latest.checksum,
+    })
 }
 
 #[tauri::command]
@@ -119,6 +318,7 @@ pub async fn download_and_apply_update(
     app: AppHandle,
     client: State<'_, reqwest::Client>,
     download_url: String,
+    checksum: Option<String>,
 ) -> Result<(), String> {
     let emit_progress = |stage: &str, progress: u32| {
         let _ = window.emit("update-progress", update::UpdateProgress {
@@ -139,9 +339,23 @@ pub async fn download_and_apply_update(
     let mirror_config = mirror::read_mirror_config(&exe_dir);
     let actual_download_url = mirror_config.transform_url(&download_url);
 
-    update::download_new_exe(&client, &actual_download_url, &paths.new_exe, |p| {
-        emit_progress("downloading", p);
-    }).await?;
+    // 优先使用 release 中的 digest，否则尝试同名 .sha256 文件；两者都没有时跳过校验。
+    // .sha256 总是从原始 GitHub 地址获取，镜像无法同时替换安装包和校验值
+    let expected_checksum = match checksum.filter(|s| !s.trim().is_empty()) {
+        Some(c) => Some(c),
+        None => update::fetch_sha256_sidecar(&client, &download_url).await,
+    };
+
+    update::download_new_exe(
+        &client,
+        &actual_download_url,
+        &paths.new_exe,
+        expected_checksum.as_deref(),
+        |p| {
+            emit_progress("downloading", p);
+        },
+    )
+    .await?;
 
     emit_progress("preparing", 100);
 
@@ -169,6 +383,29 @@ pub async fn download_and_apply_update(
     Ok(())
 }
 
+/// 校验镜像配置，供设置页在保存前拒绝无效的自定义模板
+#[tauri::command]
+pub fn validate_mirror_config(config: mirror::GithubMirrorConfig) -> Result<(), String> {
+    config.validate()
+}
+
+/// 按镜像配置测试连通性，返回是否可用、延迟与 HTTP 状态
+#[tauri::command]
+pub async fn test_mirror(
+    client: State<'_, reqwest::Client>,
+    config: mirror::GithubMirrorConfig,
+) -> Result<mirror::MirrorTestResult, String> {
+    mirror::test_mirror(&client, &config).await
+}
+
+/// 自动选择响应最快的内置镜像，均不可用时返回 None
+#[tauri::command]
+pub async fn auto_select_mirror(
+    client: State<'_, reqwest::Client>,
+) -> Result<Option<mirror::GithubMirrorSource>, String> {
+    Ok(mirror::auto_select_mirror(&client).await)
+}
+
 /// 测试 GitHub 镜像连通性，返回延迟毫秒数
 #[tauri::command]
 pub async fn test_github_mirror(
@@ -176,8 +413,7 @@ pub async fn test_github_mirror(
     mirror_url_template: String,
 ) -> Result<u64, String> {
     // 使用一个小的 GitHub 文件测试连通性
-    let test_url = "https://raw.githubusercontent.com/BoxCatTeam/endfield-cat/master/package.json";
-    let proxied_url = mirror_url_template.replace("{url}", test_url);
+    let proxied_url = mirror_url_template.replace("{url}", mirror::MIRROR_TEST_URL);
 
     let start = std::time::Instant::now();
     let resp = client
@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
-use sqlx::{sqlite::SqlitePoolOptions, Pool, Sqlite, Row};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous};
+use sqlx::{Pool, Sqlite, Row};
 // std::collections imported inline where needed
 use tauri::{State, AppHandle};
 
@@ -11,18 +12,28 @@ macro_rules! log_dev {
     };
 }
 
+use std::collections::HashMap;
 use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
 
 pub type DbPool = Pool<Sqlite>;
 const CURRENT_DB_VERSION: i32 = 2; // 1: legacy (no version); 2: schema guard (pre-release; schema may evolve without bump)
 
+/// Location of the SQLite file inside the (possibly relocated) data directory
+pub fn db_file_path(exe_dir: &Path) -> PathBuf {
+    crate::services::config::resolve_data_dir(exe_dir).join("database").join("endcat.db")
+}
+
 // Initialize the database pool
 pub async fn init_db(_app: &AppHandle) -> Result<DbPool, Box<dyn std::error::Error>> {
     let mut exe_path = std::env::current_exe()?;
     exe_path.pop(); // Remove executable name
     
-    let db_dir = exe_path.join("data").join("database");
-    let config_dir = exe_path.join("data").join("config");
+    let data_dir = crate::services::config::resolve_data_dir(&exe_path);
+    let db_dir = data_dir.join("database");
+    let config_dir = data_dir.join("config");
     let old_user_data_dir = exe_path.join("userData");
     
     // Create new directories
@@ -52,9 +63,15 @@ pub async fn init_db(_app: &AppHandle) -> Result<DbPool, Box<dyn std::error::Err
     let database_url = format!("sqlite:{}?mode=rwc", db_path_str);
     
     let existed_before = db_path.exists();
+    // Applied to every pooled connection: wait on locks instead of failing with
+    // "database is locked", and let UI reads proceed while a sync is writing.
+    let connect_options = SqliteConnectOptions::from_str(&database_url)?
+        .busy_timeout(Duration::from_millis(5000))
+        .journal_mode(SqliteJournalMode::Wal)
+        .synchronous(SqliteSynchronous::Normal);
     let pool = SqlitePoolOptions::new()
         .max_connections(5)
-        .connect(&database_url)
+        .connect_with(connect_options)
         .await?;
 
     // Schema version guard / migrations
@@ -84,6 +101,58 @@ pub async fn init_db(_app: &AppHandle) -> Result<DbPool, Box<dyn std::error::Err
         }
     }
 
+    migrate_schema(&pool).await?;
+
+    // Stamp version for fresh/legacy DB after migrations
+    if should_stamp_version {
+        sqlx::query(&format!("PRAGMA user_version = {}", CURRENT_DB_VERSION))
+            .execute(&pool)
+            .await
+            .ok();
+    }
+        
+    Ok(pool)
+}
+
+/// Column additions (Migrations)
+async fn add_missing_columns(pool: &DbPool) {
+    let columns = vec![
+        ("accounts", "role_id", "TEXT"),
+        ("accounts", "nick_name", "TEXT"),
+        ("accounts", "server_id", "TEXT DEFAULT '1'"),
+        ("accounts", "channel_id", "INTEGER"),
+        ("accounts", "user_token", "TEXT"),
+        ("accounts", "oauth_token", "TEXT"),
+        ("accounts", "u8_token", "TEXT"),
+        ("accounts", "created_at", "INTEGER DEFAULT (unixepoch())"),
+        ("accounts", "updated_at", "INTEGER DEFAULT (unixepoch())"),
+        ("accounts", "last_synced_at", "INTEGER"),
+        ("accounts", "u8_token_fetched_at", "INTEGER"),
+        ("accounts", "alias", "TEXT"),
+        ("accounts", "sort_order", "INTEGER"),
+        ("accounts", "archived", "INTEGER NOT NULL DEFAULT 0"),
+        ("gacha_pulls", "seq_id", "TEXT"),
+        ("gacha_pulls", "item_id", "TEXT"),
+        ("gacha_pulls", "pool_type", "TEXT"),
+        ("gacha_pulls", "is_free", "INTEGER"),
+        ("gacha_pulls", "is_new", "INTEGER"),
+        ("gacha_pulls", "item_name_i18n", "TEXT"),
+        ("gacha_pulls", "source", "TEXT DEFAULT 'unknown'"),
+    ];
+    
+    for (table, col, ty) in columns {
+        let check_sql = format!("SELECT count(*) FROM pragma_table_info('{}') WHERE name = '{}'", table, col);
+        let count: i32 = sqlx::query_scalar(&check_sql).fetch_one(pool).await.unwrap_or(0);
+        if count == 0 {
+            let alter_sql = format!("ALTER TABLE {} ADD COLUMN {} {}", table, col, ty);
+            sqlx::query(&alter_sql).execute(pool).await.ok();
+        }
+    }
+}
+
+/// Create missing tables and bring older layouts up to date. Idempotent; also used by tests
+/// so their fixtures share the production schema.
+pub(crate) async fn migrate_schema(pool: &DbPool) -> Result<(), String> {
     // Manual Migrations (ensure tables exist)
     sqlx::query(r#"
 CREATE TABLE IF NOT EXISTS gacha_pulls (
@@ -111,58 +180,51 @@ CREATE TABLE IF NOT EXISTS accounts (
   updated_at INTEGER NOT NULL DEFAULT (unixepoch())
 );
 CREATE INDEX IF NOT EXISTS idx_accounts_updated_at ON accounts(updated_at DESC);
-"#).execute(&pool).await.map_err(|e| e.to_string())?;
 
-    // Column additions (Migrations)
-    let columns = vec![
-        ("accounts", "role_id", "TEXT"),
-        ("accounts", "nick_name", "TEXT"),
-        ("accounts", "server_id", "TEXT DEFAULT '1'"),
-        ("accounts", "channel_id", "INTEGER"),
-        ("accounts", "user_token", "TEXT"),
-        ("accounts", "oauth_token", "TEXT"),
-        ("accounts", "u8_token", "TEXT"),
-        ("accounts", "created_at", "INTEGER DEFAULT (unixepoch())"),
-        ("accounts", "updated_at", "INTEGER DEFAULT (unixepoch())"),
-        ("gacha_pulls", "seq_id", "TEXT"),
-        ("gacha_pulls", "item_id", "TEXT"),
-        ("gacha_pulls", "pool_type", "TEXT"),
-        ("gacha_pulls", "is_free", "INTEGER"),
-        ("gacha_pulls", "is_new", "INTEGER"),
-    ];
-    
-    for (table, col, ty) in columns {
-        let check_sql = format!("SELECT count(*) FROM pragma_table_info('{}') WHERE name = '{}'", table, col);
-        let count: i32 = sqlx::query_scalar(&check_sql).fetch_one(&pool).await.unwrap_or(0);
-        if count == 0 {
-            let alter_sql = format!("ALTER TABLE {} ADD COLUMN {} {}", table, col, ty);
-            sqlx::query(&alter_sql).execute(&pool).await.ok();
-        }
-    }
+CREATE TABLE IF NOT EXISTS sync_log (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  uid TEXT,
+  started_at INTEGER NOT NULL,
+  finished_at INTEGER NOT NULL,
+  mode TEXT NOT NULL,
+  count INTEGER,
+  success INTEGER NOT NULL,
+  error_message TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_sync_log_uid_time ON sync_log(uid, started_at DESC);
 
-    // Indices for seq_id
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_gacha_pulls_seq_id ON gacha_pulls(seq_id)")
-        .execute(&pool).await.ok();
+CREATE TABLE IF NOT EXISTS gacha_tags (
+  uid TEXT NOT NULL,
+  seq_id TEXT NOT NULL,
+  pool_type TEXT NOT NULL,
+  tag TEXT NOT NULL,
+  created_at INTEGER NOT NULL DEFAULT (unixepoch()),
+  PRIMARY KEY (uid, seq_id, pool_type, tag)
+);
+"#).execute(pool).await.map_err(|e| e.to_string())?;
+
+    // Older tables lack columns the rebuild below copies
+    add_missing_columns(pool).await;
 
     // Pre-release migration: make accounts token columns nullable if they were created as NOT NULL.
     // We intentionally do NOT bump `user_version` here to avoid forcing resets before release.
     // SQLite can't alter column nullability; we must rebuild the table if needed.
     let notnull_user_token: i64 = sqlx::query_scalar(
-        "SELECT COALESCE((SELECT notnull FROM pragma_table_info('accounts') WHERE name = 'user_token' LIMIT 1), 0)"
+        "SELECT COALESCE((SELECT \"notnull\" FROM pragma_table_info('accounts') WHERE name = 'user_token' LIMIT 1), 0)"
     )
-    .fetch_one(&pool)
+    .fetch_one(pool)
     .await
     .unwrap_or(0);
     let notnull_oauth_token: i64 = sqlx::query_scalar(
-        "SELECT COALESCE((SELECT notnull FROM pragma_table_info('accounts') WHERE name = 'oauth_token' LIMIT 1), 0)"
+        "SELECT COALESCE((SELECT \"notnull\" FROM pragma_table_info('accounts') WHERE name = 'oauth_token' LIMIT 1), 0)"
     )
-    .fetch_one(&pool)
+    .fetch_one(pool)
     .await
     .unwrap_or(0);
     let notnull_u8_token: i64 = sqlx::query_scalar(
-        "SELECT COALESCE((SELECT notnull FROM pragma_table_info('accounts') WHERE name = 'u8_token' LIMIT 1), 0)"
+        "SELECT COALESCE((SELECT \"notnull\" FROM pragma_table_info('accounts') WHERE name = 'u8_token' LIMIT 1), 0)"
     )
-    .fetch_one(&pool)
+    .fetch_one(pool)
     .await
     .unwrap_or(0);
 
@@ -218,15 +280,18 @@ FROM accounts;
         tx.commit().await.map_err(|e| e.to_string())?;
     }
 
-    // Stamp version for fresh/legacy DB after migrations
-    if should_stamp_version {
-        sqlx::query(&format!("PRAGMA user_version = {}", CURRENT_DB_VERSION))
-            .execute(&pool)
-            .await
-            .ok();
-    }
-        
-    Ok(pool)
+    // The rebuild above only keeps the original columns, so add the later ones back
+    add_missing_columns(pool).await;
+
+    // Rows from before provenance was tracked
+    sqlx::query("UPDATE gacha_pulls SET source = 'unknown' WHERE source IS NULL")
+        .execute(pool).await.ok();
+
+    // Indices for seq_id
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_gacha_pulls_seq_id ON gacha_pulls(seq_id)")
+        .execute(pool).await.ok();
+
+    Ok(())
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -241,8 +306,20 @@ pub struct GachaPull {
     pub pulled_at: i64,
     pub seq_id: Option<String>,
     pub pool_type: Option<String>,
+    /// `pulled_at` 按请求的时区偏移格式化的 ISO 8601 时间（仅在传入 `tzOffsetSeconds` 时返回）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pulled_at_local: Option<String>,
+    /// Where the row came from: `token`, `log`, `import`, `manual`, or `unknown` for legacy rows
+    pub source: String,
 }
 
+/// `gacha_pulls.source` values
+pub const SOURCE_TOKEN: &str = "token";
+pub const SOURCE_LOG: &str = "log";
+pub const SOURCE_IMPORT: &str = "import";
+pub const SOURCE_MANUAL: &str = "manual";
+pub const SOURCE_UNKNOWN: &str = "unknown";
+
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
 struct GachaRow {
     uid: String,
@@ -254,19 +331,147 @@ struct GachaRow {
     pulled_at: i64,
     seq_id: Option<String>,
     pool_type: Option<String>,
+    #[sqlx(default)]
+    source: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvalidRecordsDeleted {
+    /// Rows with `pulled_at = 0` (timestamp missing).
+    pub zero_pulled_at: u64,
+    /// Rows with `rarity = 0` (rarity missing).
+    pub zero_rarity: u64,
 }
 
 #[tauri::command]
 pub async fn db_delete_invalid_gacha_records(
     pool: State<'_, DbPool>,
     uid: String,
-) -> Result<(), String> {
-    sqlx::query("DELETE FROM gacha_pulls WHERE uid = ? AND pulled_at = 0")
-        .bind(uid)
+) -> Result<InvalidRecordsDeleted, String> {
+    let zero_pulled_at = sqlx::query("DELETE FROM gacha_pulls WHERE uid = ? AND pulled_at = 0")
+        .bind(&uid)
+        .execute(pool.inner())
+        .await
+        .map_err(|e| e.to_string())?
+        .rows_affected();
+    let zero_rarity = sqlx::query("DELETE FROM gacha_pulls WHERE uid = ? AND rarity = 0")
+        .bind(&uid)
         .execute(pool.inner())
         .await
+        .map_err(|e| e.to_string())?
+        .rows_affected();
+    Ok(InvalidRecordsDeleted { zero_pulled_at, zero_rarity })
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanCleanup {
+    /// `gacha_pulls` rows whose uid has no `accounts` row.
+    pub deleted_rows: u64,
+    /// `gacha_tags` rows whose record no longer exists.
+    pub deleted_tags: u64,
+    /// Database file shrinkage after `VACUUM` (may be 0 or negative).
+    pub freed_bytes: i64,
+}
+
+/// Size of the main database file, as `page_count * page_size`.
+async fn db_file_size(pool: &DbPool) -> Result<i64, String> {
+    let page_count: i64 = sqlx::query_scalar("PRAGMA page_count")
+        .fetch_one(pool)
+        .await
         .map_err(|e| e.to_string())?;
-    Ok(())
+    let page_size: i64 = sqlx::query_scalar("PRAGMA page_size")
+        .fetch_one(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(page_count * page_size)
+}
+
+/// Housekeeping: drop records of accounts that no longer exist and tags of records that no
+/// longer exist, then optimize and vacuum.
+#[tauri::command]
+pub async fn db_cleanup_orphans(pool: State<'_, DbPool>) -> Result<OrphanCleanup, String> {
+    let size_before = db_file_size(pool.inner()).await?;
+
+    let deleted_rows = sqlx::query(
+        "DELETE FROM gacha_pulls WHERE uid NOT IN (SELECT uid FROM accounts)"
+    )
+    .execute(pool.inner())
+    .await
+    .map_err(|e| e.to_string())?
+    .rows_affected();
+
+    let deleted_tags = sqlx::query(
+        "DELETE FROM gacha_tags WHERE NOT EXISTS (
+           SELECT 1 FROM gacha_pulls p
+           WHERE p.uid = gacha_tags.uid AND p.seq_id = gacha_tags.seq_id AND p.pool_type = gacha_tags.pool_type)"
+    )
+    .execute(pool.inner())
+    .await
+    .map_err(|e| e.to_string())?
+    .rows_affected();
+
+    sqlx::query("PRAGMA optimize")
+        .execute(pool.inner())
+        .await
+        .map_err(|e| e.to_string())?;
+    sqlx::query("VACUUM")
+        .execute(pool.inner())
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let size_after = db_file_size(pool.inner()).await?;
+    Ok(OrphanCleanup { deleted_rows, deleted_tags, freed_bytes: size_before - size_after })
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DbStats {
+    pub db_size_bytes: u64,
+    /// Size of the `-wal` sidecar; 0 when it doesn't exist.
+    pub wal_size_bytes: u64,
+    pub account_count: i64,
+    pub total_pulls: i64,
+    /// Oldest / newest `pulled_at` in seconds, ignoring rows without a timestamp.
+    pub oldest_pull: Option<i64>,
+    pub newest_pull: Option<i64>,
+}
+
+#[tauri::command]
+pub async fn db_stats(pool: State<'_, DbPool>) -> Result<DbStats, String> {
+    let exe_dir = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|d| d.to_path_buf()))
+        .ok_or("Cannot get exe directory")?;
+    let db_path = db_file_path(&exe_dir);
+    let file_size = |path: &Path| fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    let mut wal_path = db_path.clone().into_os_string();
+    wal_path.push("-wal");
+
+    let account_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM accounts")
+        .fetch_one(pool.inner())
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let row = sqlx::query(
+        "SELECT COUNT(*) AS total,
+                MIN(CASE WHEN pulled_at = 0 THEN NULL WHEN pulled_at < 1000000000000 THEN pulled_at ELSE pulled_at / 1000 END) AS oldest,
+                MAX(CASE WHEN pulled_at = 0 THEN NULL WHEN pulled_at < 1000000000000 THEN pulled_at ELSE pulled_at / 1000 END) AS newest
+         FROM gacha_pulls"
+    )
+    .fetch_one(pool.inner())
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(DbStats {
+        db_size_bytes: file_size(&db_path),
+        wal_size_bytes: file_size(Path::new(&wal_path)),
+        account_count,
+        total_pulls: row.get("total"),
+        oldest_pull: row.get("oldest"),
+        newest_pull: row.get("newest"),
+    })
 }
 
 #[tauri::command]
@@ -274,21 +479,52 @@ pub async fn db_list_gacha_pulls(
     pool: State<'_, DbPool>,
     uid: String,
     limit: i64,
+    tz_offset_seconds: Option<i32>,
+    lang: Option<String>,
 ) -> Result<Vec<GachaPull>, String> {
+    // `item_name` holds the sync's primary language, so it is the fallback for any other.
+    let name_path = lang
+        .as_deref()
+        .map(normalize_lang)
+        .transpose()?
+        .map(|lang| format!("$.\"{lang}\""));
+    let offset = tz_offset_seconds
+        .map(|secs| {
+            chrono::FixedOffset::east_opt(secs)
+                .ok_or_else(|| format!("invalid tzOffsetSeconds: {secs}"))
+        })
+        .transpose()?;
+
     let rows = sqlx::query_as::<_, GachaRow>(
-        "SELECT uid, banner_id, banner_name, item_name, item_id, rarity, pulled_at, seq_id, pool_type 
+        "SELECT uid, banner_id, banner_name,
+                COALESCE(json_extract(item_name_i18n, ?), item_name) AS item_name,
+                item_id, rarity, pulled_at, seq_id, pool_type, source
          FROM gacha_pulls 
          WHERE uid = ? 
          ORDER BY pulled_at DESC 
          LIMIT ?"
     )
+    .bind(name_path)
     .bind(uid)
     .bind(limit)
     .fetch_all(pool.inner())
     .await
     .map_err(|e| e.to_string())?;
 
-    let pulls = rows.into_iter().map(|r| {
+    let pulls = rows
+        .into_iter()
+        .map(|r| {
+            let mut pull = GachaPull::from(r);
+            pull.pulled_at_local = offset.and_then(|o| pulled_at_local(pull.pulled_at, o));
+            pull
+        })
+        .collect();
+
+    Ok(pulls)
+}
+
+impl From<GachaRow> for GachaPull {
+    fn from(r: GachaRow) -> Self {
         GachaPull {
             uid: r.uid,
             banner_id: r.banner_id,
@@ -299,152 +535,941 @@ pub async fn db_list_gacha_pulls(
             pulled_at: r.pulled_at,
             seq_id: r.seq_id,
             pool_type: r.pool_type,
+            pulled_at_local: None,
+            source: r.source.unwrap_or_else(|| SOURCE_UNKNOWN.to_string()),
         }
-    }).collect();
+    }
+}
 
-    Ok(pulls)
+/// Lower-case a record language code such as `en-US`; it ends up in a JSON path, so only
+/// letters, digits and `-` are accepted.
+pub fn normalize_lang(lang: &str) -> Result<String, String> {
+    let lang = lang.trim().to_ascii_lowercase();
+    if lang.is_empty() || !lang.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(format!("invalid lang: {lang:?}"));
+    }
+    Ok(lang)
 }
 
-#[derive(Deserialize)]
-pub struct ApiGachaRecord {
-    pub name: String,
-    pub item_id: Option<String>,
-    pub rarity: i64,
-    pub pool_id: String,
-    pub pool_name: String,
-    pub seq_id: String,
+/// Format a stored `pulled_at` (seconds or milliseconds) as ISO 8601 at `offset`,
+/// e.g. `2024-01-01T08:00:00+08:00`. `None` for the `0` placeholder or out-of-range values.
+fn pulled_at_local(pulled_at: i64, offset: chrono::FixedOffset) -> Option<String> {
+    if pulled_at == 0 {
+        return None;
+    }
+    let secs = if pulled_at < 1000000000000 { pulled_at } else { pulled_at / 1000 };
+    chrono::DateTime::from_timestamp(secs, 0).map(|t| t.with_timezone(&offset).to_rfc3339())
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct GachaPageRow {
+    id: i64,
+    #[sqlx(flatten)]
+    row: GachaRow,
+}
+
+/// Position after the last row of a page, ordered by `(pulled_at DESC, id DESC)`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GachaPullCursor {
     pub pulled_at: i64,
-    pub pool_type: String,
-    pub is_free: bool,
-    pub is_new: bool,
+    pub id: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GachaPullPage {
+    pub items: Vec<GachaPull>,
+    /// `None` once the last page has been returned.
+    pub next_cursor: Option<GachaPullCursor>,
 }
 
+/// Keyset pagination over a uid's records, newest first. Pass the previous page's
+/// `next_cursor` as `before_pulled_at` / `before_id`; omit both for the first page.
 #[tauri::command]
-pub async fn db_save_gacha_records(
+pub async fn db_list_gacha_pulls_page(
     pool: State<'_, DbPool>,
     uid: String,
-    records: Vec<ApiGachaRecord>,
-) -> Result<(), String> {
-    if records.is_empty() {
-        return Ok(());
-    }
-    
-    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    before_pulled_at: Option<i64>,
+    before_id: Option<i64>,
+    limit: i64,
+) -> Result<GachaPullPage, String> {
+    let limit = limit.max(1);
+    let before_id = before_id.unwrap_or(i64::MAX);
 
-    // We now rely on seq_id column for deduplication
-    // 1. Get existing seq_ids for this UID to filtering insesrts/updates
-    // Actually, `INSERT OR REPLACE` or `ON CONFLICT` strategy involves UNIQUE constraint on seq_id?
-    // We don't have a UNIQUE constraint on seq_id globally (or (uid, seq_id)).
-    // The current schema index is just INDEX.
-    // So we should check existence manually or use UPSERT with explicit check if we can add constraint.
-    // But we can't easily add constraint to existing table in SQLite without full copy-migration.
-    // So manual check is safer.
+    // One extra row tells whether another page exists.
+    let mut rows = sqlx::query_as::<_, GachaPageRow>(
+        "SELECT id, uid, banner_id, banner_name, item_name, item_id, rarity, pulled_at, seq_id, pool_type, source
+         FROM gacha_pulls
+         WHERE uid = ?
+           AND (? IS NULL OR pulled_at < ? OR (pulled_at = ? AND id < ?))
+         ORDER BY pulled_at DESC, id DESC
+         LIMIT ?"
+    )
+    .bind(&uid)
+    .bind(before_pulled_at)
+    .bind(before_pulled_at)
+    .bind(before_pulled_at)
+    .bind(before_id)
+    .bind(limit + 1)
+    .fetch_all(pool.inner())
+    .await
+    .map_err(|e| e.to_string())?;
 
-    // Get existing seq_ids to avoid duplicates.
-    // Since we fetch records in batches, we can just query for existence of these specifc seq_ids?
-    // But API usually returns new pages.
-    // Let's optimize: query all seq_id for this user? Might be large.
-    // Better: For each record, try update, if not affected, insert.
-    // Or: Query existing seq_ids that match the input list.
-    
-    let incoming_seq_ids: Vec<String> = records.iter().map(|r| r.seq_id.clone()).collect();
-    // SQLite has limit on bound variables (usually 999 or 32766). 
-    // If records len is small (<500), we can use `seq_id IN (...)`.
-    
-    let mut existing_seq_ids = std::collections::HashSet::new();
-    if incoming_seq_ids.len() < 500 {
-        // Construct query
-        let placeholders: Vec<_> = incoming_seq_ids.iter().map(|_| "?").collect();
-        let query = format!("SELECT seq_id FROM gacha_pulls WHERE uid = ? AND seq_id IN ({})", placeholders.join(","));
-        let mut q = sqlx::query(&query).bind(&uid);
-        for sid in &incoming_seq_ids {
-            q = q.bind(sid);
-        }
-        
-        let rows = q.fetch_all(&mut *tx).await.map_err(|e| e.to_string())?;
-        for row in rows {
-            let s: String = row.get("seq_id");
-            existing_seq_ids.insert(s);
-        }
+    let has_more = rows.len() as i64 > limit;
+    rows.truncate(limit as usize);
+
+    let next_cursor = if has_more {
+        rows.last().map(|r| GachaPullCursor { pulled_at: r.row.pulled_at, id: r.id })
     } else {
-        // Fallback for large batches: just check one by one or fetch all user's latest (not safe)
-        // Or fetch all seq_ids (only strings) if not too massive.
-        // Let's assume batch size is usually small (page size 10-100).
-        // If > 500, we proceed one-by-one check inside loop or chunk it. 
-        // Let's just handle inside loop for robustness if list is huge, 
-        // though `hg_fetch_char_records` fetches all pages before saving? 
-        // Ah, `saveGachaRecords` is called with `allFetched`.
-        // If user pulls 1000 items, `existing_seq_ids` query might fail if we bind all.
-        // Let's skip the batch check if it's too large and rely on check-per-item or `INSERT ... WHERE NOT EXISTS`.
-        // But we want to UPDATE if exists (to update pool_type etc).
-    }
+        None
+    };
 
-    // Actually, since we removed meta merging logic, we just want to ensure the record is up to date.
-    // `seq_id` is the unique key from API.
-    
-    for r in records {
-        // Try UPDATE first
-        // IMPORTANT: seq_id is only unique within the same pool_type, not globally!
-        // So we must include pool_type in the WHERE clause.
-        let affected = sqlx::query(
-            "UPDATE gacha_pulls SET 
-                banner_id = ?, banner_name = ?, item_name = ?, item_id = ?, rarity = ?, pulled_at = ?, is_free = ?, is_new = ?
-             WHERE uid = ? AND seq_id = ? AND pool_type = ?"
-        )
-        .bind(&r.pool_id)
-        .bind(&r.pool_name)
-        .bind(&r.name)
-        .bind(&r.item_id)
-        .bind(r.rarity)
-        .bind(r.pulled_at)
-        .bind(r.is_free)
-        .bind(r.is_new)
-        .bind(&uid)
-        .bind(&r.seq_id)
-        .bind(&r.pool_type)
-        .execute(&mut *tx)
-        .await
-        .map_err(|e| e.to_string())?
-        .rows_affected();
-        
-        if affected == 0 {
-            // INSERT
-            sqlx::query(
-                "INSERT INTO gacha_pulls (uid, banner_id, banner_name, item_name, item_id, rarity, pulled_at, seq_id, pool_type, is_free, is_new)
-                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
-            )
-            .bind(&uid)
-            .bind(&r.pool_id)
-            .bind(&r.pool_name)
-            .bind(&r.name)
-            .bind(&r.item_id)
-            .bind(r.rarity)
-            .bind(r.pulled_at)
-            .bind(&r.seq_id)
-            .bind(&r.pool_type)
-            .bind(r.is_free)
-            .bind(r.is_new)
-            .execute(&mut *tx)
-            .await
-            .map_err(|e| e.to_string())?;
-        }
-    }
+    Ok(GachaPullPage {
+        items: rows.into_iter().map(|r| r.row.into()).collect(),
+        next_cursor,
+    })
+}
 
-    tx.commit().await.map_err(|e| e.to_string())?;
-    Ok(())
+// ─────────────── Tags API ───────────────
+
+/// Separator for `group_concat` of tags; a control char can't appear in user-typed tags.
+const TAG_SEPARATOR: char = '\u{1f}';
+
+fn split_tags(joined: Option<String>) -> Vec<String> {
+    joined
+        .map(|s| s.split(TAG_SEPARATOR).filter(|t| !t.is_empty()).map(str::to_owned).collect())
+        .unwrap_or_default()
 }
 
-// ─────────────── Account API ───────────────
+fn normalize_tag(tag: &str) -> Result<String, String> {
+    let tag = tag.trim();
+    if tag.is_empty() || tag.contains(TAG_SEPARATOR) {
+        return Err("无效的标签".to_string());
+    }
+    Ok(tag.to_owned())
+}
 
-#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+#[derive(Debug, Serialize, sqlx::FromRow)]
 #[serde(rename_all = "camelCase")]
-pub struct Account {
-    pub uid: String,
-    pub role_id: Option<String>,
+pub struct GachaTag {
+    pub seq_id: String,
+    pub pool_type: String,
+    pub tag: String,
+    pub created_at: i64,
+}
+
+/// Attach `tag` (e.g. "favorite") to one record. Tagging twice is a no-op.
+#[tauri::command]
+pub async fn db_tag_pull(
+    pool: State<'_, DbPool>,
+    uid: String,
+    seq_id: String,
+    pool_type: String,
+    tag: String,
+) -> Result<(), String> {
+    let tag = normalize_tag(&tag)?;
+    sqlx::query("INSERT OR IGNORE INTO gacha_tags (uid, seq_id, pool_type, tag) VALUES (?, ?, ?, ?)")
+        .bind(uid)
+        .bind(seq_id)
+        .bind(pool_type)
+        .bind(tag)
+        .execute(pool.inner())
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Remove `tag` from one record; returns whether it was set.
+#[tauri::command]
+pub async fn db_untag_pull(
+    pool: State<'_, DbPool>,
+    uid: String,
+    seq_id: String,
+    pool_type: String,
+    tag: String,
+) -> Result<bool, String> {
+    let res = sqlx::query("DELETE FROM gacha_tags WHERE uid = ? AND seq_id = ? AND pool_type = ? AND tag = ?")
+        .bind(uid)
+        .bind(seq_id)
+        .bind(pool_type)
+        .bind(tag.trim())
+        .execute(pool.inner())
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Every tag of a uid, newest first.
+#[tauri::command]
+pub async fn db_list_tags(pool: State<'_, DbPool>, uid: String) -> Result<Vec<GachaTag>, String> {
+    sqlx::query_as::<_, GachaTag>(
+        "SELECT seq_id, pool_type, tag, created_at FROM gacha_tags WHERE uid = ? ORDER BY created_at DESC, tag ASC"
+    )
+    .bind(uid)
+    .fetch_all(pool.inner())
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaggedGachaPull {
+    #[serde(flatten)]
+    pub pull: GachaPull,
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct TaggedGachaRow {
+    #[sqlx(flatten)]
+    row: GachaRow,
+    tags: Option<String>,
+}
+
+/// [`db_list_gacha_pulls`] with each record's tags attached.
+#[tauri::command]
+pub async fn db_list_gacha_pulls_with_tags(
+    pool: State<'_, DbPool>,
+    uid: String,
+    limit: i64,
+) -> Result<Vec<TaggedGachaPull>, String> {
+    let rows = sqlx::query_as::<_, TaggedGachaRow>(
+        "SELECT g.uid, g.banner_id, g.banner_name, g.item_name, g.item_id, g.rarity, g.pulled_at, g.seq_id, g.pool_type, g.source,
+                (SELECT group_concat(t.tag, char(31)) FROM gacha_tags t
+                 WHERE t.uid = g.uid AND t.seq_id = g.seq_id AND t.pool_type = g.pool_type) AS tags
+         FROM gacha_pulls g
+         WHERE g.uid = ?
+         ORDER BY g.pulled_at DESC
+         LIMIT ?"
+    )
+    .bind(uid)
+    .bind(limit)
+    .fetch_all(pool.inner())
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| TaggedGachaPull { pull: r.row.into(), tags: split_tags(r.tags) })
+        .collect())
+}
+
+/// Escape `\`, `%` and `_` so `query` matches literally inside `LIKE ... ESCAPE '\'`.
+fn escape_like(query: &str) -> String {
+    let mut escaped = String::with_capacity(query.len());
+    for c in query.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Records whose `item_name` contains `query` (case-insensitive), newest first.
+#[tauri::command]
+pub async fn db_search_gacha(
+    pool: State<'_, DbPool>,
+    uid: String,
+    query: String,
+    limit: i64,
+) -> Result<Vec<GachaPull>, String> {
+    search_gacha(pool.inner(), &uid, &query, limit).await
+}
+
+async fn search_gacha(pool: &DbPool, uid: &str, query: &str, limit: i64) -> Result<Vec<GachaPull>, String> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    let pattern = format!("%{}%", escape_like(query));
+
+    let rows = sqlx::query_as::<_, GachaRow>(
+        "SELECT uid, banner_id, banner_name, item_name, item_id, rarity, pulled_at, seq_id, pool_type, source
+         FROM gacha_pulls
+         WHERE uid = ? AND item_name LIKE ? ESCAPE '\\'
+         ORDER BY pulled_at DESC, id DESC
+         LIMIT ?"
+    )
+    .bind(uid)
+    .bind(pattern)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(rows.into_iter().map(GachaPull::from).collect())
+}
+
+/// Records pulled strictly after `after_pulled_at`, oldest first so consumers can append.
+/// `pulled_at` may be stored in seconds or milliseconds; both sides are compared in seconds.
+#[tauri::command]
+pub async fn db_gacha_after(
+    pool: State<'_, DbPool>,
+    uid: String,
+    after_pulled_at: i64,
+) -> Result<Vec<GachaPull>, String> {
+    let after_secs = if after_pulled_at < 1000000000000 { after_pulled_at } else { after_pulled_at / 1000 };
+
+    let rows = sqlx::query_as::<_, GachaRow>(
+        "SELECT uid, banner_id, banner_name, item_name, item_id, rarity, pulled_at, seq_id, pool_type, source
+         FROM gacha_pulls
+         WHERE uid = ?
+           AND (CASE WHEN pulled_at < 1000000000000 THEN pulled_at ELSE pulled_at / 1000 END) > ?
+         ORDER BY (CASE WHEN pulled_at < 1000000000000 THEN pulled_at ELSE pulled_at / 1000 END) ASC, id ASC"
+    )
+    .bind(uid)
+    .bind(after_secs)
+    .fetch_all(pool.inner())
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(rows.into_iter().map(GachaPull::from).collect())
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolSummary {
+    pub banner_id: String,
+    pub banner_name: String,
+    pub pool_type: Option<String>,
+    pub count: i64,
+    pub first_pull: i64,
+    pub last_pull: i64,
+}
+
+/// Distinct pools a uid has pulled from, newest first, for filter dropdowns.
+#[tauri::command]
+pub async fn db_list_pools(
+    pool: State<'_, DbPool>,
+    uid: String,
+) -> Result<Vec<PoolSummary>, String> {
+    sqlx::query_as::<_, PoolSummary>(
+        "SELECT banner_id, MAX(banner_name) AS banner_name, pool_type, COUNT(*) AS count,
+                MIN(pulled_at) AS first_pull, MAX(pulled_at) AS last_pull
+         FROM gacha_pulls
+         WHERE uid = ?
+         GROUP BY banner_id, pool_type
+         ORDER BY last_pull DESC"
+    )
+    .bind(uid)
+    .fetch_all(pool.inner())
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct TimelineBucket {
+    /// Bucket start as unix seconds (UTC), aligned to the caller's local midnight.
+    pub bucket_start: i64,
+    pub count: i64,
+    pub six_star_count: i64,
+}
+
+/// Pull counts grouped by local day / week (Monday start) / month for charting.
+/// `tz_offset_seconds` is the user's UTC offset, e.g. 28800 for UTC+8.
+#[tauri::command]
+pub async fn db_gacha_timeline(
+    pool: State<'_, DbPool>,
+    uid: String,
+    bucket: String,
+    tz_offset_seconds: Option<i64>,
+) -> Result<Vec<TimelineBucket>, String> {
+    // `ts` is the local-time pull timestamp in seconds (older rows may store milliseconds).
+    let bucket_expr = match bucket.as_str() {
+        "day" => "(ts / 86400) * 86400",
+        // 1970-01-01 was a Thursday; shift by 3 days so weeks start on Monday.
+        "week" => "((ts / 86400 + 3) / 7 * 7 - 3) * 86400",
+        "month" => "CAST(strftime('%s', ts, 'unixepoch', 'start of month') AS INTEGER)",
+        other => return Err(format!("unsupported bucket: {other}")),
+    };
+    let offset = tz_offset_seconds.unwrap_or(0);
+
+    let sql = format!(
+        "SELECT {bucket_expr} - ? AS bucket_start, COUNT(*) AS count,
+                SUM(CASE WHEN rarity = 6 THEN 1 ELSE 0 END) AS six_star_count
+         FROM (
+           SELECT (CASE WHEN pulled_at < 1000000000000 THEN pulled_at ELSE pulled_at / 1000 END) + ? AS ts, rarity
+           FROM gacha_pulls
+           WHERE uid = ? AND pulled_at != 0
+         )
+         GROUP BY bucket_start
+         ORDER BY bucket_start"
+    );
+    sqlx::query_as::<_, TimelineBucket>(&sql)
+        .bind(offset)
+        .bind(offset)
+        .bind(uid)
+        .fetch_all(pool.inner())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Default cost of one character pull, overridable via config `gachaPullCost`.
+pub const DEFAULT_PULL_COST: i64 = 500;
+
+/// Character and weapon pools keep separate pity; weapon banners each count on their own.
+const POOL_KEY_SQL: &str = "CASE WHEN pool_type = 'E_CharacterGachaPoolType_Weapon' THEN banner_id
+                                 ELSE COALESCE(NULLIF(pool_type, ''), banner_id) END";
+
+#[derive(Debug, sqlx::FromRow)]
+struct ReportPoolRow {
+    pool_key: String,
+    pool_type: Option<String>,
+    banner_name: String,
+    total: i64,
+    paid: i64,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct ReportSixStarRow {
+    pool_key: String,
+    item_name: String,
+    item_id: Option<String>,
+    banner_name: String,
+    pulled_at: i64,
+    /// 1-based position within the pool, oldest first
+    n: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportSixStar {
+    pub item_name: String,
+    pub item_id: Option<String>,
+    pub banner_name: String,
+    pub pulled_at: i64,
+    /// Pulls it took, counting this one
+    pub pity: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportPool {
+    /// `pool_type` for character pools, `banner_id` for weapon banners
+    pub pool_key: String,
+    pub pool_type: Option<String>,
+    pub banner_name: String,
+    pub total_pulls: i64,
+    /// Pulls since the last 6-star
+    pub current_pity: i64,
+    /// Oldest first
+    pub six_stars: Vec<ReportSixStar>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GachaReport {
+    pub uid: String,
+    pub total_pulls: i64,
+    pub pull_cost: i64,
+    /// Non-free character pulls × `pull_cost`; weapon pulls use a separate currency.
+    pub spend_estimate: i64,
+    pub first_pull: Option<i64>,
+    pub last_pull: Option<i64>,
+    pub pools: Vec<ReportPool>,
+    pub luckiest: Option<ReportSixStar>,
+    pub unluckiest: Option<ReportSixStar>,
+}
+
+/// Attach 6-stars to their pools and work out pity from their positions.
+fn build_report_pools(pool_rows: Vec<ReportPoolRow>, six_rows: Vec<ReportSixStarRow>) -> Vec<ReportPool> {
+    let mut pools: Vec<ReportPool> = pool_rows
+        .into_iter()
+        .map(|r| ReportPool {
+            pool_key: r.pool_key,
+            pool_type: r.pool_type,
+            banner_name: r.banner_name,
+            total_pulls: r.total,
+            current_pity: r.total,
+            six_stars: Vec::new(),
+        })
+        .collect();
+
+    let mut last_n: HashMap<String, i64> = HashMap::new();
+    for row in six_rows {
+        let prev = last_n.insert(row.pool_key.clone(), row.n).unwrap_or(0);
+        if let Some(pool) = pools.iter_mut().find(|p| p.pool_key == row.pool_key) {
+            pool.current_pity = pool.total_pulls - row.n;
+            pool.six_stars.push(ReportSixStar {
+                item_name: row.item_name,
+                item_id: row.item_id,
+                banner_name: row.banner_name,
+                pulled_at: row.pulled_at,
+                pity: row.n - prev,
+            });
+        }
+    }
+    pools
+}
+
+/// Everything a shareable report card needs in one call: per-pool 6-stars with pity, totals,
+/// an estimated spend, the luckiest / unluckiest 6-star and the date range.
+#[tauri::command]
+pub async fn db_gacha_report(pool: State<'_, DbPool>, uid: String) -> Result<GachaReport, String> {
+    let pull_cost = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|d| d.to_path_buf()))
+        .and_then(|dir| crate::services::config::read_config_field::<i64>(&dir, "gachaPullCost"))
+        .filter(|cost| *cost >= 0)
+        .unwrap_or(DEFAULT_PULL_COST);
+
+    let pool_rows = sqlx::query_as::<_, ReportPoolRow>(&format!(
+        "SELECT {POOL_KEY_SQL} AS pool_key, MAX(pool_type) AS pool_type, MAX(banner_name) AS banner_name,
+                COUNT(*) AS total,
+                SUM(CASE WHEN COALESCE(pool_type, '') != 'E_CharacterGachaPoolType_Weapon' AND COALESCE(is_free, 0) = 0
+                         THEN 1 ELSE 0 END) AS paid
+         FROM gacha_pulls
+         WHERE uid = ?
+         GROUP BY pool_key
+         ORDER BY MAX(pulled_at) DESC"
+    ))
+    .bind(&uid)
+    .fetch_all(pool.inner())
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let six_rows = sqlx::query_as::<_, ReportSixStarRow>(&format!(
+        "SELECT pool_key, item_name, item_id, banner_name, pulled_at, n
+         FROM (
+           SELECT pool_key, item_name, item_id, banner_name, rarity, ts AS pulled_at,
+                  ROW_NUMBER() OVER (PARTITION BY pool_key ORDER BY ts, CAST(seq_id AS INTEGER), id) AS n
+           FROM (
+             SELECT {POOL_KEY_SQL} AS pool_key, item_name, item_id, banner_name, rarity, seq_id, id,
+                    CASE WHEN pulled_at < 1000000000000 THEN pulled_at ELSE pulled_at / 1000 END AS ts
+             FROM gacha_pulls
+             WHERE uid = ?
+           )
+         )
+         WHERE rarity = 6
+         ORDER BY pool_key, n"
+    ))
+    .bind(&uid)
+    .fetch_all(pool.inner())
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let range = sqlx::query(
+        "SELECT MIN(ts) AS first_pull, MAX(ts) AS last_pull
+         FROM (SELECT CASE WHEN pulled_at < 1000000000000 THEN pulled_at ELSE pulled_at / 1000 END AS ts
+               FROM gacha_pulls WHERE uid = ? AND pulled_at != 0)"
+    )
+    .bind(&uid)
+    .fetch_one(pool.inner())
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let total_pulls = pool_rows.iter().map(|r| r.total).sum();
+    let paid_pulls: i64 = pool_rows.iter().map(|r| r.paid).sum();
+    let pools = build_report_pools(pool_rows, six_rows);
+
+    let all_six = pools.iter().flat_map(|p| p.six_stars.iter());
+    let luckiest = all_six.clone().min_by_key(|s| s.pity).cloned();
+    let unluckiest = all_six.max_by_key(|s| s.pity).cloned();
+
+    Ok(GachaReport {
+        uid,
+        total_pulls,
+        pull_cost,
+        spend_estimate: paid_pulls * pull_cost,
+        first_pull: range.get("first_pull"),
+        last_pull: range.get("last_pull"),
+        pools,
+        luckiest,
+        unluckiest,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LimitedOutcome {
+    /// Rate-up won on a 50/50
+    Won,
+    /// Off-banner 6-star; the next one is guaranteed
+    Lost,
+    /// Rate-up obtained through the guarantee after a loss
+    Guaranteed,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LimitedSixStar {
+    pub item_name: String,
+    pub item_id: Option<String>,
+    pub pulled_at: i64,
+    pub outcome: LimitedOutcome,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LimitedAnalysis {
+    pub banner_id: String,
+    /// Every item id that was on rate-up for this banner in the metadata
+    pub rate_up: Vec<String>,
+    pub wins: usize,
+    pub losses: usize,
+    pub guaranteed: usize,
+    /// The last 6-star was off-banner, so the next one is the rate-up
+    pub next_is_guaranteed: bool,
+    /// Oldest first
+    pub six_stars: Vec<LimitedSixStar>,
+}
+
+/// Classify 6-stars (oldest first) against the banner's rate-up windows.
+/// Returns the outcomes and whether the next 6-star is guaranteed.
+fn classify_limited<'a>(
+    six_stars: impl IntoIterator<Item = (Option<&'a str>, i64)>,
+    windows: &[crate::services::metadata::RateUpWindow],
+) -> (Vec<LimitedOutcome>, bool) {
+    let mut guaranteed = false;
+    let outcomes = six_stars
+        .into_iter()
+        .map(|(item_id, pulled_at)| {
+            let on_banner = item_id.is_some_and(|id| windows.iter().any(|w| w.is_rate_up(id, pulled_at)));
+            let outcome = match (on_banner, guaranteed) {
+                (true, false) => LimitedOutcome::Won,
+                (true, true) => LimitedOutcome::Guaranteed,
+                (false, _) => LimitedOutcome::Lost,
+            };
+            guaranteed = !on_banner;
+            outcome
+        })
+        .collect();
+    (outcomes, guaranteed)
+}
+
+/// 50/50 record for one limited banner, using the metadata's rate-up list for `banner_id`.
+#[tauri::command]
+pub async fn db_limited_analysis(
+    pool: State<'_, DbPool>,
+    uid: String,
+    banner_id: String,
+) -> Result<LimitedAnalysis, String> {
+    let exe_dir = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|d| d.to_path_buf()))
+        .ok_or("Cannot get exe directory")?;
+    let windows = crate::services::metadata::load_rate_ups(&exe_dir, &banner_id);
+    if windows.is_empty() {
+        return Err(format!("元数据中没有卡池 {banner_id} 的 UP 信息，请先更新元数据"));
+    }
+
+    let rows = sqlx::query(
+        "SELECT item_name, item_id, pulled_at FROM gacha_pulls
+         WHERE uid = ? AND banner_id = ? AND rarity = 6
+         ORDER BY CASE WHEN pulled_at < 1000000000000 THEN pulled_at ELSE pulled_at / 1000 END,
+                  CAST(seq_id AS INTEGER), id"
+    )
+    .bind(&uid)
+    .bind(&banner_id)
+    .fetch_all(pool.inner())
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let items: Vec<(String, Option<String>, i64)> = rows
+        .iter()
+        .map(|r| (r.get("item_name"), r.get("item_id"), r.get("pulled_at")))
+        .collect();
+    let (outcomes, next_is_guaranteed) =
+        classify_limited(items.iter().map(|(_, id, at)| (id.as_deref(), *at)), &windows);
+
+    let mut rate_up: Vec<String> = windows.iter().flat_map(|w| w.up.iter().cloned()).collect();
+    rate_up.sort();
+    rate_up.dedup();
+
+    let count = |o: LimitedOutcome| outcomes.iter().filter(|x| **x == o).count();
+    Ok(LimitedAnalysis {
+        banner_id,
+        rate_up,
+        wins: count(LimitedOutcome::Won),
+        losses: count(LimitedOutcome::Lost),
+        guaranteed: count(LimitedOutcome::Guaranteed),
+        next_is_guaranteed,
+        six_stars: items
+            .into_iter()
+            .zip(outcomes)
+            .map(|((item_name, item_id, pulled_at), outcome)| LimitedSixStar { item_name, item_id, pulled_at, outcome })
+            .collect(),
+    })
+}
+
+#[derive(Deserialize)]
+pub struct ApiGachaRecord {
+    pub name: String,
+    pub item_id: Option<String>,
+    pub rarity: i64,
+    pub pool_id: String,
+    pub pool_name: String,
+    pub seq_id: String,
+    pub pulled_at: i64,
+    pub pool_type: String,
+    pub is_free: bool,
+    pub is_new: bool,
+}
+
+/// Max seq_ids bound per `IN (...)` prefetch query in `db_save_gacha_records`.
+const SEQ_ID_PREFETCH_CHUNK: usize = 400;
+
+/// Saves at least this large are followed by a `wal_checkpoint(TRUNCATE)`.
+const WAL_CHECKPOINT_MIN_RECORDS: usize = 1000;
+
+/// Drop CSV-imported rows whose seq_id was synthesized (`csv-…`, see `services::import`) once
+/// a row from the API or a log covers the same pull, matched by `(pool_type, pulled_at, item_name)`.
+/// Runs after every save, so it applies whichever of the two arrived first.
+pub(crate) async fn drop_synthetic_duplicates(conn: &mut sqlx::SqliteConnection, uid: &str) -> Result<u64, String> {
+    sqlx::query(
+        "DELETE FROM gacha_pulls
+         WHERE uid = ?1 AND seq_id LIKE 'csv-%'
+           AND EXISTS (SELECT 1 FROM gacha_pulls r
+                       WHERE r.uid = ?1 AND r.pool_type = gacha_pulls.pool_type
+                         AND r.pulled_at = gacha_pulls.pulled_at AND r.item_name = gacha_pulls.item_name
+                         AND r.seq_id NOT LIKE 'csv-%' AND r.seq_id NOT LIKE 'manual\\_%' ESCAPE '\\')"
+    )
+    .bind(uid)
+    .execute(conn)
+    .await
+    .map(|r| r.rows_affected())
+    .map_err(|e| e.to_string())
+}
+
+/// Keep the WAL file from growing after big imports; failure here is harmless.
+pub(crate) async fn checkpoint_after_save(pool: &DbPool, record_count: usize) {
+    if record_count >= WAL_CHECKPOINT_MIN_RECORDS {
+        let _ = sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)").execute(pool).await;
+    }
+}
+
+#[tauri::command]
+pub async fn db_save_gacha_records(
+    pool: State<'_, DbPool>,
+    uid: String,
+    records: Vec<ApiGachaRecord>,
+    source: Option<String>,
+) -> Result<(), String> {
+    if records.is_empty() {
+        return Ok(());
+    }
+    // The frontend saves what it fetched with the account token unless told otherwise.
+    let source = match source.as_deref().map(str::trim) {
+        None | Some("") => SOURCE_TOKEN,
+        Some(s) => [SOURCE_TOKEN, SOURCE_LOG, SOURCE_IMPORT]
+            .into_iter()
+            .find(|known| *known == s)
+            .ok_or_else(|| format!("unsupported source: {s}"))?,
+    };
+    save_gacha_records(pool.inner(), &uid, records, source).await
+}
+
+async fn save_gacha_records(
+    pool: &DbPool,
+    uid: &str,
+    records: Vec<ApiGachaRecord>,
+    source: &str,
+) -> Result<(), String> {
+    let record_count = records.len();
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+
+    // We now rely on seq_id column for deduplication
+    // 1. Get existing seq_ids for this UID to filtering insesrts/updates
+    // Actually, `INSERT OR REPLACE` or `ON CONFLICT` strategy involves UNIQUE constraint on seq_id?
+    // We don't have a UNIQUE constraint on seq_id globally (or (uid, seq_id)).
+    // The current schema index is just INDEX.
+    // So we should check existence manually or use UPSERT with explicit check if we can add constraint.
+    // But we can't easily add constraint to existing table in SQLite without full copy-migration.
+    // So manual check is safer.
+
+    // Get existing seq_ids to avoid duplicates.
+    // Since we fetch records in batches, we can just query for existence of these specifc seq_ids?
+    // But API usually returns new pages.
+    // Let's optimize: query all seq_id for this user? Might be large.
+    // Better: For each record, try update, if not affected, insert.
+    // Or: Query existing seq_ids that match the input list.
+    
+    let incoming_seq_ids: Vec<String> = records.iter().map(|r| r.seq_id.clone()).collect();
+    // SQLite limits bound variables (999 on older builds), so prefetch in chunks.
+    // seq_id is only unique per pool_type, so the set is keyed by both.
+    let mut existing_seq_ids: std::collections::HashSet<(String, String)> = std::collections::HashSet::new();
+    for chunk in incoming_seq_ids.chunks(SEQ_ID_PREFETCH_CHUNK) {
+        let placeholders: Vec<_> = chunk.iter().map(|_| "?").collect();
+        let query = format!(
+            "SELECT seq_id, pool_type FROM gacha_pulls WHERE uid = ? AND seq_id IN ({})",
+            placeholders.join(",")
+        );
+        let mut q = sqlx::query(&query).bind(uid);
+        for sid in chunk {
+            q = q.bind(sid);
+        }
+
+        let rows = q.fetch_all(&mut *tx).await.map_err(|e| e.to_string())?;
+        for row in rows {
+            let seq_id: String = row.get("seq_id");
+            let pool_type: Option<String> = row.get("pool_type");
+            existing_seq_ids.insert((seq_id, pool_type.unwrap_or_default()));
+        }
+    }
+
+    // Actually, since we removed meta merging logic, we just want to ensure the record is up to date.
+    // `seq_id` is the unique key from API.
+    
+    for r in records {
+        // Rows the prefetch didn't see can go straight to INSERT.
+        let known = existing_seq_ids.contains(&(r.seq_id.clone(), r.pool_type.clone()));
+
+        // Try UPDATE first
+        // IMPORTANT: seq_id is only unique within the same pool_type, not globally!
+        // So we must include pool_type in the WHERE clause.
+        let affected = if known {
+            sqlx::query(
+                "UPDATE gacha_pulls SET 
+                    banner_id = ?, banner_name = ?, item_name = ?, item_id = ?, rarity = ?, pulled_at = ?, is_free = ?, is_new = ?,
+                    source = COALESCE(NULLIF(source, 'unknown'), ?)
+                 WHERE uid = ? AND seq_id = ? AND pool_type = ?"
+            )
+            .bind(&r.pool_id)
+            .bind(&r.pool_name)
+            .bind(&r.name)
+            .bind(&r.item_id)
+            .bind(r.rarity)
+            .bind(r.pulled_at)
+            .bind(r.is_free)
+            .bind(r.is_new)
+            .bind(source)
+            .bind(uid)
+            .bind(&r.seq_id)
+            .bind(&r.pool_type)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?
+            .rows_affected()
+        } else {
+            0
+        };
+        
+        if affected == 0 {
+            // INSERT
+            sqlx::query(
+                "INSERT INTO gacha_pulls (uid, banner_id, banner_name, item_name, item_id, rarity, pulled_at, seq_id, pool_type, is_free, is_new, source)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+            )
+            .bind(uid)
+            .bind(&r.pool_id)
+            .bind(&r.pool_name)
+            .bind(&r.name)
+            .bind(&r.item_id)
+            .bind(r.rarity)
+            .bind(r.pulled_at)
+            .bind(&r.seq_id)
+            .bind(&r.pool_type)
+            .bind(r.is_free)
+            .bind(r.is_new)
+            .bind(source)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+            // A repeat later in the same batch must update this row, not insert another.
+            existing_seq_ids.insert((r.seq_id, r.pool_type));
+        }
+    }
+
+    drop_synthetic_duplicates(&mut tx, uid).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    checkpoint_after_save(pool, record_count).await;
+    Ok(())
+}
+
+/// Stop points for incremental sync: seq_id of the newest stored record per pool, keyed by
+/// `pool_type` for character pools and by banner id for weapon banners (each is paged on its own).
+pub(crate) async fn incremental_cursors(pool: &DbPool, uid: &str) -> Result<HashMap<String, String>, String> {
+    // Ten-pulls share a timestamp, so ties go to the larger seq_id. Hand-entered rows and CSV
+    // rows with synthesized seq_ids never appear in the API, so they can't serve as a stop point.
+    let rows = sqlx::query_as::<_, (String, String)>(&format!(
+        "SELECT p.pool_key,
+                (SELECT seq_id FROM gacha_pulls
+                 WHERE uid = ?1 AND pool_type IS NOT NULL AND ({POOL_KEY_SQL}) = p.pool_key AND seq_id IS NOT NULL
+                   AND seq_id NOT LIKE 'manual\\_%' ESCAPE '\\' AND seq_id NOT LIKE 'csv-%'
+                 ORDER BY pulled_at DESC, CAST(seq_id AS INTEGER) DESC
+                 LIMIT 1) AS seq_id
+         FROM (SELECT DISTINCT {POOL_KEY_SQL} AS pool_key FROM gacha_pulls
+               WHERE uid = ?1 AND pool_type IS NOT NULL AND seq_id IS NOT NULL
+                 AND seq_id NOT LIKE 'manual\\_%' ESCAPE '\\' AND seq_id NOT LIKE 'csv-%') p"
+    ))
+    .bind(uid)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(rows.into_iter().collect())
+}
+
+/// The cursors the next incremental sync of `uid` will stop at, for debugging.
+#[tauri::command]
+pub async fn db_incremental_cursors(
+    pool: State<'_, DbPool>,
+    uid: String,
+) -> Result<HashMap<String, String>, String> {
+    incremental_cursors(pool.inner(), &uid).await
+}
+
+// ─────────────── Sync Log API ───────────────
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncLogEntry {
+    pub id: i64,
+    pub uid: Option<String>,
+    pub started_at: i64,
+    pub finished_at: i64,
+    pub mode: String,
+    pub count: Option<i64>,
+    pub success: bool,
+    pub error_message: Option<String>,
+}
+
+/// Most recent sync attempts, newest first; `uid = None` lists all accounts.
+#[tauri::command]
+pub async fn db_list_sync_log(
+    pool: State<'_, DbPool>,
+    uid: Option<String>,
+    limit: Option<i64>,
+) -> Result<Vec<SyncLogEntry>, String> {
+    sqlx::query_as::<_, SyncLogEntry>(
+        "SELECT id, uid, started_at, finished_at, mode, count, success, error_message
+         FROM sync_log
+         WHERE ?1 IS NULL OR uid = ?1
+         ORDER BY started_at DESC, id DESC
+         LIMIT ?2"
+    )
+    .bind(uid)
+    .bind(limit.unwrap_or(50))
+    .fetch_all(pool.inner())
+    .await
+    .map_err(|e| e.to_string())
+}
+
+// ─────────────── Account API ───────────────
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Account {
+    pub uid: String,
+    pub role_id: Option<String>,
     pub nick_name: Option<String>,
     pub server_id: Option<String>,
     pub channel_id: Option<i64>,
     pub updated_at: i64,
+    /// Unix seconds of the last successful gacha sync; `None` if never synced.
+    pub last_synced_at: Option<i64>,
+    /// User-chosen label, overriding the API nickname for display.
+    pub alias: Option<String>,
+    /// `alias` when set, otherwise `nick_name`.
+    pub display_name: Option<String>,
+    /// User-defined position; `None` sorts after positioned accounts by `updated_at`.
+    pub sort_order: Option<i64>,
+    /// Removed by the user but kept with its records; see [`db_delete_account`].
+    pub archived: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
@@ -458,13 +1483,23 @@ pub struct AccountWithTokens {
     pub user_token: Option<String>,
     pub oauth_token: Option<String>,
     pub u8_token: Option<String>,
+    /// Unix seconds when `u8_token` was issued, used to reuse it within its TTL.
+    pub u8_token_fetched_at: Option<i64>,
 }
 
 #[tauri::command]
-pub async fn db_list_accounts(pool: State<'_, DbPool>) -> Result<Vec<Account>, String> {
+pub async fn db_list_accounts(
+    pool: State<'_, DbPool>,
+    include_archived: Option<bool>,
+) -> Result<Vec<Account>, String> {
     sqlx::query_as::<_, Account>(
-        "SELECT uid, role_id, nick_name, server_id, channel_id, updated_at FROM accounts ORDER BY updated_at DESC"
+        "SELECT uid, role_id, nick_name, server_id, channel_id, updated_at, last_synced_at, alias,
+                COALESCE(NULLIF(alias, ''), nick_name) AS display_name, sort_order, archived
+         FROM accounts
+         WHERE ? OR archived = 0
+         ORDER BY sort_order IS NULL, sort_order ASC, updated_at DESC"
     )
+    .bind(include_archived.unwrap_or(false))
     .fetch_all(pool.inner())
     .await
     .map_err(|e| e.to_string())
@@ -493,6 +1528,7 @@ pub async fn db_upsert_account(
            user_token = CASE WHEN excluded.user_token != '' THEN excluded.user_token ELSE accounts.user_token END,
            oauth_token = CASE WHEN excluded.oauth_token != '' THEN excluded.oauth_token ELSE accounts.oauth_token END,
            u8_token = CASE WHEN excluded.u8_token != '' THEN excluded.u8_token ELSE accounts.u8_token END,
+           archived = 0,
            updated_at = unixepoch()"
     )
     .bind(uid)
@@ -509,23 +1545,268 @@ pub async fn db_upsert_account(
     Ok(())
 }
 
+/// Store the account order shown in the UI. Accounts missing from `uids` lose their position
+/// and sort after the listed ones.
+#[tauri::command]
+pub async fn db_reorder_accounts(pool: State<'_, DbPool>, uids: Vec<String>) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    sqlx::query("UPDATE accounts SET sort_order = NULL")
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    for (position, uid) in uids.iter().enumerate() {
+        sqlx::query("UPDATE accounts SET sort_order = ? WHERE uid = ?")
+            .bind(position as i64)
+            .bind(uid)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Set or clear (`None` / blank) the display alias of an account.
+#[tauri::command]
+pub async fn db_set_account_alias(
+    pool: State<'_, DbPool>,
+    uid: String,
+    alias: Option<String>,
+) -> Result<(), String> {
+    let alias = alias.map(|a| a.trim().to_string()).filter(|a| !a.is_empty());
+    let result = sqlx::query("UPDATE accounts SET alias = ? WHERE uid = ?")
+        .bind(alias)
+        .bind(&uid)
+        .execute(pool.inner())
+        .await
+        .map_err(|e| e.to_string())?;
+    if result.rows_affected() == 0 {
+        return Err(format!("账号不存在：{uid}"));
+    }
+    Ok(())
+}
+
+/// Archive an account: it is hidden from [`db_list_accounts`] and skipped by batch sync, but its
+/// records are kept. Adding the account again restores it; use [`db_purge_account`] to delete it.
 #[tauri::command]
 pub async fn db_delete_account(pool: State<'_, DbPool>, uid: String) -> Result<(), String> {
-    sqlx::query("DELETE FROM accounts WHERE uid = ?")
+    set_account_archived(pool.inner(), &uid, true).await
+}
+
+#[tauri::command]
+pub async fn db_restore_account(pool: State<'_, DbPool>, uid: String) -> Result<(), String> {
+    set_account_archived(pool.inner(), &uid, false).await
+}
+
+async fn set_account_archived(pool: &DbPool, uid: &str, archived: bool) -> Result<(), String> {
+    let result = sqlx::query("UPDATE accounts SET archived = ? WHERE uid = ?")
+        .bind(archived)
         .bind(uid)
-        .execute(pool.inner())
+        .execute(pool)
         .await
         .map_err(|e| e.to_string())?;
+    if result.rows_affected() == 0 {
+        return Err(format!("账号不存在：{uid}"));
+    }
     Ok(())
 }
 
+/// Permanently delete an account, and its records when `delete_records` is set.
+/// Returns the number of deleted records.
+#[tauri::command]
+pub async fn db_purge_account(
+    pool: State<'_, DbPool>,
+    uid: String,
+    delete_records: Option<bool>,
+) -> Result<u64, String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+
+    let deleted_records = if delete_records.unwrap_or(false) {
+        sqlx::query("DELETE FROM gacha_tags WHERE uid = ?")
+            .bind(&uid)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+        sqlx::query("DELETE FROM gacha_pulls WHERE uid = ?")
+            .bind(&uid)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?
+            .rows_affected()
+    } else {
+        0
+    };
+
+    sqlx::query("DELETE FROM accounts WHERE uid = ?")
+        .bind(&uid)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(deleted_records)
+}
+
+/// Delete every gacha record (and tag) of `uid`, keeping the account itself. Returns the number
+/// of deleted records.
+#[tauri::command]
+pub async fn db_delete_account_records(pool: State<'_, DbPool>, uid: String) -> Result<u64, String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    sqlx::query("DELETE FROM gacha_tags WHERE uid = ?")
+        .bind(&uid)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    let result = sqlx::query("DELETE FROM gacha_pulls WHERE uid = ?")
+        .bind(&uid)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(result.rows_affected())
+}
+
+
+/// Add a record by hand (pulls from before tracking or another device). The synthetic
+/// `manual_<uuid>` seq_id never collides with API seq_ids, so syncs neither update nor
+/// stop at it.
+#[tauri::command]
+pub async fn db_insert_manual_pull(
+    pool: State<'_, DbPool>,
+    uid: String,
+    pool_type: String,
+    item_name: String,
+    rarity: i64,
+    pulled_at: i64,
+    banner_name: String,
+) -> Result<GachaPull, String> {
+    let pool_type = pool_type.trim();
+    let item_name = item_name.trim();
+    if pool_type.is_empty() || item_name.is_empty() {
+        return Err("卡池类型与物品名不能为空".to_string());
+    }
+    if !(1..=6).contains(&rarity) {
+        return Err(format!("无效的星级: {rarity}"));
+    }
+    if pulled_at <= 0 {
+        return Err(format!("无效的时间戳: {pulled_at}"));
+    }
+    let banner_name = Some(banner_name.trim()).filter(|b| !b.is_empty()).unwrap_or(pool_type);
+    let seq_id = format!("manual_{}", uuid::Uuid::new_v4());
+
+    sqlx::query(
+        "INSERT INTO gacha_pulls (uid, banner_id, banner_name, item_name, rarity, pulled_at, seq_id, pool_type, is_free, is_new, source)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, 0, 0, ?)"
+    )
+    .bind(&uid)
+    .bind(pool_type)
+    .bind(banner_name)
+    .bind(item_name)
+    .bind(rarity)
+    .bind(pulled_at)
+    .bind(&seq_id)
+    .bind(pool_type)
+    .bind(SOURCE_MANUAL)
+    .execute(pool.inner())
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(GachaPull {
+        uid,
+        banner_id: pool_type.to_string(),
+        banner_name: banner_name.to_string(),
+        item_name: item_name.to_string(),
+        item_id: None,
+        rarity,
+        pulled_at,
+        seq_id: Some(seq_id),
+        pool_type: Some(pool_type.to_string()),
+        pulled_at_local: None,
+        source: SOURCE_MANUAL.to_string(),
+    })
+}
+
+/// Delete one pool's records and their tags (e.g. `E_CharacterGachaPoolType_Weapon`) so the
+/// next full sync rebuilds just that pool.
+#[tauri::command]
+pub async fn db_delete_pool_records(
+    pool: State<'_, DbPool>,
+    uid: String,
+    pool_type: String,
+) -> Result<u64, String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    sqlx::query("DELETE FROM gacha_tags WHERE uid = ? AND pool_type = ?")
+        .bind(&uid)
+        .bind(&pool_type)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    let result = sqlx::query("DELETE FROM gacha_pulls WHERE uid = ? AND pool_type = ?")
+        .bind(&uid)
+        .bind(&pool_type)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(result.rows_affected())
+}
+
+/// Re-resolve `item_name` from the local metadata for rows where it is empty or just the raw `item_id`.
+/// Returns the number of updated rows.
+#[tauri::command]
+pub async fn db_backfill_item_names(
+    pool: State<'_, DbPool>,
+    uid: String,
+    lang: Option<String>,
+) -> Result<u64, String> {
+    let exe_dir = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|d| d.to_path_buf()))
+        .ok_or("Cannot get exe directory")?;
+    let names = crate::services::metadata::load_item_names(&exe_dir, lang.as_deref().unwrap_or("zh-CN"));
+    if names.is_empty() {
+        return Err("本地元数据中没有可用的名称表".to_string());
+    }
+
+    let item_ids: Vec<String> = sqlx::query_scalar(
+        "SELECT DISTINCT item_id FROM gacha_pulls
+         WHERE uid = ? AND item_id IS NOT NULL AND item_id != ''
+           AND (TRIM(item_name) = '' OR item_name = item_id)"
+    )
+    .bind(&uid)
+    .fetch_all(pool.inner())
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let mut updated = 0u64;
+    for item_id in item_ids {
+        let Some(name) = names.get(&item_id) else {
+            continue;
+        };
+        updated += sqlx::query(
+            "UPDATE gacha_pulls SET item_name = ?
+             WHERE uid = ? AND item_id = ? AND (TRIM(item_name) = '' OR item_name = item_id)"
+        )
+        .bind(name)
+        .bind(&uid)
+        .bind(&item_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?
+        .rows_affected();
+    }
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(updated)
+}
+
 #[tauri::command]
 pub async fn db_get_account_tokens(
     pool: State<'_, DbPool>,
     uid: String,
 ) -> Result<Option<AccountWithTokens>, String> {
     let account = sqlx::query_as::<_, AccountWithTokens>(
-        "SELECT uid, role_id, nick_name, server_id, channel_id, user_token, oauth_token, u8_token FROM accounts WHERE uid = ? LIMIT 1"
+        "SELECT uid, role_id, nick_name, server_id, channel_id, user_token, oauth_token, u8_token, u8_token_fetched_at FROM accounts WHERE uid = ? LIMIT 1"
     )
     .bind(uid)
     .fetch_optional(pool.inner())
@@ -534,3 +1815,220 @@ pub async fn db_get_account_tokens(
 
     Ok(account)
 }
+
+/// In-memory pool with the production schema, for tests across modules.
+#[cfg(test)]
+pub(crate) async fn test_pool() -> DbPool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    migrate_schema(&pool).await.unwrap();
+    pool
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_split_and_normalize() {
+        assert_eq!(split_tags(None), Vec::<String>::new());
+        assert_eq!(split_tags(Some("favorite\u{1f}clutch".into())), vec!["favorite", "clutch"]);
+        assert_eq!(normalize_tag("  favorite "), Ok("favorite".to_string()));
+        assert!(normalize_tag("   ").is_err());
+        assert!(normalize_tag("a\u{1f}b").is_err());
+    }
+
+    #[test]
+    fn pulled_at_local_applies_offset_to_seconds_and_millis() {
+        let cn = chrono::FixedOffset::east_opt(8 * 3600).unwrap();
+        assert_eq!(pulled_at_local(1704067200, cn).as_deref(), Some("2024-01-01T08:00:00+08:00"));
+        assert_eq!(pulled_at_local(1704067200000, cn).as_deref(), Some("2024-01-01T08:00:00+08:00"));
+        assert_eq!(pulled_at_local(0, cn), None);
+    }
+
+    #[tokio::test]
+    async fn migrate_schema_keeps_new_account_columns_after_nullable_rebuild() {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        // Pre-release layout: token columns NOT NULL, none of the later columns
+        sqlx::query(
+            "CREATE TABLE accounts (uid TEXT PRIMARY KEY, user_token TEXT NOT NULL, oauth_token TEXT NOT NULL, u8_token TEXT NOT NULL,
+             created_at INTEGER NOT NULL DEFAULT (unixepoch()), updated_at INTEGER NOT NULL DEFAULT (unixepoch()))",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO accounts (uid, user_token, oauth_token, u8_token) VALUES ('u', 'a', 'b', 'c')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        migrate_schema(&pool).await.unwrap();
+
+        let (uid, archived): (String, bool) = sqlx::query_as(
+            "SELECT uid, archived FROM accounts WHERE last_synced_at IS NULL AND u8_token_fetched_at IS NULL
+               AND alias IS NULL AND sort_order IS NULL",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!((uid.as_str(), archived), ("u", false));
+        let notnull: i64 = sqlx::query_scalar("SELECT \"notnull\" FROM pragma_table_info('accounts') WHERE name = 'user_token'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(notnull, 0);
+    }
+
+    #[tokio::test]
+    async fn incremental_cursors_cover_pools_outside_recent_window() {
+        let pool = test_pool().await;
+
+        let mut tx = pool.begin().await.unwrap();
+        // A rarely used pool whose records are all older than the busy pool's last 1000.
+        for (seq, ts) in [("5", 100), ("6", 200), ("7", 200)] {
+            sqlx::query("INSERT INTO gacha_pulls (uid, banner_id, banner_name, item_name, rarity, pool_type, seq_id, pulled_at) VALUES ('u', 'b', 'b', 'x', 4, 'standard', ?, ?)")
+                .bind(seq).bind(ts).execute(&mut *tx).await.unwrap();
+        }
+        for n in 0..1200i64 {
+            sqlx::query("INSERT INTO gacha_pulls (uid, banner_id, banner_name, item_name, rarity, pool_type, seq_id, pulled_at) VALUES ('u', 'b', 'b', 'x', 4, 'special', ?, ?)")
+                .bind((1000 + n).to_string()).bind(1000 + n).execute(&mut *tx).await.unwrap();
+        }
+        sqlx::query("INSERT INTO gacha_pulls (uid, banner_id, banner_name, item_name, rarity, pool_type, seq_id, pulled_at) VALUES ('other', 'b', 'b', 'x', 4, 'beginner', '1', 9999)")
+            .execute(&mut *tx).await.unwrap();
+        sqlx::query("INSERT INTO gacha_pulls (uid, banner_id, banner_name, item_name, rarity, pool_type, seq_id, pulled_at) VALUES ('u', 'b', 'b', 'x', 4, 'standard', 'manual_x', 9999)")
+            .execute(&mut *tx).await.unwrap();
+        sqlx::query("INSERT INTO gacha_pulls (uid, banner_id, banner_name, item_name, rarity, pool_type, seq_id, pulled_at) VALUES ('u', 'b', 'b', 'x', 4, 'manual_only', 'manual_y', 9999)")
+            .execute(&mut *tx).await.unwrap();
+        sqlx::query("INSERT INTO gacha_pulls (uid, banner_id, banner_name, item_name, rarity, pool_type, seq_id, pulled_at) VALUES ('u', 'b', 'b', 'x', 4, 'standard', 'csv-9999-1', 9999)")
+            .execute(&mut *tx).await.unwrap();
+        // Weapon banners share one pool_type but are paged per banner.
+        for (banner, seq, ts) in [("weapon_a", "50", 300), ("weapon_a", "51", 400), ("weapon_b", "60", 350)] {
+            sqlx::query("INSERT INTO gacha_pulls (uid, banner_id, banner_name, item_name, rarity, pool_type, seq_id, pulled_at) VALUES ('u', ?, 'w', 'x', 4, 'E_CharacterGachaPoolType_Weapon', ?, ?)")
+                .bind(banner).bind(seq).bind(ts).execute(&mut *tx).await.unwrap();
+        }
+        tx.commit().await.unwrap();
+
+        let cursors = incremental_cursors(&pool, "u").await.unwrap();
+        assert_eq!(cursors.len(), 4);
+        assert_eq!(cursors.get("weapon_a").map(String::as_str), Some("51"));
+        assert_eq!(cursors.get("weapon_b").map(String::as_str), Some("60"));
+        assert_eq!(cursors.get("special").map(String::as_str), Some("2199"));
+        assert_eq!(cursors.get("standard").map(String::as_str), Some("7"));
+    }
+
+    #[test]
+    fn build_report_pools_computes_pity_per_pool() {
+        let pool_row = |key: &str, total| ReportPoolRow {
+            pool_key: key.to_owned(),
+            pool_type: Some(key.to_owned()),
+            banner_name: key.to_owned(),
+            total,
+            paid: total,
+        };
+        let six = |key: &str, n| ReportSixStarRow {
+            pool_key: key.to_owned(),
+            item_name: format!("{key}-{n}"),
+            item_id: None,
+            banner_name: key.to_owned(),
+            pulled_at: n,
+            n,
+        };
+        let pools = build_report_pools(
+            vec![pool_row("special", 100), pool_row("standard", 30)],
+            vec![six("special", 60), six("special", 75), six("standard", 30)],
+        );
+
+        let pity: Vec<i64> = pools[0].six_stars.iter().map(|s| s.pity).collect();
+        assert_eq!(pity, [60, 15]);
+        assert_eq!(pools[0].current_pity, 25);
+        assert_eq!(pools[1].six_stars[0].pity, 30);
+        assert_eq!(pools[1].current_pity, 0);
+    }
+
+    #[test]
+    fn classify_limited_tracks_guarantee() {
+        let windows: Vec<crate::services::metadata::RateUpWindow> = serde_json::from_value(serde_json::json!([
+            { "poolId": "special_1", "up": ["char_up"], "start_time": 1000, "end_time": 2000 }
+        ]))
+        .unwrap();
+        let (outcomes, next) = classify_limited(
+            [
+                (Some("char_up"), 1100),
+                (Some("char_std"), 1200),
+                (Some("char_up"), 1300),
+                (Some("char_up"), 2500), // outside the window
+            ],
+            &windows,
+        );
+        assert_eq!(
+            outcomes,
+            [LimitedOutcome::Won, LimitedOutcome::Lost, LimitedOutcome::Guaranteed, LimitedOutcome::Lost]
+        );
+        assert!(next);
+    }
+
+    #[test]
+    fn escape_like_escapes_wildcards() {
+        assert_eq!(escape_like("Endministrator"), "Endministrator");
+        assert_eq!(escape_like("100%_a\\b"), "100\\%\\_a\\\\b");
+    }
+
+    #[tokio::test]
+    async fn save_gacha_records_collapses_duplicates_within_a_batch() {
+        let pool = test_pool().await;
+        let record = |seq_id: &str, pool_type: &str, name: &str| ApiGachaRecord {
+            name: name.to_owned(),
+            item_id: None,
+            rarity: 5,
+            pool_id: "pool_a".to_owned(),
+            pool_name: "Pool A".to_owned(),
+            seq_id: seq_id.to_owned(),
+            pulled_at: 1_700_000_000,
+            pool_type: pool_type.to_owned(),
+            is_free: false,
+            is_new: false,
+        };
+        let batch = vec![
+            record("1", "special", "A"),
+            record("2", "special", "B"),
+            record("1", "special", "A2"),
+            record("1", "standard", "C"),
+        ];
+        save_gacha_records(&pool, "u", batch, SOURCE_TOKEN).await.unwrap();
+
+        let rows: Vec<(String, String, String)> =
+            sqlx::query_as("SELECT seq_id, pool_type, item_name FROM gacha_pulls ORDER BY pool_type, seq_id")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        let expected = [("1", "special", "A2"), ("2", "special", "B"), ("1", "standard", "C")]
+            .map(|(a, b, c)| (a.to_owned(), b.to_owned(), c.to_owned()));
+        assert_eq!(rows, expected);
+    }
+
+    #[tokio::test]
+    async fn search_gacha_matches_wildcards_literally() {
+        let pool = test_pool().await;
+        for (n, name) in ["Ember_100%", "Ember-100x", "ember_100% Mk2"].iter().enumerate() {
+            sqlx::query("INSERT INTO gacha_pulls (uid, banner_id, banner_name, item_name, rarity, pulled_at) VALUES ('u', 'b', 'b', ?, 6, ?)")
+                .bind(name)
+                .bind(n as i64)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        let names = |rows: Vec<GachaPull>| rows.into_iter().map(|r| r.item_name).collect::<Vec<_>>();
+        let found = search_gacha(&pool, "u", "EMBER_100%", 10).await.unwrap();
+        assert_eq!(names(found), vec!["ember_100% Mk2", "Ember_100%"]);
+        assert!(search_gacha(&pool, "other", "Ember", 10).await.unwrap().is_empty());
+        assert!(search_gacha(&pool, "u", "  ", 10).await.unwrap().is_empty());
+    }
+}
@@ -1,7 +1,7 @@
 use serde::Serialize;
 use serde_json::Value;
 
-use super::utils::{json_str, json_i64};
+use super::utils::{json_str, json_i64, parse_oauth_token, redact, redact_json};
 
 macro_rules! log_dev {
     ($($arg:tt)*) => {
@@ -172,7 +172,11 @@ fn extract_binding_info(binding_list_json: &Value) -> Vec<BindingInfo> {
 }
 
 #[tauri::command]
-pub async fn hg_exchange_user_token(token: String, provider: Option<String>) -> Result<HgExchangeResult, String> {
+pub async fn hg_exchange_user_token(
+    client: tauri::State<'_, reqwest::Client>,
+    token: String,
+    provider: Option<String>,
+) -> Result<HgExchangeResult, String> {
     let token = token.trim();
     log_dev!("[hg-exchange] called with token len={}", token.len());
 
@@ -182,11 +186,6 @@ pub async fn hg_exchange_user_token(token: String, provider: Option<String>) ->
 
     let provider = normalize_provider(provider)?;
 
-    let client = reqwest::Client::builder()
-        .user_agent("endfield-cat")
-        .build()
-        .map_err(|e| e.to_string())?;
-
     let grant_json = client
         .post(format!("https://as.{provider}.com/user/oauth2/v2/grant"))
         .json(&serde_json::json!({
@@ -211,18 +210,15 @@ pub async fn hg_exchange_user_token(token: String, provider: Option<String>) ->
             .unwrap_or("OAuth 换取失败");
         log_dev!(
             "[hg-exchange] grant failed code={} msg={} body={:?}",
-            code, msg, grant_json
+            code, msg, redact_json(&grant_json)
         );
         return Err(msg.to_owned());
     }
 
-    let oauth_token = json_str(&grant_json, "/data/token")
-        .or_else(|| json_str(&grant_json, "/token"))
-        .unwrap_or_default();
-    if oauth_token.trim().is_empty() {
-        log_dev!("[hg-exchange] oauth_token missing in grant body {:?}", grant_json);
+    let Some(oauth_token) = parse_oauth_token(&grant_json) else {
+        log_dev!("[hg-exchange] oauth_token missing in grant body {:?}", redact_json(&grant_json));
         return Err("OAuth 响应缺少 token".to_owned());
-    }
+    };
     log_dev!(
         "[hg-exchange] oauth_token len={} uids? pending binding_list",
         oauth_token.len()
@@ -238,7 +234,11 @@ pub async fn hg_exchange_user_token(token: String, provider: Option<String>) ->
         .await
         .map_err(|e| e.to_string())?;
     
-    log_dev!("[hg-exchange] binding_list response: {:?}", binding_json);
+    log_dev!(
+        "[hg-exchange] binding_list response status={:?} msg={:?}",
+        json_i64(&binding_json, "status"),
+        binding_json.get("msg").and_then(|v| v.as_str())
+    );
 
     let status = json_i64(&binding_json, "status").unwrap_or(-1);
     if status != 0 {
@@ -274,8 +274,13 @@ pub async fn hg_exchange_user_token(token: String, provider: Option<String>) ->
 }
 
 #[tauri::command]
-pub async fn hg_u8_token_by_uid(uid: String, oauth_token: String, provider: Option<String>) -> Result<String, String> {
-    log_dev!("[hg-u8] called with uid={}, oauth_token len={}", uid, oauth_token.len());
+pub async fn hg_u8_token_by_uid(
+    client: tauri::State<'_, reqwest::Client>,
+    uid: String,
+    oauth_token: String,
+    provider: Option<String>,
+) -> Result<String, String> {
+    log_dev!("[hg-u8] called with uid={}, oauth_token len={}", redact(&uid), oauth_token.len());
     
     if uid.trim().is_empty() {
         return Err("missing uid".to_owned());
@@ -286,16 +291,10 @@ pub async fn hg_u8_token_by_uid(uid: String, oauth_token: String, provider: Opti
 
     let provider = normalize_provider(provider)?;
 
-    let client = reqwest::Client::builder()
-        .user_agent("endfield-cat")
-        .build()
-        .map_err(|e| e.to_string())?;
-
     let request_body = serde_json::json!({
         "uid": uid,
         "token": oauth_token,
     });
-    log_dev!("[hg-u8] request body: {:?}", request_body);
 
     let u8_json = client
         .post(format!("https://binding-api-account-prod.{provider}.com/account/binding/v1/u8_token_by_uid"))
@@ -307,7 +306,7 @@ pub async fn hg_u8_token_by_uid(uid: String, oauth_token: String, provider: Opti
         .await
         .map_err(|e| e.to_string())?;
 
-    log_dev!("[hg-u8] response: {:?}", u8_json);
+    log_dev!("[hg-u8] response: {:?}", redact_json(&u8_json));
 
     let status = json_i64(&u8_json, "status").unwrap_or(-1);
     if status != 0 {
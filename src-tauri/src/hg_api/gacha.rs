@@ -1,6 +1,7 @@
 use serde::Serialize;
 use serde_json::Value;
-use super::utils::json_i64;
+use tauri::Emitter;
+use super::utils::{json_i64, read_json, signed_query};
 
 macro_rules! log_dev {
     ($($arg:tt)*) => {
@@ -19,6 +20,55 @@ fn normalize_provider(provider: Option<String>) -> Result<String, String> {
     }
 }
 
+/// Default cap on records fetched per pool, protecting against a runaway pagination loop.
+pub const DEFAULT_MAX_RECORDS: usize = 10000;
+
+/// Default pause between record pages, overridable via config `gachaRequestDelayMs`.
+pub const DEFAULT_REQUEST_DELAY_MS: u64 = 100;
+const MAX_REQUEST_DELAY_MS: u64 = 2000;
+
+/// Clamp a configured delay into 0–2000ms; a missing value uses the default.
+fn clamp_request_delay(ms: Option<i64>) -> std::time::Duration {
+    let ms = match ms {
+        Some(ms) => ms.clamp(0, MAX_REQUEST_DELAY_MS as i64) as u64,
+        None => DEFAULT_REQUEST_DELAY_MS,
+    };
+    std::time::Duration::from_millis(ms)
+}
+
+/// Delay between record pages, read from config `gachaRequestDelayMs`.
+pub fn request_delay_from_config() -> std::time::Duration {
+    let ms = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.to_path_buf()))
+        .and_then(|dir| crate::services::config::read_config_field::<i64>(&dir, "gachaRequestDelayMs"));
+    clamp_request_delay(ms)
+}
+
+/// Records fetched from one pool; `truncated` is set when `max_records` stopped the fetch
+/// before the server ran out of pages.
+#[derive(Serialize, Default)]
+pub struct GachaFetchResult {
+    pub records: Vec<GachaRecord>,
+    pub truncated: bool,
+}
+
+/// Apply `max_records` to the records collected so far. Returns `Some(truncated)` when the
+/// fetch loop should stop.
+pub fn apply_record_cap(
+    records: &mut Vec<GachaRecord>,
+    max_records: Option<usize>,
+    has_more: Option<bool>,
+) -> Option<bool> {
+    let max = max_records?;
+    if records.len() < max {
+        return None;
+    }
+    let truncated = records.len() > max || has_more != Some(false);
+    records.truncate(max);
+    Some(truncated)
+}
+
 #[derive(Serialize, Clone)]
 pub struct GachaRecord {
     pub name: String,
@@ -33,52 +83,226 @@ pub struct GachaRecord {
     pub is_new: bool,
 }
 
-#[tauri::command]
-pub async fn hg_fetch_char_records(
-    client: tauri::State<'_, reqwest::Client>,
-    token: String,
-    server_id: String,
-    pool_type: String,
-    last_seq_id_stop: Option<String>,
-    provider: Option<String>,
-) -> Result<Vec<GachaRecord>, String> {
-    log_dev!("[hg-gacha] fetching char records: pool_type={}, stop_at={:?}", pool_type, last_seq_id_stop);
+/// Parse one `/api/record/char` list item.
+pub fn char_record_from_item(item: &Value, pool_type: &str) -> GachaRecord {
+    GachaRecord {
+        name: item
+            .get("charName")
+            .or(item.get("charId"))
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_owned(),
+        item_id: item
+            .get("charId")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_owned(),
+        rarity: item
+            .get("rarity")
+            .and_then(|v| v.as_i64().or_else(|| v.as_str().and_then(|s| s.parse().ok())))
+            .unwrap_or(0),
+        pool_id: item
+            .get("poolId")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_owned(),
+        pool_name: item
+            .get("poolName")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_owned(),
+        seq_id: item
+            .get("seqId")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_owned(),
+        pulled_at: item
+            .get("gachaTs")
+            .and_then(|v| v.as_i64().or_else(|| v.as_str().and_then(|s| s.parse().ok())))
+            .unwrap_or(0),
+        pool_type: pool_type.to_owned(),
+        is_free: item.get("isFree").and_then(|v| v.as_bool()).unwrap_or(false),
+        is_new: item.get("isNew").and_then(|v| v.as_bool()).unwrap_or(false),
+    }
+}
 
-    let provider = normalize_provider(provider)?;
-    let url = format!("https://ef-webview.{provider}.com/api/record/char");
-    let mut all_records = Vec::new();
-    let mut next_seq_id: Option<String> = None;
+/// Parse one `/api/record/weapon` list item. `pool_id` / `pool_name` come from the pool list
+/// and fill in when the item leaves them out (weapon items often have an empty `poolName`).
+pub fn weapon_record_from_item(item: &Value, pool_id: &str, pool_name: &str) -> GachaRecord {
+    GachaRecord {
+        name: item
+            .get("weaponName")
+            .or(item.get("weaponId"))
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_owned(),
+        item_id: item
+            .get("weaponId")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_owned(),
+        rarity: item
+            .get("rarity")
+            .and_then(|v| v.as_i64().or_else(|| v.as_str().and_then(|s| s.parse().ok())))
+            .unwrap_or(0),
+        pool_id: item
+            .get("poolId")
+            .and_then(|v| v.as_str())
+            .unwrap_or(pool_id)
+            .to_owned(),
+        pool_name: item
+            .get("poolName")
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.is_empty())
+            .unwrap_or(pool_name)
+            .to_owned(),
+        seq_id: item
+            .get("seqId")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_owned(),
+        pulled_at: item
+            .get("gachaTs")
+            .and_then(|v| v.as_i64().or_else(|| v.as_str().and_then(|s| s.parse().ok())))
+            .unwrap_or(0),
+        pool_type: "E_CharacterGachaPoolType_Weapon".to_string(),
+        is_free: item.get("isFree").and_then(|v| v.as_bool()).unwrap_or(false),
+        is_new: item.get("isNew").and_then(|v| v.as_bool()).unwrap_or(false),
+    }
+}
 
-    'outer: loop {
-        let mut params = vec![
-            ("token", token.as_str()),
-            ("server_id", server_id.as_str()),
-            ("lang", "zh-cn"),
-            ("pool_type", pool_type.as_str()),
-        ];
-        if let Some(seq) = &next_seq_id {
-            params.push(("seq_id", seq));
-        }
+const RATE_LIMIT_RETRIES: u32 = 3;
+const DEFAULT_RETRY_AFTER_SECS: u64 = 2;
+const MAX_RETRY_AFTER_SECS: u64 = 30;
+
+/// Parse a `Retry-After` header given in seconds, capped so a bad value can't stall a sync.
+/// HTTP-date values are not used by this API and fall back to the default.
+fn parse_retry_after(header: Option<&str>) -> std::time::Duration {
+    let secs = header
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .unwrap_or(DEFAULT_RETRY_AFTER_SECS)
+        .min(MAX_RETRY_AFTER_SECS);
+    std::time::Duration::from_secs(secs)
+}
 
-        log_dev!("[hg-gacha] fetching page seq_id={:?}", next_seq_id);
+/// The API reports rarity as 3–6; a `0` means the field was missing and the record would
+/// skew stats, so such records are dropped instead of stored.
+pub fn has_valid_rarity(record: &GachaRecord) -> bool {
+    record.rarity > 0
+}
+
+/// Business `code` the record endpoints answer with (inside an HTTP 200) when throttling
+const THROTTLED_CODE: i64 = 429;
+
+/// Whether a 200 body is a throttling response rather than a real error: the throttling
+/// `code`, or a `msg` saying requests are too frequent.
+fn is_throttled_body(code: i64, json: &Value) -> bool {
+    code == THROTTLED_CODE
+        || json
+            .get("msg")
+            .and_then(|v| v.as_str())
+            .is_some_and(|msg| msg.contains("频繁"))
+}
 
-        let json = client
-            .get(&url)
-            .query(&params)
+/// GET one page of a record endpoint and check its business code.
+/// HTTP 429 and a throttling business code are retried up to `RATE_LIMIT_RETRIES` times,
+/// honouring `Retry-After` when the response carries one.
+pub async fn fetch_record_page(
+    client: &reqwest::Client,
+    url: &str,
+    params: &[(&str, &str)],
+    fallback_msg: &str,
+) -> Result<Value, String> {
+    let mut attempt = 0;
+    loop {
+        let res = client
+            .get(url)
+            .query(&signed_query(params))
             .send()
             .await
-            .map_err(|e| e.to_string())?
-            .json::<Value>()
-            .await
             .map_err(|e| e.to_string())?;
 
-        let code = json_i64(&json, "code")
-            .or_else(|| json_i64(&json, "status"))
-            .unwrap_or(-1);
-        if code != 0 {
-            let msg = json.get("msg").and_then(|v| v.as_str()).unwrap_or("获取寻访记录失败");
-            return Err(msg.to_owned());
+        let wait = parse_retry_after(
+            res.headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok()),
+        );
+        if res.status() != reqwest::StatusCode::TOO_MANY_REQUESTS {
+            let json = read_json(res).await?;
+            let code = json_i64(&json, "code")
+                .or_else(|| json_i64(&json, "status"))
+                .unwrap_or(-1);
+            if code == 0 {
+                return Ok(json);
+            }
+            if !is_throttled_body(code, &json) {
+                let msg = json.get("msg").and_then(|v| v.as_str()).unwrap_or(fallback_msg);
+                return Err(msg.to_owned());
+            }
+        }
+
+        if attempt >= RATE_LIMIT_RETRIES {
+            return Err("请求过于频繁，请稍后再试".to_owned());
         }
+        attempt += 1;
+        log_dev!("[hg-gacha] rate limited, retry {}/{} in {:?}", attempt, RATE_LIMIT_RETRIES, wait);
+        tokio::time::sleep(wait).await;
+    }
+}
+
+/// Payload of the `gacha-page-progress` event, emitted after each fetched page.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GachaPageProgress {
+    /// Character pool type, or the weapon pool id
+    pub pool_type: String,
+    pub records_so_far: usize,
+    /// 1-based page number
+    pub page: usize,
+}
+
+/// Called with `(page, records_so_far)` after each page of [`paginate_records`].
+pub type PageProgressFn<'a> = &'a (dyn Fn(usize, usize) + Send + Sync);
+
+/// Emit `gacha-page-progress` for `pool_type`; used by the fetch commands.
+fn emit_page_progress(app: &tauri::AppHandle, pool_type: &str) -> impl Fn(usize, usize) + Send + Sync {
+    let app = app.clone();
+    let pool_type = pool_type.to_owned();
+    move |page, records_so_far| {
+        let _ = app.emit(
+            "gacha-page-progress",
+            GachaPageProgress { pool_type: pool_type.clone(), records_so_far, page },
+        );
+    }
+}
+
+/// Walk the `seq_id` cursor pagination shared by the char and weapon record endpoints.
+///
+/// `fetch_page` receives the cursor (`None` for the first page) and returns the page JSON.
+/// Stops at `last_seq_id_stop`, when `hasMore` is false, when `max_records` is reached,
+/// or when the server hands back a page whose cursor does not advance.
+/// `on_page` is told the page number and record count after each page.
+pub async fn paginate_records<F, Fut, P>(
+    mut fetch_page: F,
+    parse_item: P,
+    last_seq_id_stop: Option<&str>,
+    max_records: Option<usize>,
+    page_delay: std::time::Duration,
+    on_page: Option<PageProgressFn<'_>>,
+) -> Result<GachaFetchResult, String>
+where
+    F: FnMut(Option<String>) -> Fut,
+    Fut: std::future::Future<Output = Result<Value, String>>,
+    P: Fn(&Value) -> GachaRecord,
+{
+    let mut all_records = Vec::new();
+    let mut next_seq_id: Option<String> = None;
+    let mut truncated = false;
+    let mut page_no = 0;
+
+    'outer: loop {
+        log_dev!("[hg-gacha] fetching page seq_id={:?}", next_seq_id);
+        let json = fetch_page(next_seq_id.clone()).await?;
 
         let list = json.pointer("/data/list").and_then(|v| v.as_array());
         let Some(list) = list else {
@@ -88,54 +312,104 @@ pub async fn hg_fetch_char_records(
             break;
         }
 
-        for item in list {
-            let seq_id = item.get("seqId").and_then(|v| v.as_str()).unwrap_or("").to_owned();
-            
+        let page: Vec<GachaRecord> = list.iter().map(&parse_item).collect();
+
+        // A page ending on the cursor we just sent means the server is repeating itself.
+        let page_cursor = page.last().map(|r| r.seq_id.as_str()).unwrap_or("");
+        if page_cursor.is_empty() || next_seq_id.as_deref() == Some(page_cursor) {
+            log_dev!("[hg-gacha] seq_id cursor did not advance ({:?}), stopping", next_seq_id);
+            break;
+        }
+
+        let page_cursor = page_cursor.to_owned();
+        page_no += 1;
+        for record in page {
             // Incremental stop check
-            if let Some(stop_id) = &last_seq_id_stop {
-                if &seq_id == stop_id {
+            if let Some(stop_id) = last_seq_id_stop {
+                if record.seq_id == stop_id {
                     log_dev!("[hg-gacha] reached last_seq_id={}, stopping", stop_id);
+                    if let Some(on_page) = on_page {
+                        on_page(page_no, all_records.len());
+                    }
                     break 'outer;
                 }
             }
-
-            let record = GachaRecord {
-                name: item.get("charName").or(item.get("charId")).and_then(|v| v.as_str()).unwrap_or("").to_owned(),
-                item_id: item.get("charId").and_then(|v| v.as_str()).unwrap_or("").to_owned(),
-                rarity: item.get("rarity").and_then(|v| v.as_i64().or_else(|| v.as_str().and_then(|s| s.parse().ok()))).unwrap_or(0),
-                pool_id: item.get("poolId").and_then(|v| v.as_str()).unwrap_or("").to_owned(),
-                pool_name: item.get("poolName").and_then(|v| v.as_str()).unwrap_or("").to_owned(),
-                seq_id,
-                pulled_at: item.get("gachaTs").and_then(|v| v.as_i64().or_else(|| v.as_str().and_then(|s| s.parse().ok()))).unwrap_or(0),
-                pool_type: pool_type.clone(),
-                is_free: item.get("isFree").and_then(|v| v.as_bool()).unwrap_or(false),
-                is_new: item.get("isNew").and_then(|v| v.as_bool()).unwrap_or(false),
-            };
+            if !has_valid_rarity(&record) {
+                log_dev!("[hg-gacha] dropping seq_id={} with missing rarity", record.seq_id);
+                continue;
+            }
             all_records.push(record);
         }
 
-        if let Some(last) = all_records.last() {
-            next_seq_id = Some(last.seq_id.clone());
-        } else {
+        next_seq_id = Some(page_cursor);
+
+        let has_more = json.pointer("/data/hasMore").and_then(|v| v.as_bool());
+        let cap = apply_record_cap(&mut all_records, max_records, has_more);
+        if let Some(on_page) = on_page {
+            on_page(page_no, all_records.len());
+        }
+        if let Some(cut) = cap {
+            truncated = cut;
+            if cut {
+                log_dev!("[hg-gacha] reached max_records={:?}, result truncated", max_records);
+            }
             break;
         }
 
-        if all_records.len() > 10000 {
-            log_dev!("[hg-gacha] too many records, breaking");
+        if has_more == Some(false) {
             break;
         }
-        
-        if let Some(has_more) = json.pointer("/data/hasMore").and_then(|v| v.as_bool()) {
-            if !has_more {
-                break;
-            }
-        }
-        
-        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        tokio::time::sleep(page_delay).await;
     }
 
-    log_dev!("[hg-gacha] fetched total {} char records", all_records.len());
-    Ok(all_records)
+    Ok(GachaFetchResult { records: all_records, truncated })
+}
+
+/// `max_records = None` fetches the whole history; syncs pass `Some(DEFAULT_MAX_RECORDS)`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn hg_fetch_char_records(
+    app: tauri::AppHandle,
+    client: tauri::State<'_, reqwest::Client>,
+    token: String,
+    server_id: String,
+    pool_type: String,
+    last_seq_id_stop: Option<String>,
+    provider: Option<String>,
+    max_records: Option<usize>,
+) -> Result<GachaFetchResult, String> {
+    log_dev!("[hg-gacha] fetching char records: pool_type={}, stop_at={:?}", pool_type, last_seq_id_stop);
+
+    let provider = normalize_provider(provider)?;
+    let url = format!("https://ef-webview.{provider}.com/api/record/char");
+    let (client, url, token, server_id, pool_type) =
+        (client.inner(), url.as_str(), token.as_str(), server_id.as_str(), pool_type.as_str());
+    let on_page = emit_page_progress(&app, pool_type);
+
+    let result = paginate_records(
+        |seq_id| async move {
+            let mut params = vec![
+                ("token", token),
+                ("server_id", server_id),
+                ("lang", "zh-cn"),
+                ("pool_type", pool_type),
+            ];
+            if let Some(seq) = seq_id.as_deref() {
+                params.push(("seq_id", seq));
+            }
+            fetch_record_page(client, url, &params, "获取寻访记录失败").await
+        },
+        |item| char_record_from_item(item, pool_type),
+        last_seq_id_stop.as_deref(),
+        max_records,
+        request_delay_from_config(),
+        Some(&on_page),
+    )
+    .await?;
+
+    log_dev!("[hg-gacha] fetched total {} char records", result.records.len());
+    Ok(result)
 }
 
 #[derive(Serialize)]
@@ -161,15 +435,13 @@ pub async fn hg_fetch_weapon_pools(
         ("lang", "zh-cn".to_string()),
     ];
 
-    let json = client
+    let res = client
         .get(&url)
-        .query(&params)
+        .query(&signed_query(&params))
         .send()
         .await
-        .map_err(|e| e.to_string())?
-        .json::<Value>()
-        .await
         .map_err(|e| e.to_string())?;
+    let json = read_json(res).await?;
 
     let code = json_i64(&json, "code")
         .or_else(|| json_i64(&json, "status"))
@@ -191,106 +463,227 @@ pub async fn hg_fetch_weapon_pools(
     Ok(pools)
 }
 
+/// `max_records = None` fetches the whole history; syncs pass `Some(DEFAULT_MAX_RECORDS)`.
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn hg_fetch_weapon_records(
+    app: tauri::AppHandle,
     client: tauri::State<'_, reqwest::Client>,
     token: String,
     server_id: String,
     pool_id: String,
+    pool_name: Option<String>,
     last_seq_id_stop: Option<String>,
     provider: Option<String>,
-) -> Result<Vec<GachaRecord>, String> {
+    max_records: Option<usize>,
+) -> Result<GachaFetchResult, String> {
     log_dev!("[hg-gacha] fetching weapon records: pool_id={}, stop_at={:?}", pool_id, last_seq_id_stop);
 
     let provider = normalize_provider(provider)?;
     let url = format!("https://ef-webview.{provider}.com/api/record/weapon");
-    let mut all_records = Vec::new();
-    let mut next_seq_id: Option<String> = None;
+    let pool_name = pool_name.unwrap_or_default();
+    let (client, url, token, server_id, pool_id, pool_name) = (
+        client.inner(),
+        url.as_str(),
+        token.as_str(),
+        server_id.as_str(),
+        pool_id.as_str(),
+        pool_name.as_str(),
+    );
+    let on_page = emit_page_progress(&app, pool_id);
+
+    let result = paginate_records(
+        |seq_id| async move {
+            let mut params = vec![
+                ("token", token),
+                ("server_id", server_id),
+                ("pool_id", pool_id),
+                ("lang", "zh-cn"),
+            ];
+            if let Some(seq) = seq_id.as_deref() {
+                params.push(("seq_id", seq));
+            }
+            fetch_record_page(client, url, &params, "获取武器记录失败").await
+        },
+        |item| weapon_record_from_item(item, pool_id, pool_name),
+        last_seq_id_stop.as_deref(),
+        max_records,
+        request_delay_from_config(),
+        Some(&on_page),
+    )
+    .await?;
+
+    log_dev!("[hg-gacha] fetched total {} weapon records", result.records.len());
+    Ok(result)
+}
 
-    'outer: loop {
-        let mut params = vec![
-            ("token", token.as_str()),
-            ("server_id", server_id.as_str()),
-            ("pool_id", pool_id.as_str()),
-            ("lang", "zh-cn"),
-        ];
-        if let Some(seq) = &next_seq_id {
-            params.push(("seq_id", seq));
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records(n: usize) -> Vec<GachaRecord> {
+        (0..n)
+            .map(|i| GachaRecord {
+                name: String::new(),
+                item_id: String::new(),
+                rarity: 4,
+                pool_id: String::new(),
+                pool_name: String::new(),
+                seq_id: i.to_string(),
+                pulled_at: 0,
+                pool_type: String::new(),
+                is_free: false,
+                is_new: false,
+            })
+            .collect()
+    }
 
-        log_dev!("[hg-gacha] fetching weapon page seq_id={:?}", next_seq_id);
+    #[test]
+    fn apply_record_cap_flags_truncation() {
+        let mut recs = records(5);
+        assert_eq!(apply_record_cap(&mut recs, None, Some(true)), None);
+        assert_eq!(apply_record_cap(&mut recs, Some(10), Some(true)), None);
 
-        let json = client
-            .get(&url)
-            .query(&params)
-            .send()
-            .await
-            .map_err(|e| e.to_string())?
-            .json::<Value>()
-            .await
-            .map_err(|e| e.to_string())?;
+        assert_eq!(apply_record_cap(&mut recs, Some(3), Some(false)), Some(true));
+        assert_eq!(recs.len(), 3);
 
-        let code = json_i64(&json, "code")
-            .or_else(|| json_i64(&json, "status"))
-            .unwrap_or(-1);
-        if code != 0 {
-            let msg = json.get("msg").and_then(|v| v.as_str()).unwrap_or("获取武器记录失败");
-            return Err(msg.to_owned());
-        }
+        // Exactly at the cap on the last page is not a truncation.
+        let mut recs = records(3);
+        assert_eq!(apply_record_cap(&mut recs, Some(3), Some(false)), Some(false));
+        assert_eq!(apply_record_cap(&mut recs, Some(3), None), Some(true));
+    }
 
-        let list = json.pointer("/data/list").and_then(|v| v.as_array());
-        let Some(list) = list else {
-            break;
-        };
-        if list.is_empty() {
-            break;
-        }
+    #[test]
+    fn clamp_request_delay_bounds_config_value() {
+        use std::time::Duration;
+        assert_eq!(clamp_request_delay(None), Duration::from_millis(100));
+        assert_eq!(clamp_request_delay(Some(-5)), Duration::ZERO);
+        assert_eq!(clamp_request_delay(Some(250)), Duration::from_millis(250));
+        assert_eq!(clamp_request_delay(Some(60_000)), Duration::from_millis(2000));
+    }
 
-        for item in list {
-            let seq_id = item.get("seqId").and_then(|v| v.as_str()).unwrap_or("").to_owned();
+    #[test]
+    fn weapon_record_falls_back_to_pool_name() {
+        let item = serde_json::json!({ "seqId": "1", "weaponName": "W", "poolName": "" });
+        let r = weapon_record_from_item(&item, "wpn_pool_1", "武器池");
+        assert_eq!(r.pool_id, "wpn_pool_1");
+        assert_eq!(r.pool_name, "武器池");
 
-            // Incremental stop check
-            if let Some(stop_id) = &last_seq_id_stop {
-                if &seq_id == stop_id {
-                    log_dev!("[hg-gacha] reached weapon last_seq_id={}, stopping", stop_id);
-                    break 'outer;
-                }
+        let item = serde_json::json!({ "seqId": "1", "weaponName": "W", "poolName": "自带池名" });
+        assert_eq!(weapon_record_from_item(&item, "wpn_pool_1", "武器池").pool_name, "自带池名");
+    }
+
+    #[tokio::test]
+    async fn paginate_records_drops_zero_rarity() {
+        let page = serde_json::json!({
+            "code": 0,
+            "data": {
+                "list": [
+                    { "seqId": "3", "charName": "A", "rarity": 5 },
+                    { "seqId": "2", "charName": "B" },
+                    { "seqId": "1", "charName": "C", "rarity": "4" }
+                ],
+                "hasMore": false
             }
+        });
+        let result = paginate_records(
+            |_seq_id| {
+                let page = page.clone();
+                async move { Ok(page) }
+            },
+            |item| char_record_from_item(item, "E_CharacterGachaPoolType_Standard"),
+            None,
+            None,
+            std::time::Duration::ZERO,
+            None,
+        )
+        .await
+        .unwrap();
 
-            let record = GachaRecord {
-                name: item.get("weaponName").or(item.get("weaponId")).and_then(|v| v.as_str()).unwrap_or("").to_owned(),
-                item_id: item.get("weaponId").and_then(|v| v.as_str()).unwrap_or("").to_owned(),
-                rarity: item.get("rarity").and_then(|v| v.as_i64().or_else(|| v.as_str().and_then(|s| s.parse().ok()))).unwrap_or(0),
-                pool_id: item.get("poolId").and_then(|v| v.as_str()).unwrap_or(&pool_id).to_owned(),
-                pool_name: item.get("poolName").and_then(|v| v.as_str()).unwrap_or("").to_owned(),
-                seq_id,
-                pulled_at: item.get("gachaTs").and_then(|v| v.as_i64().or_else(|| v.as_str().and_then(|s| s.parse().ok()))).unwrap_or(0),
-                pool_type: "E_CharacterGachaPoolType_Weapon".to_string(),
-                is_free: item.get("isFree").and_then(|v| v.as_bool()).unwrap_or(false),
-                is_new: item.get("isNew").and_then(|v| v.as_bool()).unwrap_or(false),
-            };
-            all_records.push(record);
-        }
+        let seq_ids: Vec<_> = result.records.iter().map(|r| r.seq_id.as_str()).collect();
+        assert_eq!(seq_ids, ["3", "1"]);
+    }
 
-        if let Some(last) = all_records.last() {
-            next_seq_id = Some(last.seq_id.clone());
-        } else {
-            break;
-        }
+    #[tokio::test]
+    async fn paginate_records_without_cap_fetches_past_default_max() {
+        const PAGE: usize = 500;
+        let total = DEFAULT_MAX_RECORDS + PAGE;
+        let result = paginate_records(
+            |cursor: Option<String>| async move {
+                let start = cursor.map_or(total, |c| c.parse::<usize>().unwrap());
+                let list: Vec<_> = (1..=PAGE.min(start))
+                    .map(|i| serde_json::json!({ "seqId": (start - i).to_string(), "charName": "A", "rarity": 4 }))
+                    .collect();
+                Ok(serde_json::json!({ "code": 0, "data": { "list": list, "hasMore": start > PAGE } }))
+            },
+            |item| char_record_from_item(item, "E_CharacterGachaPoolType_Standard"),
+            None,
+            None,
+            std::time::Duration::ZERO,
+            None,
+        )
+        .await
+        .unwrap();
 
-        if all_records.len() > 10000 {
-            break;
-        }
-        
-        if let Some(has_more) = json.pointer("/data/hasMore").and_then(|v| v.as_bool()) {
-            if !has_more {
-                break;
-            }
-        }
-        
-        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert_eq!(result.records.len(), total);
+        assert!(!result.truncated);
+    }
+
+    #[test]
+    fn is_throttled_body_matches_code_or_message() {
+        assert!(is_throttled_body(THROTTLED_CODE, &serde_json::json!({ "code": THROTTLED_CODE })));
+        assert!(is_throttled_body(1, &serde_json::json!({ "code": 1, "msg": "请求过于频繁" })));
+        assert!(!is_throttled_body(1, &serde_json::json!({ "code": 1, "msg": "token 无效" })));
+    }
+
+    #[test]
+    fn parse_retry_after_defaults_and_caps() {
+        use std::time::Duration;
+        assert_eq!(parse_retry_after(Some("5")), Duration::from_secs(5));
+        assert_eq!(parse_retry_after(Some(" 0 ")), Duration::ZERO);
+        assert_eq!(parse_retry_after(Some("3600")), Duration::from_secs(30));
+        assert_eq!(parse_retry_after(Some("Wed, 21 Oct 2015 07:28:00 GMT")), Duration::from_secs(2));
+        assert_eq!(parse_retry_after(None), Duration::from_secs(2));
     }
 
-    log_dev!("[hg-gacha] fetched total {} weapon records", all_records.len());
-    Ok(all_records)
+    #[tokio::test]
+    async fn paginate_records_stops_when_cursor_repeats() {
+        // The server ignores the cursor and returns the same page forever.
+        let page = serde_json::json!({
+            "code": 0,
+            "data": {
+                "list": [
+                    { "seqId": "20", "charName": "A", "rarity": 6 },
+                    { "seqId": "19", "charName": "B", "rarity": 4 }
+                ],
+                "hasMore": true
+            }
+        });
+        let mut calls = 0;
+        let pages = std::sync::Mutex::new(Vec::new());
+        let on_page = |page: usize, so_far: usize| pages.lock().unwrap().push((page, so_far));
+        let result = paginate_records(
+            |_seq_id| {
+                calls += 1;
+                let page = page.clone();
+                async move {
+                    assert!(calls < 10, "pagination did not terminate");
+                    Ok(page)
+                }
+            },
+            |item| char_record_from_item(item, "E_CharacterGachaPoolType_Special"),
+            None,
+            None,
+            std::time::Duration::ZERO,
+            Some(&on_page),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(calls, 2);
+        let seq_ids: Vec<_> = result.records.iter().map(|r| r.seq_id.as_str()).collect();
+        assert_eq!(seq_ids, ["20", "19"]);
+        assert!(!result.truncated);
+        assert_eq!(*pages.lock().unwrap(), [(1, 2)]);
+    }
 }
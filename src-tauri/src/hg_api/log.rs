@@ -1,3 +1,4 @@
+use flate2::read::GzDecoder;
 use serde::Serialize;
 use serde_json::json;
 use std::{
@@ -48,7 +49,64 @@ fn default_windows_log_path() -> Result<PathBuf, String> {
         .join("HGWebview.log"))
 }
 
-fn read_tail_text(path: &Path, max_bytes: u64) -> Result<String, String> {
+/// Decode log bytes, detecting UTF-16 via BOM or by where the zero bytes fall (ASCII text in UTF-16
+/// has every other byte zero). A tail cut at an odd offset is realigned by dropping the first byte.
+pub(crate) fn decode_log_bytes(buf: &[u8]) -> String {
+    let utf16 = |bytes: &[u8], le: bool| {
+        let units: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|c| if le { u16::from_le_bytes([c[0], c[1]]) } else { u16::from_be_bytes([c[0], c[1]]) })
+            .collect();
+        String::from_utf16_lossy(&units)
+    };
+
+    if let Some(rest) = buf.strip_prefix(&[0xFF, 0xFE]) {
+        return utf16(rest, true);
+    }
+    if let Some(rest) = buf.strip_prefix(&[0xFE, 0xFF]) {
+        return utf16(rest, false);
+    }
+    if let Some(rest) = buf.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        return String::from_utf8_lossy(rest).to_string();
+    }
+
+    let sample = &buf[..buf.len().min(4096)];
+    let zeros_even = sample.iter().step_by(2).filter(|b| **b == 0).count();
+    let zeros_odd = sample.iter().skip(1).step_by(2).filter(|b| **b == 0).count();
+    let threshold = sample.len() / 8;
+    if zeros_odd > threshold && zeros_odd > zeros_even * 4 {
+        return utf16(buf, true);
+    }
+    if zeros_even > threshold && zeros_even > zeros_odd * 4 {
+        // Either UTF-16BE, or UTF-16LE whose tail started on an odd byte; ASCII-heavy logs make LE the likely one.
+        return utf16(&buf[1..], true);
+    }
+    String::from_utf8_lossy(buf).to_string()
+}
+
+/// Default bytes read from the end of the log, overridable via config `logTailSizeMb`.
+pub const DEFAULT_LOG_TAIL_BYTES: u64 = 2 * 1024 * 1024;
+const MAX_LOG_TAIL_MB: i64 = 32;
+
+/// Clamp a configured tail size into 1–32MB; a missing value uses the default.
+fn clamp_log_tail_bytes(mb: Option<i64>) -> u64 {
+    match mb {
+        Some(mb) => mb.clamp(1, MAX_LOG_TAIL_MB) as u64 * 1024 * 1024,
+        None => DEFAULT_LOG_TAIL_BYTES,
+    }
+}
+
+/// Log tail size, read from config `logTailSizeMb`.
+pub(crate) fn log_tail_bytes_from_config() -> u64 {
+    let mb = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.to_path_buf()))
+        .and_then(|dir| crate::services::config::read_config_field::<i64>(&dir, "logTailSizeMb"));
+    clamp_log_tail_bytes(mb)
+}
+
+/// Last `max_bytes` of the file, plus whether earlier content was left unread.
+fn read_tail_text(path: &Path, max_bytes: u64) -> Result<(String, bool), String> {
     let mut f = File::open(path).map_err(|e| format!("无法打开日志文件：{} ({})", path.display(), e))?;
     let len = f.metadata().map_err(|e| e.to_string())?.len();
     let start = len.saturating_sub(max_bytes);
@@ -56,7 +114,83 @@ fn read_tail_text(path: &Path, max_bytes: u64) -> Result<String, String> {
 
     let mut buf = Vec::new();
     f.read_to_end(&mut buf).map_err(|e| e.to_string())?;
-    Ok(String::from_utf8_lossy(&buf).to_string())
+    Ok((decode_log_bytes(&buf), start > 0))
+}
+
+/// The live log followed by rotated siblings (`HGWebview.log.1`, `HGWebview.log.2.gz`, ...), newest first.
+fn log_candidates(path: &Path) -> Vec<PathBuf> {
+    let mut candidates = vec![path.to_path_buf()];
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name().and_then(|n| n.to_str())) else {
+        return candidates;
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return candidates;
+    };
+
+    let prefix = format!("{name}.");
+    let mut rotated: Vec<(std::time::SystemTime, PathBuf)> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_name().to_str().is_some_and(|n| n.starts_with(&prefix)))
+        .filter_map(|e| {
+            let meta = e.metadata().ok().filter(|m| m.is_file())?;
+            Some((meta.modified().unwrap_or(std::time::UNIX_EPOCH), e.path()))
+        })
+        .collect();
+    rotated.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    candidates.extend(rotated.into_iter().map(|(_, p)| p));
+    candidates
+}
+
+/// Tail of a log file; `.gz` rotations are decompressed first since they can't be seeked.
+fn read_log_tail(path: &Path, max_bytes: u64) -> Result<(String, bool), String> {
+    if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("gz")) {
+        let f = File::open(path).map_err(|e| format!("无法打开日志文件：{} ({})", path.display(), e))?;
+        let mut buf = Vec::new();
+        GzDecoder::new(f)
+            .read_to_end(&mut buf)
+            .map_err(|e| format!("日志解压失败：{} ({})", path.display(), e))?;
+        let start = buf.len().saturating_sub(usize::try_from(max_bytes).unwrap_or(usize::MAX));
+        return Ok((decode_log_bytes(&buf[start..]), start > 0));
+    }
+    read_tail_text(path, max_bytes)
+}
+
+/// Search the live log and its rotations, newest first, returning the gacha URL and the file it came from.
+/// Only the last `max_bytes` of each file are read at first; if nothing turns up, files that were cut
+/// short are scanned in full once.
+pub(crate) fn find_gacha_url_in_logs(path: &Path, max_bytes: u64) -> Result<(String, PathBuf), String> {
+    let mut first_err = None;
+    let mut read_any = false;
+    let mut truncated = Vec::new();
+    for candidate in log_candidates(path) {
+        match read_log_tail(&candidate, max_bytes) {
+            Ok((text, cut)) => {
+                read_any = true;
+                if let Some(url) = extract_latest_gacha_url(&text) {
+                    return Ok((url, candidate));
+                }
+                if cut {
+                    truncated.push(candidate);
+                }
+            }
+            Err(e) => {
+                log_dev!("[hg-log] skip {}: {}", candidate.display(), e);
+                first_err.get_or_insert(e);
+            }
+        }
+    }
+    for candidate in truncated {
+        log_dev!("[hg-log] full scan {}", candidate.display());
+        if let Ok((text, _)) = read_log_tail(&candidate, u64::MAX) {
+            if let Some(url) = extract_latest_gacha_url(&text) {
+                return Ok((url, candidate));
+            }
+        }
+    }
+    match first_err {
+        Some(e) if !read_any => Err(e),
+        _ => Err("未在日志中找到抽卡链接：请先在游戏内打开一次抽卡记录页面（角色池即可）再同步".to_owned()),
+    }
 }
 
 fn extract_url_from_line(line: &str) -> Option<String> {
@@ -92,6 +226,16 @@ fn extract_latest_gacha_url(log_text: &str) -> Option<String> {
     None
 }
 
+/// All gacha page URLs in the text, newest first.
+fn extract_gacha_urls(log_text: &str) -> Vec<String> {
+    log_text
+        .lines()
+        .rev()
+        .filter(|line| line.contains("/page/gacha_") && line.contains("https://ef-webview."))
+        .filter_map(extract_url_from_line)
+        .collect()
+}
+
 fn query_map(url: &tauri::Url) -> HashMap<String, String> {
     url.query_pairs()
         .map(|(k, v)| (k.to_string(), v.to_string()))
@@ -111,6 +255,38 @@ pub struct LogGachaAuth {
     pub source_url: String,
 }
 
+fn parse_gacha_auth(url_str: &str, source_path: &Path) -> Result<LogGachaAuth, String> {
+    let parsed = tauri::Url::parse(url_str).map_err(|e| format!("抽卡链接解析失败：{} ({})", url_str, e))?;
+    let q = query_map(&parsed);
+
+    let Some(u8_token) = q.get("u8_token").cloned() else {
+        return Err("抽卡链接参数解析失败：未找到 u8_token".to_owned());
+    };
+
+    let server_id = q.get("server_id").cloned().unwrap_or_else(|| "1".to_owned());
+    let channel = q.get("channel").cloned();
+    let sub_channel = q.get("subChannel").cloned().or_else(|| q.get("sub_channel").cloned());
+    let inferred_uid = infer_system_uid(channel.as_deref(), sub_channel.as_deref());
+
+    let provider = parsed
+        .host_str()
+        .and_then(|host| host.strip_prefix("ef-webview."))
+        .and_then(|rest| rest.strip_suffix(".com"))
+        .unwrap_or("hypergryph")
+        .to_owned();
+
+    Ok(LogGachaAuth {
+        u8_token,
+        server_id,
+        provider,
+        inferred_uid,
+        channel,
+        sub_channel,
+        source_path: source_path.to_string_lossy().to_string(),
+        source_url: url_str.to_owned(),
+    })
+}
+
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RoleIdentity {
@@ -210,52 +386,125 @@ pub async fn hg_gacha_auth_from_log(log_path: Option<String>) -> Result<LogGacha
         _ => default_windows_log_path()?,
     };
 
-    // Read only tail to avoid loading huge logs.
-    let text = read_tail_text(&path, 2 * 1024 * 1024)?;
-    let Some(url_str) = extract_latest_gacha_url(&text) else {
-        return Err("未在日志中找到抽卡链接：请先在游戏内打开一次抽卡记录页面（角色池即可）再同步".to_owned());
-    };
-
-    let parsed = tauri::Url::parse(&url_str).map_err(|e| format!("抽卡链接解析失败：{} ({})", url_str, e))?;
-    let q = query_map(&parsed);
-
-    let Some(u8_token) = q.get("u8_token").cloned() else {
-        return Err("抽卡链接参数解析失败：未找到 u8_token".to_owned());
-    };
-
-    let server_id = q.get("server_id").cloned().unwrap_or_else(|| "1".to_owned());
-    let channel = q.get("channel").cloned();
-    let sub_channel = q.get("subChannel").cloned().or_else(|| q.get("sub_channel").cloned());
-    let inferred_uid = infer_system_uid(channel.as_deref(), sub_channel.as_deref());
+    // Read only tail to avoid loading huge logs; falls back to a full scan when the tail has no URL.
+    let (url_str, source_path) = find_gacha_url_in_logs(&path, log_tail_bytes_from_config())?;
 
-    let provider = parsed
-        .host_str()
-        .and_then(|host| host.strip_prefix("ef-webview."))
-        .and_then(|rest| rest.strip_suffix(".com"))
-        .unwrap_or("hypergryph")
-        .to_owned();
+    let auth = parse_gacha_auth(&url_str, &source_path)?;
 
     // 日志解析暂时仅支持国服（hypergryph）。国际服请走手动添加账号流程。
-    if provider != "hypergryph" {
-        return Err(format!("日志暂时只支持国服（hypergryph），检测到 provider={provider}"));
+    if auth.provider != "hypergryph" {
+        return Err(format!("日志暂时只支持国服（hypergryph），检测到 provider={}", auth.provider));
     }
 
     log_dev!(
         "[hg-log] path={}, provider={}, inferred_uid={}, token_len={}",
-        path.display(),
-        provider,
-        inferred_uid,
-        u8_token.len()
+        auth.source_path,
+        auth.provider,
+        auth.inferred_uid,
+        auth.u8_token.len()
     );
 
-    Ok(LogGachaAuth {
-        u8_token,
-        server_id,
-        provider,
-        inferred_uid,
-        channel,
-        sub_channel,
-        source_path: path.to_string_lossy().to_string(),
-        source_url: url_str,
-    })
+    Ok(auth)
+}
+
+/// Every distinct gacha session captured in the log and its rotations, newest first,
+/// so the user can pick one when several accounts were opened in-session.
+#[tauri::command]
+pub async fn hg_list_log_gacha_urls(log_path: Option<String>) -> Result<Vec<LogGachaAuth>, String> {
+    let path = match log_path {
+        Some(p) if !p.trim().is_empty() => PathBuf::from(p),
+        _ => default_windows_log_path()?,
+    };
+
+    let max_bytes = log_tail_bytes_from_config();
+    let mut seen = std::collections::HashSet::new();
+    let mut sessions = Vec::new();
+    for candidate in log_candidates(&path) {
+        let text = match read_log_tail(&candidate, max_bytes) {
+            Ok((text, _)) => text,
+            Err(e) if candidate == path => return Err(e),
+            Err(e) => {
+                log_dev!("[hg-log] skip {}: {}", candidate.display(), e);
+                continue;
+            }
+        };
+        for url in extract_gacha_urls(&text) {
+            let Ok(auth) = parse_gacha_auth(&url, &candidate) else {
+                continue;
+            };
+            let key = (
+                auth.u8_token.clone(),
+                auth.server_id.clone(),
+                auth.provider.clone(),
+                auth.inferred_uid.clone(),
+            );
+            if seen.insert(key) {
+                sessions.push(auth);
+            }
+        }
+    }
+    Ok(sessions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    #[test]
+    fn finds_gacha_url_in_gzipped_rotation() {
+        let dir = std::env::temp_dir().join(format!("endcat-log-rotation-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let live = dir.join("HGWebview.log");
+        std::fs::write(&live, "startup\n").unwrap();
+
+        let url = "https://ef-webview.hypergryph.com/page/gacha_char?u8_token=abc&server_id=1";
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        writeln!(gz, "[info] open {url}").unwrap();
+        std::fs::write(dir.join("HGWebview.log.1.gz"), gz.finish().unwrap()).unwrap();
+
+        let (found, source) = find_gacha_url_in_logs(&live, 1024).unwrap();
+        assert_eq!(found, url);
+        assert_eq!(source.file_name().unwrap(), "HGWebview.log.1.gz");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn extracts_gacha_url_from_utf16le_log() {
+        let url = "https://ef-webview.hypergryph.com/page/gacha_char?u8_token=abc&server_id=1";
+        let text = format!("[info] boot\r\n[info] open {url}\r\n");
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend(text.encode_utf16().flat_map(|u| u.to_le_bytes()));
+
+        assert_eq!(extract_latest_gacha_url(&decode_log_bytes(&bytes)).as_deref(), Some(url));
+        // Without the BOM, and with a tail cut at an odd offset.
+        assert_eq!(extract_latest_gacha_url(&decode_log_bytes(&bytes[2..])).as_deref(), Some(url));
+        assert_eq!(extract_latest_gacha_url(&decode_log_bytes(&bytes[3..])).as_deref(), Some(url));
+    }
+
+    #[test]
+    fn extract_gacha_urls_lists_newest_first() {
+        let text = "open https://ef-webview.hypergryph.com/page/gacha_char?u8_token=old\n\
+                    noise\n\
+                    open https://ef-webview.hypergryph.com/page/gacha_weapon?u8_token=new\n";
+        let urls = extract_gacha_urls(text);
+        assert_eq!(urls.len(), 2);
+        assert!(urls[0].ends_with("u8_token=new"));
+        assert!(urls[1].ends_with("u8_token=old"));
+    }
+
+    #[test]
+    fn falls_back_to_full_scan_when_url_is_before_tail() {
+        let dir = std::env::temp_dir().join(format!("endcat-log-fullscan-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let live = dir.join("HGWebview.log");
+        let url = "https://ef-webview.hypergryph.com/page/gacha_char?u8_token=abc&server_id=1";
+        std::fs::write(&live, format!("open {url}\n{}", "x".repeat(4096))).unwrap();
+
+        let (found, _) = find_gacha_url_in_logs(&live, 1024).unwrap();
+        assert_eq!(found, url);
+        assert_eq!(clamp_log_tail_bytes(Some(1000)), 32 * 1024 * 1024);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
@@ -2,20 +2,18 @@
 //! These are high-level commands called by the frontend.
 
 use serde::Serialize;
-use tauri::State;
-use std::collections::HashMap;
-
-use crate::database::{DbPool, ApiGachaRecord};
-use crate::hg_api::gacha::GachaRecord;
-use crate::hg_api::utils::{json_i64, json_str};
-
-macro_rules! log_dev {
-    ($($arg:tt)*) => {
-        if cfg!(debug_assertions) {
-            println!($($arg)*)
-        }
-    };
-}
+use tauri::{AppHandle, Emitter, State};
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+use crate::database::{DbPool, ApiGachaRecord, SOURCE_LOG, SOURCE_TOKEN};
+use crate::services::shutdown::ActiveTasks;
+use crate::hg_api::gacha::{
+    char_record_from_item, fetch_record_page, paginate_records, request_delay_from_config,
+    weapon_record_from_item,
+    GachaFetchResult, GachaRecord, DEFAULT_MAX_RECORDS,
+};
+use crate::hg_api::utils::{json_i64, json_str, parse_oauth_token, read_json, redact, signed_query};
 
 fn normalize_provider(provider: Option<String>) -> Result<String, String> {
     let raw = provider.unwrap_or_else(|| "hypergryph".to_owned());
@@ -26,11 +24,44 @@ fn normalize_provider(provider: Option<String>) -> Result<String, String> {
     }
 }
 
-fn provider_from_channel_id(channel_id: Option<i64>) -> String {
-    if channel_id == Some(6) {
-        "gryphline".to_owned()
-    } else {
-        "hypergryph".to_owned()
+/// Login channel of an account (`channelMasterId` in the binding list, `channelId` in role queries).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    /// 国服官服
+    Official,
+    /// 国服 B 服
+    Bilibili,
+    /// 国际服
+    Gryphline,
+}
+
+impl Channel {
+    pub fn from_id(id: i64) -> Option<Self> {
+        match id {
+            1 => Some(Self::Official),
+            2 => Some(Self::Bilibili),
+            6 => Some(Self::Gryphline),
+            _ => None,
+        }
+    }
+
+    /// Domain family the account's APIs live under; Bilibili accounts use the CN endpoints.
+    pub fn provider(self) -> &'static str {
+        match self {
+            Self::Official | Self::Bilibili => "hypergryph",
+            Self::Gryphline => "gryphline",
+        }
+    }
+}
+
+/// Provider for a stored channel id. Accounts saved before channel ids were recorded have none
+/// and are CN official; an id we don't know is an error rather than a guess at the domain.
+fn provider_from_channel_id(channel_id: Option<i64>) -> Result<String, String> {
+    match channel_id {
+        None => Ok(Channel::Official.provider().to_owned()),
+        Some(id) => Channel::from_id(id)
+            .map(|c| c.provider().to_owned())
+            .ok_or_else(|| format!("未知的渠道 channelId={id}")),
     }
 }
 
@@ -49,17 +80,15 @@ async fn get_u8_token(
         "token": oauth_token,
     });
 
-    let u8_json = client
+    let res = client
         .post(format!(
             "https://binding-api-account-prod.{provider}.com/account/binding/v1/u8_token_by_uid"
         ))
         .json(&request_body)
         .send()
         .await
-        .map_err(|e| e.to_string())?
-        .json::<serde_json::Value>()
-        .await
         .map_err(|e| e.to_string())?;
+    let u8_json = read_json(res).await?;
 
     let status = json_i64(&u8_json, "status").unwrap_or(-1);
     if status != 0 {
@@ -73,6 +102,50 @@ async fn get_u8_token(
     json_str(&u8_json, "/data/token").ok_or_else(|| "u8_token 响应缺少 data.token".to_owned())
 }
 
+const DEFAULT_U8_TOKEN_TTL_MINUTES: i64 = 30;
+
+fn now_secs() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// TTL for a stored u8_token, read from config `u8TokenTtlMinutes` (default 30).
+fn u8_token_ttl_from_config() -> i64 {
+    let minutes = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.to_path_buf()))
+        .and_then(|dir| crate::services::config::read_config_field::<i64>(&dir, "u8TokenTtlMinutes"))
+        .unwrap_or(DEFAULT_U8_TOKEN_TTL_MINUTES);
+    minutes.max(0) * 60
+}
+
+fn u8_token_is_fresh(fetched_at: Option<i64>, now: i64, ttl_secs: i64) -> bool {
+    fetched_at.is_some_and(|at| at <= now && now - at < ttl_secs)
+}
+
+/// Fetch a new u8_token and, unless `persist` is false, store it with its issue time.
+async fn refresh_u8_token(
+    pool: &DbPool,
+    client: &reqwest::Client,
+    uid: &str,
+    oauth_token: &str,
+    provider: &str,
+    persist: bool,
+) -> Result<String, String> {
+    let u8_token = get_u8_token(client, uid, oauth_token, provider).await?;
+    if persist {
+        sqlx::query("UPDATE accounts SET u8_token = ?, u8_token_fetched_at = unixepoch() WHERE uid = ?")
+            .bind(&u8_token)
+            .bind(uid)
+            .execute(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(u8_token)
+}
+
 #[derive(Debug)]
 struct RoleInfo {
     uid: String,
@@ -92,15 +165,13 @@ async fn query_role_list(
         "serverId": server_id,
     });
 
-    let json = client
+    let res = client
         .post(url)
         .json(&req_body)
         .send()
         .await
-        .map_err(|e| e.to_string())?
-        .json::<serde_json::Value>()
-        .await
         .map_err(|e| e.to_string())?;
+    let json = read_json(res).await?;
 
     let code = json_i64(&json, "code")
         .or_else(|| json_i64(&json, "status"))
@@ -147,6 +218,16 @@ async fn query_role_list(
     })
 }
 
+/// Record language used when a sync does not request any
+const DEFAULT_LANG: &str = "zh-cn";
+
+const CHAR_POOL_TYPES: [&str; 3] = [
+    "E_CharacterGachaPoolType_Special",
+    "E_CharacterGachaPoolType_Standard",
+    "E_CharacterGachaPoolType_Beginner",
+];
+
+#[allow(clippy::too_many_arguments)]
 async fn fetch_char_records_internal(
     client: &reqwest::Client,
     token: &str,
@@ -154,122 +235,33 @@ async fn fetch_char_records_internal(
     pool_type: &str,
     last_seq_id_stop: Option<&str>,
     provider: &str,
-) -> Result<Vec<GachaRecord>, String> {
+    lang: &str,
+    max_records: Option<usize>,
+    page_delay: Duration,
+) -> Result<GachaFetchResult, String> {
     let url = format!("https://ef-webview.{provider}.com/api/record/char");
-    let mut all_records = Vec::new();
-    let mut next_seq_id: Option<String> = None;
-
-    'outer: loop {
-        let mut params = vec![
-            ("token", token),
-            ("server_id", server_id),
-            ("lang", "zh-cn"),
-            ("pool_type", pool_type),
-        ];
-        let seq_holder;
-        if let Some(seq) = &next_seq_id {
-            seq_holder = seq.clone();
-            params.push(("seq_id", &seq_holder));
-        }
-
-        let json = client
-            .get(&url)
-            .query(&params)
-            .send()
-            .await
-            .map_err(|e| e.to_string())?
-            .json::<serde_json::Value>()
-            .await
-            .map_err(|e| e.to_string())?;
-
-        let code = json_i64(&json, "code")
-            .or_else(|| json_i64(&json, "status"))
-            .unwrap_or(-1);
-        if code != 0 {
-            let msg = json
-                .get("msg")
-                .and_then(|v| v.as_str())
-                .unwrap_or("获取寻访记录失败");
-            return Err(msg.to_owned());
-        }
-
-        let list = json.pointer("/data/list").and_then(|v| v.as_array());
-        let Some(list) = list else { break };
-        if list.is_empty() {
-            break;
-        }
-
-        for item in list {
-            let seq_id = item
-                .get("seqId")
-                .and_then(|v| v.as_str())
-                .unwrap_or("")
-                .to_owned();
-
-            if let Some(stop_id) = last_seq_id_stop {
-                if seq_id == stop_id {
-                    break 'outer;
-                }
-            }
-
-            let record = GachaRecord {
-                name: item
-                    .get("charName")
-                    .or(item.get("charId"))
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("")
-                    .to_owned(),
-                item_id: item
-                    .get("charId")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("")
-                    .to_owned(),
-                rarity: item
-                    .get("rarity")
-                    .and_then(|v| v.as_i64().or_else(|| v.as_str().and_then(|s| s.parse().ok())))
-                    .unwrap_or(0),
-                pool_id: item
-                    .get("poolId")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("")
-                    .to_owned(),
-                pool_name: item
-                    .get("poolName")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("")
-                    .to_owned(),
-                seq_id,
-                pulled_at: item
-                    .get("gachaTs")
-                    .and_then(|v| v.as_i64().or_else(|| v.as_str().and_then(|s| s.parse().ok())))
-                    .unwrap_or(0),
-                pool_type: pool_type.to_owned(),
-                is_free: item.get("isFree").and_then(|v| v.as_bool()).unwrap_or(false),
-                is_new: item.get("isNew").and_then(|v| v.as_bool()).unwrap_or(false),
-            };
-            all_records.push(record);
-        }
-
-        if let Some(last) = all_records.last() {
-            next_seq_id = Some(last.seq_id.clone());
-        } else {
-            break;
-        }
-
-        if all_records.len() > 10000 {
-            break;
-        }
-
-        if let Some(has_more) = json.pointer("/data/hasMore").and_then(|v| v.as_bool()) {
-            if !has_more {
-                break;
+    let url = url.as_str();
+
+    paginate_records(
+        |seq_id| async move {
+            let mut params = vec![
+                ("token", token),
+                ("server_id", server_id),
+                ("lang", lang),
+                ("pool_type", pool_type),
+            ];
+            if let Some(seq) = seq_id.as_deref() {
+                params.push(("seq_id", seq));
             }
-        }
-
-        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-    }
-
-    Ok(all_records)
+            fetch_record_page(client, url, &params, "获取寻访记录失败").await
+        },
+        |item| char_record_from_item(item, pool_type),
+        last_seq_id_stop,
+        max_records,
+        page_delay,
+        None,
+    )
+    .await
 }
 
 async fn fetch_weapon_pools_internal(
@@ -277,23 +269,22 @@ async fn fetch_weapon_pools_internal(
     token: &str,
     server_id: &str,
     provider: &str,
+    lang: &str,
 ) -> Result<Vec<(String, String)>, String> {
     let url = format!("https://ef-webview.{provider}.com/api/record/weapon/pool");
     let params = [
         ("token", token),
         ("server_id", server_id),
-        ("lang", "zh-cn"),
+        ("lang", lang),
     ];
 
-    let json = client
+    let res = client
         .get(&url)
-        .query(&params)
+        .query(&signed_query(&params))
         .send()
         .await
-        .map_err(|e| e.to_string())?
-        .json::<serde_json::Value>()
-        .await
         .map_err(|e| e.to_string())?;
+    let json = read_json(res).await?;
 
     let code = json_i64(&json, "code")
         .or_else(|| json_i64(&json, "status"))
@@ -332,129 +323,86 @@ async fn fetch_weapon_pools_internal(
     Ok(pools)
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn fetch_weapon_records_internal(
     client: &reqwest::Client,
     token: &str,
     server_id: &str,
     pool_id: &str,
+    pool_name: &str,
     last_seq_id_stop: Option<&str>,
     provider: &str,
-) -> Result<Vec<GachaRecord>, String> {
+    lang: &str,
+    max_records: Option<usize>,
+    page_delay: Duration,
+) -> Result<GachaFetchResult, String> {
     let url = format!("https://ef-webview.{provider}.com/api/record/weapon");
-    let mut all_records = Vec::new();
-    let mut next_seq_id: Option<String> = None;
-
-    'outer: loop {
-        let mut params = vec![
-            ("token", token),
-            ("server_id", server_id),
-            ("pool_id", pool_id),
-            ("lang", "zh-cn"),
-        ];
-        let seq_holder;
-        if let Some(seq) = &next_seq_id {
-            seq_holder = seq.clone();
-            params.push(("seq_id", &seq_holder));
-        }
-
-        let json = client
-            .get(&url)
-            .query(&params)
-            .send()
-            .await
-            .map_err(|e| e.to_string())?
-            .json::<serde_json::Value>()
-            .await
-            .map_err(|e| e.to_string())?;
-
-        let code = json_i64(&json, "code")
-            .or_else(|| json_i64(&json, "status"))
-            .unwrap_or(-1);
-        if code != 0 {
-            let msg = json
-                .get("msg")
-                .and_then(|v| v.as_str())
-                .unwrap_or("获取武器记录失败");
-            return Err(msg.to_owned());
-        }
-
-        let list = json.pointer("/data/list").and_then(|v| v.as_array());
-        let Some(list) = list else { break };
-        if list.is_empty() {
-            break;
-        }
-
-        for item in list {
-            let seq_id = item
-                .get("seqId")
-                .and_then(|v| v.as_str())
-                .unwrap_or("")
-                .to_owned();
-
-            if let Some(stop_id) = last_seq_id_stop {
-                if seq_id == stop_id {
-                    break 'outer;
-                }
+    let url = url.as_str();
+
+    paginate_records(
+        |seq_id| async move {
+            let mut params = vec![
+                ("token", token),
+                ("server_id", server_id),
+                ("pool_id", pool_id),
+                ("lang", lang),
+            ];
+            if let Some(seq) = seq_id.as_deref() {
+                params.push(("seq_id", seq));
             }
+            fetch_record_page(client, url, &params, "获取武器记录失败").await
+        },
+        |item| weapon_record_from_item(item, pool_id, pool_name),
+        last_seq_id_stop,
+        max_records,
+        page_delay,
+        None,
+    )
+    .await
+}
 
-            let record = GachaRecord {
-                name: item
-                    .get("weaponName")
-                    .or(item.get("weaponId"))
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("")
-                    .to_owned(),
-                item_id: item
-                    .get("weaponId")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("")
-                    .to_owned(),
-                rarity: item
-                    .get("rarity")
-                    .and_then(|v| v.as_i64().or_else(|| v.as_str().and_then(|s| s.parse().ok())))
-                    .unwrap_or(0),
-                pool_id: item
-                    .get("poolId")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or(pool_id)
-                    .to_owned(),
-                pool_name: item
-                    .get("poolName")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("")
-                    .to_owned(),
-                seq_id,
-                pulled_at: item
-                    .get("gachaTs")
-                    .and_then(|v| v.as_i64().or_else(|| v.as_str().and_then(|s| s.parse().ok())))
-                    .unwrap_or(0),
-                pool_type: "E_CharacterGachaPoolType_Weapon".to_string(),
-                is_free: item.get("isFree").and_then(|v| v.as_bool()).unwrap_or(false),
-                is_new: item.get("isNew").and_then(|v| v.as_bool()).unwrap_or(false),
-            };
-            all_records.push(record);
-        }
-
-        if let Some(last) = all_records.last() {
-            next_seq_id = Some(last.seq_id.clone());
-        } else {
-            break;
-        }
+/// Incremental stop point for one pool: character pools are looked up by `pool_type`, weapon
+/// banners by their pool id, matching the keys of [`crate::database::incremental_cursors`].
+fn pool_cursor<'a>(last_seq_map: &'a HashMap<String, String>, pool_key: &str) -> Option<&'a str> {
+    last_seq_map.get(pool_key).map(String::as_str)
+}
 
-        if all_records.len() > 10000 {
-            break;
+/// Item names of the records above `last_seq_map` in another `lang`, keyed by
+/// `(seq_id, pool_type)`. A pool that fails only loses its names in that language.
+#[allow(clippy::too_many_arguments)]
+async fn fetch_localized_names(
+    client: &reqwest::Client,
+    token: &str,
+    server_id: &str,
+    provider: &str,
+    lang: &str,
+    weapon_pools: &[(String, String)],
+    last_seq_map: &HashMap<String, String>,
+    page_delay: Duration,
+) -> HashMap<(String, String), String> {
+    let mut records = Vec::new();
+    for pt in CHAR_POOL_TYPES {
+        let stop_at = pool_cursor(last_seq_map, pt);
+        match fetch_char_records_internal(client, token, server_id, pt, stop_at, provider, lang, Some(DEFAULT_MAX_RECORDS), page_delay).await {
+            Ok(fetched) => records.extend(fetched.records),
+            Err(e) => tracing::warn!("[sync] fetch char {} ({}) failed: {}", pt, lang, e),
         }
-
-        if let Some(has_more) = json.pointer("/data/hasMore").and_then(|v| v.as_bool()) {
-            if !has_more {
-                break;
-            }
+    }
+    for (pool_id, pool_name) in weapon_pools {
+        let stop_at = pool_cursor(last_seq_map, pool_id);
+        match fetch_weapon_records_internal(client, token, server_id, pool_id, pool_name, stop_at, provider, lang, Some(DEFAULT_MAX_RECORDS), page_delay).await {
+            Ok(fetched) => records.extend(fetched.records),
+            Err(e) => tracing::warn!("[sync] fetch weapon {} ({}) failed: {}", pool_id, lang, e),
         }
-
-        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
     }
+    records.into_iter().map(|r| ((r.seq_id, r.pool_type), r.name)).collect()
+}
 
-    Ok(all_records)
+/// Drop repeated `(seq_id, pool_type)` records, keeping the first. The API occasionally
+/// returns the same record on both sides of a page boundary.
+fn dedup_records(records: &mut Vec<GachaRecord>) {
+    let mut seen = std::collections::HashSet::new();
+    records.retain(|r| seen.insert((r.seq_id.clone(), r.pool_type.clone())));
 }
 
 fn gacha_to_api_record(r: GachaRecord) -> ApiGachaRecord {
@@ -476,34 +424,180 @@ fn gacha_to_api_record(r: GachaRecord) -> ApiGachaRecord {
 // Public Tauri Commands
 // ───────────────────────────────────────────────────────────────────────────
 
+/// Uids with a sync in progress, managed as app state
+#[derive(Default)]
+pub struct SyncLocks(std::sync::Mutex<std::collections::HashSet<String>>);
+
+/// Holds the per-uid sync lock until dropped, so every exit path releases it
+pub struct SyncLockGuard<'a> {
+    locks: &'a SyncLocks,
+    uid: String,
+}
+
+impl SyncLocks {
+    /// Lock `uid`, or `None` when a sync for it is already running.
+    pub fn try_lock(&self, uid: &str) -> Option<SyncLockGuard<'_>> {
+        let mut running = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        running.insert(uid.to_owned()).then(|| SyncLockGuard { locks: self, uid: uid.to_owned() })
+    }
+}
+
+impl Drop for SyncLockGuard<'_> {
+    fn drop(&mut self) {
+        self.locks.0.lock().unwrap_or_else(|e| e.into_inner()).remove(&self.uid);
+    }
+}
+
+/// Sync failure, serialized as `{ kind, message }` like other structured command errors
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum SyncError {
+    /// Another sync for the same uid has not finished yet
+    AlreadyRunning { uid: String, message: String },
+    /// `check_online` was requested and the connectivity probe failed
+    Offline { message: String },
+    Failed { message: String },
+}
+
+impl SyncError {
+    fn already_running(uid: &str) -> Self {
+        Self::AlreadyRunning { uid: uid.to_owned(), message: "该账号正在同步中，请稍候".to_owned() }
+    }
+
+    fn offline() -> Self {
+        Self::Offline { message: "网络不可用，请检查网络连接".to_owned() }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            Self::AlreadyRunning { message, .. } | Self::Offline { message } | Self::Failed { message } => message,
+        }
+    }
+}
+
+impl From<String> for SyncError {
+    fn from(message: String) -> Self {
+        Self::Failed { message }
+    }
+}
+
+impl std::fmt::Display for SyncError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SyncResult {
     pub count: usize,
     pub account_updated: bool,
+    /// At least one pool hit `DEFAULT_MAX_RECORDS`, so older records may be missing.
+    pub truncated: bool,
+    /// Only set when `dry_run` is requested; nothing is written in that case.
+    pub preview: Option<SyncPreview>,
+    /// Only set in `repair` mode: rows that were missing locally and got inserted.
+    pub recovered: Option<usize>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncPreview {
+    pub new_records: usize,
+    pub updated_records: usize,
+    pub unchanged: usize,
 }
 
 /// Sync gacha records for an existing account using stored OAuth token.
 /// This command:
-/// 1. Gets u8_token from stored oauth_token
-/// 2. Queries role info and updates account (channel_id, role_id, nick_name)
+/// 1. Reuses the stored u8_token within its TTL, or gets a new one from the oauth_token
+/// 2. Queries role info and updates account (channel_id, role_id, nick_name) when the
+///    u8_token was refreshed
 /// 3. Fetches all gacha records
 /// 4. Saves records to database
+///
+/// `repair` mode fetches everything like `full` but only inserts rows missing locally, which
+/// fills gaps left below the incremental stop point by an interrupted sync. Nothing is
+/// deleted or overwritten.
+///
+/// With `dry_run`, steps 2 and 4 are skipped and the fetched records are only compared
+/// against the database, so the caller can see what a real sync would change.
+///
+/// With `check_online`, a failed connectivity probe returns `SyncError::Offline` up front.
+///
+/// `langs` (e.g. `["zh-cn", "en-us"]`) fetches the records once per language: the first one
+/// fills `item_name` as before and all of them are merged into `item_name_i18n`.
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn sync_gacha_by_token(
     pool: State<'_, DbPool>,
     client: State<'_, reqwest::Client>,
+    tasks: State<'_, ActiveTasks>,
+    locks: State<'_, SyncLocks>,
     uid: String,
-    mode: String, // "incremental" or "full"
+    mode: String, // "incremental", "full" or "repair"
+    dry_run: Option<bool>,
+    check_online: Option<bool>,
+    langs: Option<Vec<String>>,
+    server_id: Option<String>,
+) -> Result<SyncResult, SyncError> {
+    let _running = tasks.begin();
+    let _locked = locks.try_lock(&uid).ok_or_else(|| SyncError::already_running(&uid))?;
+    if check_online.unwrap_or(false) && !crate::services::http::is_online(client.inner()).await {
+        return Err(SyncError::offline());
+    }
+    let langs = langs
+        .unwrap_or_default()
+        .iter()
+        .map(|lang| crate::database::normalize_lang(lang))
+        .collect::<Result<Vec<_>, _>>()?;
+    let server_id = server_id.map(|s| s.trim().to_owned()).filter(|s| !s.is_empty());
+    Ok(sync_account_internal(pool.inner(), client.inner(), &uid, &mode, dry_run.unwrap_or(false), &langs, server_id.as_deref()).await?)
+}
+
+/// Server to sync: the explicit choice, else the account's stored server, else "1".
+fn resolve_server_id<'a>(server_override: Option<&'a str>, stored: Option<&'a str>) -> &'a str {
+    server_override
+        .or(stored.filter(|s| !s.is_empty()))
+        .unwrap_or("1")
+}
+
+async fn sync_account_internal(
+    pool: &DbPool,
+    client: &reqwest::Client,
+    uid: &str,
+    mode: &str,
+    dry_run: bool,
+    langs: &[String],
+    server_id: Option<&str>,
 ) -> Result<SyncResult, String> {
-    log_dev!("[sync] sync_gacha_by_token uid={}, mode={}", uid, mode);
+    let started_at = now_secs();
+    let result = run_account_sync(pool, client, uid, mode, dry_run, langs, server_id).await;
+    if !dry_run {
+        let count = result.as_ref().ok().map(|r| r.count);
+        record_sync_log(pool, Some(uid), started_at, mode, count, result.as_ref().err()).await;
+    }
+    result
+}
+
+async fn run_account_sync(
+    pool: &DbPool,
+    client: &reqwest::Client,
+    uid: &str,
+    mode: &str,
+    dry_run: bool,
+    langs: &[String],
+    server_override: Option<&str>,
+) -> Result<SyncResult, String> {
+    tracing::info!("[sync] sync_gacha_by_token uid={}, mode={}, dry_run={}, langs={:?}, server={:?}", redact(uid), mode, dry_run, langs, server_override);
+    let primary_lang = langs.first().map(String::as_str).unwrap_or(DEFAULT_LANG);
 
     // 1. Get account with tokens
     let account = sqlx::query_as::<_, crate::database::AccountWithTokens>(
-        "SELECT uid, role_id, nick_name, server_id, channel_id, user_token, oauth_token, u8_token FROM accounts WHERE uid = ? LIMIT 1"
+        "SELECT uid, role_id, nick_name, server_id, channel_id, user_token, oauth_token, u8_token, u8_token_fetched_at FROM accounts WHERE uid = ? LIMIT 1"
     )
-    .bind(&uid)
-    .fetch_optional(pool.inner())
+    .bind(uid)
+    .fetch_optional(pool)
     .await
     .map_err(|e| e.to_string())?
     .ok_or_else(|| format!("账户不存在: {uid}"))?;
@@ -511,114 +605,398 @@ pub async fn sync_gacha_by_token(
     let oauth_token = account.oauth_token.as_ref().filter(|s| !s.is_empty())
         .ok_or("账户缺少 OAuth Token，请重新登录")?;
 
-    let server_id = account.server_id.as_deref().unwrap_or("1");
-    let provider = provider_from_channel_id(account.channel_id);
+    let server_id = resolve_server_id(server_override, account.server_id.as_deref());
+    let provider = provider_from_channel_id(account.channel_id)?;
+
+    // 2. Reuse the stored u8_token while it is within its TTL, otherwise fetch a new one
+    let cached_u8_token = account
+        .u8_token
+        .clone()
+        .filter(|t| !t.is_empty())
+        .filter(|_| u8_token_is_fresh(account.u8_token_fetched_at, now_secs(), u8_token_ttl_from_config()));
+    let mut refreshed = cached_u8_token.is_none();
+    let mut u8_token = match cached_u8_token {
+        Some(token) => token,
+        None => refresh_u8_token(pool, client, uid, oauth_token, &provider, !dry_run).await?,
+    };
 
-    // 2. Get fresh u8_token
-    let u8_token = get_u8_token(&client, &uid, oauth_token, &provider).await?;
+    // The weapon pool list is the cheapest authenticated call, so it doubles as a validity
+    // probe: a reused token that fails here is treated as expired and refreshed once.
+    let mut weapon_pools = fetch_weapon_pools_internal(client, &u8_token, server_id, &provider, primary_lang).await;
+    if weapon_pools.is_err() && !refreshed {
+        tracing::info!("[sync] cached u8_token rejected, refreshing");
+        u8_token = refresh_u8_token(pool, client, uid, oauth_token, &provider, !dry_run).await?;
+        refreshed = true;
+        weapon_pools = fetch_weapon_pools_internal(client, &u8_token, server_id, &provider, primary_lang).await;
+    }
 
-    // 3. Query role info and update account
-    let role_info = query_role_list(&client, &u8_token, server_id).await.ok();
+    // 3. Query role info and update account (only with a new token or a newly chosen server;
+    // otherwise we looked the role up recently)
+    let server_changed = server_override.is_some() && account.server_id.as_deref() != Some(server_id);
+    let role_info = if refreshed || server_changed {
+        query_role_list(client, &u8_token, server_id).await.ok()
+    } else {
+        None
+    };
     let mut account_updated = false;
 
-    if let Some(info) = &role_info {
+    // An explicitly chosen server becomes the account's default for later syncs
+    if server_changed && !dry_run {
+        sqlx::query("UPDATE accounts SET server_id = ?, updated_at = unixepoch() WHERE uid = ?")
+            .bind(server_id)
+            .bind(uid)
+            .execute(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+        account_updated = true;
+        tracing::info!("[sync] account server_id set to {}", server_id);
+    }
+
+    if let Some(info) = role_info.as_ref().filter(|_| !dry_run) {
         sqlx::query(
             "UPDATE accounts SET role_id = COALESCE(?, role_id), nick_name = COALESCE(?, nick_name), channel_id = COALESCE(?, channel_id), updated_at = unixepoch() WHERE uid = ?"
         )
         .bind(&info.role_id)
         .bind(&info.nick_name)
         .bind(info.channel_id)
-        .bind(&uid)
-        .execute(pool.inner())
+        .bind(uid)
+        .execute(pool)
         .await
         .map_err(|e| e.to_string())?;
         account_updated = true;
-        log_dev!("[sync] account updated: role_id={:?}, channel_id={:?}", info.role_id, info.channel_id);
+        tracing::debug!("[sync] account updated: role_id={:?}, channel_id={:?}", info.role_id.as_deref().map(redact), info.channel_id);
     }
 
     // 4. Get last seq_ids for incremental mode
-    let mut last_seq_map: HashMap<String, String> = HashMap::new();
-    if mode == "incremental" {
-        let rows = sqlx::query_as::<_, (String, String)>(
-            "SELECT pool_type, seq_id FROM gacha_pulls WHERE uid = ? AND seq_id IS NOT NULL ORDER BY pulled_at DESC LIMIT 1000"
-        )
-        .bind(&uid)
-        .fetch_all(pool.inner())
-        .await
-        .unwrap_or_default();
-
-        for (pool_type, seq_id) in rows {
-            last_seq_map.entry(pool_type).or_insert(seq_id);
-        }
-    }
+    let last_seq_map: HashMap<String, String> = if mode == "incremental" {
+        crate::database::incremental_cursors(pool, uid).await.unwrap_or_default()
+    } else {
+        HashMap::new()
+    };
 
     // 5. Delete invalid records if full mode
-    if mode == "full" {
+    if mode == "full" && !dry_run {
         sqlx::query("DELETE FROM gacha_pulls WHERE uid = ? AND pulled_at = 0")
-            .bind(&uid)
-            .execute(pool.inner())
+            .bind(uid)
+            .execute(pool)
             .await
             .ok();
     }
 
     // 6. Fetch all gacha records
-    let pool_types = [
-        "E_CharacterGachaPoolType_Special",
-        "E_CharacterGachaPoolType_Standard",
-        "E_CharacterGachaPoolType_Beginner",
-    ];
-
     let mut all_records: Vec<GachaRecord> = Vec::new();
-
-    for pt in pool_types {
-        let stop_at = last_seq_map.get(pt).map(|s| s.as_str());
-        match fetch_char_records_internal(&client, &u8_token, server_id, pt, stop_at, &provider).await {
-            Ok(records) => all_records.extend(records),
-            Err(e) => log_dev!("[sync] fetch char {} failed: {}", pt, e),
+    let mut truncated = false;
+    let page_delay = request_delay_from_config();
+
+    for pt in CHAR_POOL_TYPES {
+        let stop_at = pool_cursor(&last_seq_map, pt);
+        match fetch_char_records_internal(client, &u8_token, server_id, pt, stop_at, &provider, primary_lang, Some(DEFAULT_MAX_RECORDS), page_delay).await {
+            Ok(fetched) => {
+                truncated |= fetched.truncated;
+                all_records.extend(fetched.records);
+            }
+            Err(e) => tracing::warn!("[sync] fetch char {} failed: {}", pt, e),
         }
     }
 
-    // Fetch weapon pools and records
-    if let Ok(weapon_pools) = fetch_weapon_pools_internal(&client, &u8_token, server_id, &provider).await {
-        for (pool_id, _pool_name) in weapon_pools {
-            let stop_at = last_seq_map.get(&pool_id).map(|s| s.as_str());
-            match fetch_weapon_records_internal(&client, &u8_token, server_id, &pool_id, stop_at, &provider).await {
-                Ok(records) => all_records.extend(records),
-                Err(e) => log_dev!("[sync] fetch weapon {} failed: {}", pool_id, e),
+    // Fetch weapon records for the pools listed above
+    let weapon_pools = weapon_pools.unwrap_or_default();
+    for (pool_id, pool_name) in &weapon_pools {
+        let stop_at = pool_cursor(&last_seq_map, pool_id);
+        match fetch_weapon_records_internal(client, &u8_token, server_id, pool_id, pool_name, stop_at, &provider, primary_lang, Some(DEFAULT_MAX_RECORDS), page_delay).await {
+            Ok(fetched) => {
+                truncated |= fetched.truncated;
+                all_records.extend(fetched.records);
             }
+            Err(e) => tracing::warn!("[sync] fetch weapon {} failed: {}", pool_id, e),
         }
     }
 
-    log_dev!("[sync] fetched {} total records", all_records.len());
+    if truncated {
+        tracing::warn!("[sync] record cap {} reached, history may be incomplete", DEFAULT_MAX_RECORDS);
+    }
+
+    dedup_records(&mut all_records);
+    tracing::info!("[sync] fetched {} total records", all_records.len());
+
+    let api_records: Vec<ApiGachaRecord> = all_records.iter().cloned().map(gacha_to_api_record).collect();
+
+    if dry_run {
+        let preview = preview_gacha_records_internal(pool, uid, &api_records).await?;
+        tracing::info!("[sync] dry run preview: {:?}", preview);
+        return Ok(SyncResult {
+            count: all_records.len(),
+            account_updated,
+            truncated,
+            preview: Some(preview),
+            recovered: None,
+        });
+    }
 
-    // 7. Save to database
-    if !all_records.is_empty() {
-        let api_records: Vec<ApiGachaRecord> = all_records.iter().cloned().map(gacha_to_api_record).collect();
-        save_gacha_records_internal(pool.inner(), &uid, api_records).await?;
+    // 7. Save to database (repair mode only fills in rows that are missing)
+    let mut recovered = None;
+    if mode == "repair" {
+        let inserted = insert_missing_gacha_records_internal(pool, uid, api_records, SOURCE_TOKEN).await?;
+        tracing::info!("[sync] repair recovered {} missing records", inserted);
+        recovered = Some(inserted);
+    } else if !api_records.is_empty() {
+        save_gacha_records_internal(pool, uid, api_records, SOURCE_TOKEN).await?;
     }
 
+    // 8. Fetch the same records in the other requested languages and store every name
+    if !langs.is_empty() && !all_records.is_empty() {
+        let mut names: HashMap<(String, String), BTreeMap<String, String>> = HashMap::new();
+        for r in &all_records {
+            names
+                .entry((r.seq_id.clone(), r.pool_type.clone()))
+                .or_default()
+                .insert(primary_lang.to_owned(), r.name.clone());
+        }
+        for lang in &langs[1..] {
+            let localized = fetch_localized_names(client, &u8_token, server_id, &provider, lang, &weapon_pools, &last_seq_map, page_delay).await;
+            for (key, name) in localized {
+                if let Some(by_lang) = names.get_mut(&key) {
+                    by_lang.insert(lang.clone(), name);
+                }
+            }
+        }
+        save_item_names_i18n(pool, uid, &names).await?;
+    }
+    mark_account_synced(pool, uid).await?;
+
     Ok(SyncResult {
         count: all_records.len(),
         account_updated,
+        truncated,
+        preview: None,
+        recovered,
     })
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountSyncOutcome {
+    pub uid: String,
+    pub count: usize,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncAllProgress {
+    pub uid: String,
+    pub index: usize,
+    pub total: usize,
+}
+
+/// Sync every account that has a stored OAuth token, one after another.
+/// A failing account is reported in its outcome and does not stop the batch.
+/// Emits `sync-all-progress` before each account starts.
+#[tauri::command]
+pub async fn sync_all_accounts(
+    app: AppHandle,
+    pool: State<'_, DbPool>,
+    client: State<'_, reqwest::Client>,
+    tasks: State<'_, ActiveTasks>,
+    locks: State<'_, SyncLocks>,
+    mode: String,
+) -> Result<Vec<AccountSyncOutcome>, String> {
+    let _running = tasks.begin();
+    let uids: Vec<String> = sqlx::query_scalar(
+        "SELECT uid FROM accounts WHERE oauth_token IS NOT NULL AND oauth_token != '' AND archived = 0 ORDER BY updated_at DESC"
+    )
+    .fetch_all(pool.inner())
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let total = uids.len();
+    let mut outcomes = Vec::with_capacity(total);
+    for (index, uid) in uids.into_iter().enumerate() {
+        let _ = app.emit("sync-all-progress", SyncAllProgress { uid: uid.clone(), index, total });
+        let Some(_locked) = locks.try_lock(&uid) else {
+            let error = SyncError::already_running(&uid).to_string();
+            outcomes.push(AccountSyncOutcome { uid, count: 0, error: Some(error) });
+            continue;
+        };
+        let outcome = match sync_account_internal(pool.inner(), client.inner(), &uid, &mode, false, &[], None).await {
+            Ok(res) => AccountSyncOutcome { uid, count: res.count, error: None },
+            Err(e) => {
+                tracing::warn!("[sync] sync_all_accounts uid={} failed: {}", redact(&uid), e);
+                AccountSyncOutcome { uid, count: 0, error: Some(e) }
+            }
+        };
+        outcomes.push(outcome);
+    }
+
+    Ok(outcomes)
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct U8TokenRefreshOutcome {
+    pub uid: String,
+    /// Unix seconds the new token was stored at; `None` when the refresh failed.
+    pub fetched_at: Option<i64>,
+    pub error: Option<String>,
+}
+
+/// Fetch and store a fresh u8_token for every account with an OAuth token, so a following
+/// batch sync can reuse them. One account failing does not stop the others.
+#[tauri::command]
+pub async fn refresh_all_u8_tokens(
+    pool: State<'_, DbPool>,
+    client: State<'_, reqwest::Client>,
+) -> Result<Vec<U8TokenRefreshOutcome>, String> {
+    let accounts: Vec<(String, String, Option<i64>)> = sqlx::query_as(
+        "SELECT uid, oauth_token, channel_id FROM accounts WHERE oauth_token IS NOT NULL AND oauth_token != '' AND archived = 0 ORDER BY updated_at DESC"
+    )
+    .fetch_all(pool.inner())
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut outcomes = Vec::with_capacity(accounts.len());
+    for (uid, oauth_token, channel_id) in accounts {
+        let refreshed = match provider_from_channel_id(channel_id) {
+            Ok(provider) => refresh_u8_token(pool.inner(), client.inner(), &uid, &oauth_token, &provider, true).await,
+            Err(e) => Err(e),
+        };
+        let outcome = match refreshed {
+            Ok(_) => U8TokenRefreshOutcome { uid, fetched_at: Some(now_secs()), error: None },
+            Err(e) => {
+                tracing::warn!("[sync] refresh_all_u8_tokens uid={} failed: {}", redact(&uid), e);
+                U8TokenRefreshOutcome { uid, fetched_at: None, error: Some(e) }
+            }
+        };
+        outcomes.push(outcome);
+    }
+
+    Ok(outcomes)
+}
+
+/// Append a row to `sync_log`. Failures here are only logged so they never mask the
+/// sync's own result.
+async fn record_sync_log(
+    pool: &DbPool,
+    uid: Option<&str>,
+    started_at: i64,
+    mode: &str,
+    count: Option<usize>,
+    error: Option<&String>,
+) {
+    let res = sqlx::query(
+        "INSERT INTO sync_log (uid, started_at, finished_at, mode, count, success, error_message)
+         VALUES (?, ?, unixepoch(), ?, ?, ?, ?)"
+    )
+    .bind(uid)
+    .bind(started_at)
+    .bind(mode)
+    .bind(count.map(|c| c as i64))
+    .bind(error.is_none())
+    .bind(error)
+    .execute(pool)
+    .await;
+    if let Err(e) = res {
+        tracing::warn!("[sync] failed to write sync_log: {}", e);
+    }
+}
+
+/// Stamp `accounts.last_synced_at` after a sync has saved its records.
+async fn mark_account_synced(pool: &DbPool, uid: &str) -> Result<(), String> {
+    sqlx::query("UPDATE accounts SET last_synced_at = unixepoch() WHERE uid = ?")
+        .bind(uid)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[derive(sqlx::FromRow)]
+struct StoredGachaRow {
+    seq_id: String,
+    pool_type: String,
+    banner_id: String,
+    banner_name: String,
+    item_name: String,
+    item_id: Option<String>,
+    rarity: i64,
+    pulled_at: i64,
+    is_free: Option<bool>,
+    is_new: Option<bool>,
+}
+
+fn record_matches_row(r: &ApiGachaRecord, row: &StoredGachaRow) -> bool {
+    row.banner_id == r.pool_id
+        && row.banner_name == r.pool_name
+        && row.item_name == r.name
+        && row.item_id == r.item_id
+        && row.rarity == r.rarity
+        && row.pulled_at == r.pulled_at
+        && row.is_free == Some(r.is_free)
+        && row.is_new == Some(r.is_new)
+}
+
+fn classify_records(
+    existing: &HashMap<(String, String), StoredGachaRow>,
+    records: &[ApiGachaRecord],
+) -> SyncPreview {
+    let mut preview = SyncPreview::default();
+    for r in records {
+        match existing.get(&(r.seq_id.clone(), r.pool_type.clone())) {
+            None => preview.new_records += 1,
+            Some(row) if record_matches_row(r, row) => preview.unchanged += 1,
+            Some(_) => preview.updated_records += 1,
+        }
+    }
+    preview
+}
+
+/// Read-only counterpart of `save_gacha_records_internal`: reports what saving would do.
+async fn preview_gacha_records_internal(
+    pool: &DbPool,
+    uid: &str,
+    records: &[ApiGachaRecord],
+) -> Result<SyncPreview, String> {
+    if records.is_empty() {
+        return Ok(SyncPreview::default());
+    }
+
+    let rows = sqlx::query_as::<_, StoredGachaRow>(
+        "SELECT seq_id, pool_type, banner_id, banner_name, item_name, item_id, rarity, pulled_at, is_free, is_new
+         FROM gacha_pulls
+         WHERE uid = ? AND seq_id IS NOT NULL AND pool_type IS NOT NULL"
+    )
+    .bind(uid)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let existing: HashMap<(String, String), StoredGachaRow> = rows
+        .into_iter()
+        .map(|row| ((row.seq_id.clone(), row.pool_type.clone()), row))
+        .collect();
+
+    Ok(classify_records(&existing, records))
+}
+
 /// Internal function to save gacha records (mirrors db_save_gacha_records logic)
-async fn save_gacha_records_internal(
+pub(crate) async fn save_gacha_records_internal(
     pool: &DbPool,
     uid: &str,
     records: Vec<ApiGachaRecord>,
+    source: &str,
 ) -> Result<(), String> {
     if records.is_empty() {
         return Ok(());
     }
 
+    let record_count = records.len();
     let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
 
     for r in records {
         let affected = sqlx::query(
             "UPDATE gacha_pulls SET 
-                banner_id = ?, banner_name = ?, item_name = ?, item_id = ?, rarity = ?, pulled_at = ?, is_free = ?, is_new = ?
+                banner_id = ?, banner_name = ?, item_name = ?, item_id = ?, rarity = ?, pulled_at = ?, is_free = ?, is_new = ?,
+                source = COALESCE(NULLIF(source, 'unknown'), ?)
              WHERE uid = ? AND seq_id = ? AND pool_type = ?"
         )
         .bind(&r.pool_id)
@@ -629,6 +1007,7 @@ async fn save_gacha_records_internal(
         .bind(r.pulled_at)
         .bind(r.is_free)
         .bind(r.is_new)
+        .bind(source)
         .bind(uid)
         .bind(&r.seq_id)
         .bind(&r.pool_type)
@@ -639,8 +1018,8 @@ async fn save_gacha_records_internal(
 
         if affected == 0 {
             sqlx::query(
-                "INSERT INTO gacha_pulls (uid, banner_id, banner_name, item_name, item_id, rarity, pulled_at, seq_id, pool_type, is_free, is_new)
-                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+                "INSERT INTO gacha_pulls (uid, banner_id, banner_name, item_name, item_id, rarity, pulled_at, seq_id, pool_type, is_free, is_new, source)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
             )
             .bind(uid)
             .bind(&r.pool_id)
@@ -653,16 +1032,92 @@ async fn save_gacha_records_internal(
             .bind(&r.pool_type)
             .bind(r.is_free)
             .bind(r.is_new)
+            .bind(source)
             .execute(&mut *tx)
             .await
             .map_err(|e| e.to_string())?;
         }
     }
 
+    crate::database::drop_synthetic_duplicates(&mut tx, uid).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    crate::database::checkpoint_after_save(pool, record_count).await;
+    Ok(())
+}
+
+/// Merge per-language item names into `item_name_i18n`, keeping languages stored by
+/// earlier syncs.
+async fn save_item_names_i18n(
+    pool: &DbPool,
+    uid: &str,
+    names: &HashMap<(String, String), BTreeMap<String, String>>,
+) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    for ((seq_id, pool_type), by_lang) in names {
+        let patch = serde_json::to_string(by_lang).map_err(|e| e.to_string())?;
+        sqlx::query(
+            "UPDATE gacha_pulls SET item_name_i18n = json_patch(COALESCE(item_name_i18n, '{}'), ?)
+             WHERE uid = ? AND seq_id = ? AND pool_type = ?"
+        )
+        .bind(patch)
+        .bind(uid)
+        .bind(seq_id)
+        .bind(pool_type)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
     tx.commit().await.map_err(|e| e.to_string())?;
     Ok(())
 }
 
+/// Insert records whose `(seq_id, pool_type)` is not stored yet, leaving existing rows
+/// untouched. Returns how many rows were inserted.
+async fn insert_missing_gacha_records_internal(
+    pool: &DbPool,
+    uid: &str,
+    records: Vec<ApiGachaRecord>,
+    source: &str,
+) -> Result<usize, String> {
+    if records.is_empty() {
+        return Ok(0);
+    }
+
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let mut inserted = 0usize;
+
+    for r in records {
+        let affected = sqlx::query(
+            "INSERT INTO gacha_pulls (uid, banner_id, banner_name, item_name, item_id, rarity, pulled_at, seq_id, pool_type, is_free, is_new, source)
+             SELECT ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?
+             WHERE NOT EXISTS (SELECT 1 FROM gacha_pulls WHERE uid = ? AND seq_id = ? AND pool_type = ?)"
+        )
+        .bind(uid)
+        .bind(&r.pool_id)
+        .bind(&r.pool_name)
+        .bind(&r.name)
+        .bind(&r.item_id)
+        .bind(r.rarity)
+        .bind(r.pulled_at)
+        .bind(&r.seq_id)
+        .bind(&r.pool_type)
+        .bind(r.is_free)
+        .bind(r.is_new)
+        .bind(source)
+        .bind(uid)
+        .bind(&r.seq_id)
+        .bind(&r.pool_type)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?
+        .rows_affected();
+        inserted += affected as usize;
+    }
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(inserted)
+}
+
 // ───────────────────────────────────────────────────────────────────────────
 // sync_gacha_from_log - Sync using game log file
 // ───────────────────────────────────────────────────────────────────────────
@@ -672,6 +1127,7 @@ async fn save_gacha_records_internal(
 pub struct LogSyncResult {
     pub uid: String,
     pub count: usize,
+    pub truncated: bool,
 }
 
 /// Sync gacha records by parsing game log file.
@@ -679,52 +1135,57 @@ pub struct LogSyncResult {
 pub async fn sync_gacha_from_log(
     pool: State<'_, DbPool>,
     client: State<'_, reqwest::Client>,
+    tasks: State<'_, ActiveTasks>,
+    locks: State<'_, SyncLocks>,
     log_path: Option<String>,
     mode: String,
+) -> Result<LogSyncResult, String> {
+    let _running = tasks.begin();
+    let started_at = now_secs();
+    let result = sync_from_log_internal(pool.inner(), client.inner(), locks.inner(), log_path, &mode).await;
+    let (uid, count) = match &result {
+        Ok(r) => (Some(r.uid.as_str()), Some(r.count)),
+        Err(_) => (None, None),
+    };
+    record_sync_log(pool.inner(), uid, started_at, &format!("log:{mode}"), count, result.as_ref().err()).await;
+    result
+}
+
+async fn sync_from_log_internal(
+    pool: &DbPool,
+    client: &reqwest::Client,
+    locks: &SyncLocks,
+    log_path: Option<String>,
+    mode: &str,
 ) -> Result<LogSyncResult, String> {
     use std::path::PathBuf;
-    use std::fs::File;
-    use std::io::{Read, Seek, SeekFrom};
 
-    log_dev!("[sync] sync_gacha_from_log mode={}", mode);
+    tracing::info!("[sync] sync_gacha_from_log mode={}", mode);
 
     fn default_log_path() -> Result<PathBuf, String> {
         let home = std::env::var("USERPROFILE").map_err(|_| "无法获取 USERPROFILE")?;
         Ok(PathBuf::from(home).join("AppData/LocalLow/Hypergryph/Endfield/sdklogs/HGWebview.log"))
     }
 
-    fn read_tail(path: &std::path::Path, max: u64) -> Result<String, String> {
-        let mut f = File::open(path).map_err(|e| format!("无法打开日志: {}", e))?;
-        let len = f.metadata().map_err(|e| e.to_string())?.len();
-        f.seek(SeekFrom::Start(len.saturating_sub(max))).ok();
-        let mut buf = Vec::new();
-        f.read_to_end(&mut buf).map_err(|e| e.to_string())?;
-        Ok(String::from_utf8_lossy(&buf).to_string())
-    }
-
-    fn extract_url(text: &str) -> Option<String> {
-        for line in text.lines().rev() {
-            if line.contains("/page/gacha_") && line.contains("https://ef-webview.") {
-                if let Some(start) = line.find("https://ef-webview.") {
-                    let mut end = line.len();
-                    for (i, ch) in line[start..].char_indices() {
-                        if ch.is_whitespace() { end = start + i; break; }
-                    }
-                    return Some(line[start..end].trim_end_matches(|c: char| matches!(c, '"' | '\'' | ')' | ']' | '}')).to_string());
-                }
-            }
-        }
-        None
-    }
-
     let path = match log_path {
         Some(p) if !p.trim().is_empty() => PathBuf::from(p),
         _ => default_log_path()?,
     };
 
-    let text = read_tail(&path, 2 * 1024 * 1024)?;
-    let url_str = extract_url(&text).ok_or("未在日志中找到抽卡链接")?;
-    let parsed = tauri::Url::parse(&url_str).map_err(|e| format!("链接解析失败: {}", e))?;
+    let (url_str, _) = super::log::find_gacha_url_in_logs(&path, super::log::log_tail_bytes_from_config())?;
+    sync_from_gacha_url(pool, client, locks, &url_str, mode).await
+}
+
+/// Sync with the `u8_token` and `server_id` carried by an in-game gacha record URL.
+/// The uid is only known once the role list is queried; the per-uid lock is taken then.
+async fn sync_from_gacha_url(
+    pool: &DbPool,
+    client: &reqwest::Client,
+    locks: &SyncLocks,
+    url_str: &str,
+    mode: &str,
+) -> Result<LogSyncResult, String> {
+    let parsed = tauri::Url::parse(url_str).map_err(|e| format!("链接解析失败: {}", e))?;
 
     let q: HashMap<String, String> = parsed.query_pairs().map(|(k, v)| (k.to_string(), v.to_string())).collect();
     let u8_token = q.get("u8_token").cloned().ok_or("缺少 u8_token")?;
@@ -739,8 +1200,9 @@ pub async fn sync_gacha_from_log(
         return Err(format!("日志暂只支持国服，检测到 provider={}", provider));
     }
 
-    let role_info = query_role_list(&client, &u8_token, &server_id).await?;
+    let role_info = query_role_list(client, &u8_token, &server_id).await?;
     let uid = role_info.uid.clone();
+    let _locked = locks.try_lock(&uid).ok_or_else(|| SyncError::already_running(&uid).to_string())?;
 
     // Upsert account
     //
@@ -766,36 +1228,39 @@ pub async fn sync_gacha_from_log(
     .bind(&server_id)
     .bind(role_info.channel_id)
     .bind(&u8_token)
-    .execute(pool.inner())
+    .execute(pool)
     .await
     .map_err(|e| e.to_string())?;
 
-    let mut last_seq_map: HashMap<String, String> = HashMap::new();
-    if mode == "incremental" {
-        for (pt, sid) in sqlx::query_as::<_, (String, String)>("SELECT pool_type, seq_id FROM gacha_pulls WHERE uid=? AND seq_id IS NOT NULL ORDER BY pulled_at DESC LIMIT 1000").bind(&uid).fetch_all(pool.inner()).await.unwrap_or_default() {
-            last_seq_map.entry(pt).or_insert(sid);
-        }
-    }
+    let last_seq_map: HashMap<String, String> = if mode == "incremental" {
+        crate::database::incremental_cursors(pool, &uid).await.unwrap_or_default()
+    } else {
+        HashMap::new()
+    };
     if mode == "full" {
-        sqlx::query("DELETE FROM gacha_pulls WHERE uid=? AND pulled_at=0").bind(&uid).execute(pool.inner()).await.ok();
+        sqlx::query("DELETE FROM gacha_pulls WHERE uid=? AND pulled_at=0").bind(&uid).execute(pool).await.ok();
     }
 
     let pts = ["E_CharacterGachaPoolType_Special", "E_CharacterGachaPoolType_Standard", "E_CharacterGachaPoolType_Beginner"];
     let mut all: Vec<GachaRecord> = Vec::new();
+    let mut truncated = false;
+    let page_delay = request_delay_from_config();
     for pt in pts {
-        if let Ok(recs) = fetch_char_records_internal(&client, &u8_token, &server_id, pt, last_seq_map.get(pt).map(|s| s.as_str()), provider).await { all.extend(recs); }
+        if let Ok(f) = fetch_char_records_internal(client, &u8_token, &server_id, pt, pool_cursor(&last_seq_map, pt), provider, DEFAULT_LANG, Some(DEFAULT_MAX_RECORDS), page_delay).await { truncated |= f.truncated; all.extend(f.records); }
     }
-    if let Ok(pools) = fetch_weapon_pools_internal(&client, &u8_token, &server_id, provider).await {
-        for (pid, _) in pools {
-            if let Ok(recs) = fetch_weapon_records_internal(&client, &u8_token, &server_id, &pid, last_seq_map.get(&pid).map(|s| s.as_str()), provider).await { all.extend(recs); }
+    if let Ok(pools) = fetch_weapon_pools_internal(client, &u8_token, &server_id, provider, DEFAULT_LANG).await {
+        for (pid, pname) in pools {
+            if let Ok(f) = fetch_weapon_records_internal(client, &u8_token, &server_id, &pid, &pname, pool_cursor(&last_seq_map, &pid), provider, DEFAULT_LANG, Some(DEFAULT_MAX_RECORDS), page_delay).await { truncated |= f.truncated; all.extend(f.records); }
         }
     }
 
+    dedup_records(&mut all);
     if !all.is_empty() {
-        save_gacha_records_internal(pool.inner(), &uid, all.iter().cloned().map(gacha_to_api_record).collect()).await?;
+        save_gacha_records_internal(pool, &uid, all.iter().cloned().map(gacha_to_api_record).collect(), SOURCE_LOG).await?;
     }
+    mark_account_synced(pool, &uid).await?;
 
-    Ok(LogSyncResult { uid, count: all.len() })
+    Ok(LogSyncResult { uid, count: all.len(), truncated })
 }
 
 // ───────────────────────────────────────────────────────────────────────────
@@ -804,7 +1269,16 @@ pub async fn sync_gacha_from_log(
 
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
-pub struct AddedAccount { pub uid: String, pub role_id: String, pub nick_name: String, pub server_id: String }
+pub struct AddedAccount {
+    pub uid: String,
+    pub role_id: String,
+    pub nick_name: String,
+    pub server_id: String,
+    /// The binding's `channelMasterId` (1 = official, 2 = Bilibili, 6 = Gryphline)
+    pub channel_id: Option<i64>,
+    /// Provider later syncs of this account will use, derived from `channel_id`
+    pub provider: String,
+}
 
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -814,6 +1288,40 @@ fn app_code(provider: &str) -> &'static str {
     if provider == "gryphline" { "3dacefa138426cfe" } else { "be36d44aa36bfb5b" }
 }
 
+/// Exchange a user token for an OAuth token (the grant step of adding an account).
+async fn oauth_grant(client: &reqwest::Client, provider: &str, user_token: &str) -> Result<String, String> {
+    let grant = client.post(format!("https://as.{provider}.com/user/oauth2/v2/grant"))
+        .json(&serde_json::json!({"type": 1, "appCode": app_code(provider), "token": user_token}))
+        .send().await.map_err(|e| e.to_string())?
+        .json::<serde_json::Value>().await.map_err(|e| e.to_string())?;
+
+    let code = json_i64(&grant, "code").or_else(|| json_i64(&grant, "status")).unwrap_or(-1);
+    if code != 0 { return Err(grant.get("msg").and_then(|v| v.as_str()).unwrap_or("OAuth 换取失败").into()); }
+
+    parse_oauth_token(&grant).ok_or_else(|| "OAuth 响应缺少 token".into())
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenValidation { pub valid: bool, pub message: Option<String> }
+
+/// Check a pasted user token by running only the OAuth grant; nothing is stored.
+#[tauri::command]
+pub async fn hg_validate_token(
+    client: State<'_, reqwest::Client>,
+    token: String,
+    provider: Option<String>,
+) -> Result<TokenValidation, String> {
+    let provider = normalize_provider(provider)?;
+    let token = token.trim();
+    if token.is_empty() { return Ok(TokenValidation { valid: false, message: Some("missing token".into()) }); }
+
+    Ok(match oauth_grant(&client, &provider, token).await {
+        Ok(_) => TokenValidation { valid: true, message: None },
+        Err(e) => TokenValidation { valid: false, message: Some(e) },
+    })
+}
+
 #[tauri::command]
 pub async fn add_account_by_token(
     pool: State<'_, DbPool>,
@@ -825,15 +1333,7 @@ pub async fn add_account_by_token(
     let user_token = user_token.trim();
     if user_token.is_empty() { return Err("missing token".into()); }
 
-    let grant = client.post(format!("https://as.{provider}.com/user/oauth2/v2/grant"))
-        .json(&serde_json::json!({"type": 1, "appCode": app_code(&provider), "token": user_token}))
-        .send().await.map_err(|e| e.to_string())?
-        .json::<serde_json::Value>().await.map_err(|e| e.to_string())?;
-
-    let code = json_i64(&grant, "code").or_else(|| json_i64(&grant, "status")).unwrap_or(-1);
-    if code != 0 { return Err(grant.get("msg").and_then(|v| v.as_str()).unwrap_or("OAuth 换取失败").into()); }
-
-    let oauth = json_str(&grant, "/data/token").or_else(|| json_str(&grant, "/token")).ok_or("OAuth 响应缺少 token")?;
+    let oauth = oauth_grant(&client, &provider, user_token).await?;
 
     let bind = client.get(format!("https://binding-api-account-prod.{provider}.com/account/binding/v1/binding_list"))
         .query(&[("token", oauth.as_str()), ("appCode", "endfield")])
@@ -864,8 +1364,8 @@ pub async fn add_account_by_token(
                 let u8t = get_u8_token(&client, &uid, &oauth, &provider).await.ok();
 
                 sqlx::query(
-                    "INSERT INTO accounts (uid, role_id, nick_name, server_id, channel_id, user_token, oauth_token, u8_token, created_at, updated_at)
-                     VALUES (?, ?, ?, ?, ?, ?, ?, COALESCE(?, ''), unixepoch(), unixepoch())
+                    "INSERT INTO accounts (uid, role_id, nick_name, server_id, channel_id, user_token, oauth_token, u8_token, u8_token_fetched_at, created_at, updated_at)
+                     VALUES (?, ?, ?, ?, ?, ?, ?, COALESCE(?, ''), ?, unixepoch(), unixepoch())
                      ON CONFLICT(uid) DO UPDATE SET
                        role_id = COALESCE(excluded.role_id, role_id),
                        nick_name = COALESCE(excluded.nick_name, nick_name),
@@ -874,6 +1374,7 @@ pub async fn add_account_by_token(
                        user_token = CASE WHEN excluded.user_token != '' THEN excluded.user_token ELSE user_token END,
                        oauth_token = CASE WHEN excluded.oauth_token != '' THEN excluded.oauth_token ELSE oauth_token END,
                        u8_token = CASE WHEN excluded.u8_token != '' THEN excluded.u8_token ELSE u8_token END,
+                       u8_token_fetched_at = CASE WHEN excluded.u8_token != '' THEN excluded.u8_token_fetched_at ELSE u8_token_fetched_at END,
                        updated_at = unixepoch()"
                 )
                 .bind(&uid)
@@ -884,11 +1385,20 @@ pub async fn add_account_by_token(
                 .bind(user_token)
                 .bind(&oauth)
                 .bind(&u8t)
+                .bind(u8t.as_ref().map(|_| now_secs()))
                 .execute(pool.inner())
                 .await
                 .map_err(|e| e.to_string())?;
 
-                added.push(AddedAccount { uid: uid.clone(), role_id: rid, nick_name: nn, server_id: sid });
+                added.push(AddedAccount {
+                    uid: uid.clone(),
+                    role_id: rid,
+                    nick_name: nn,
+                    server_id: sid,
+                    channel_id: cmi,
+                    // The user picked the provider when adding, so it stands in for an unrecognised channel.
+                    provider: provider_from_channel_id(cmi).unwrap_or_else(|_| provider.clone()),
+                });
             }
         }
     }
@@ -896,3 +1406,284 @@ pub async fn add_account_by_token(
     if added.is_empty() { return Err("绑定列表中未解析到有效账户".into()); }
     Ok(AddAccountResult { accounts: added })
 }
+
+// ───────────────────────────────────────────────────────────────────────────
+// hg_import_token_from_clipboard - Add an account or sync from clipboard text
+// ───────────────────────────────────────────────────────────────────────────
+
+/// What clipboard text looks like: a gacha record URL carrying `u8_token`, or a user token
+/// (bare, or the `{"data":{"content": ...}}` JSON the token page returns).
+#[derive(Debug, PartialEq, Eq)]
+enum ClipboardToken {
+    GachaUrl(String),
+    UserToken(String),
+}
+
+fn detect_clipboard_token(text: &str) -> Option<ClipboardToken> {
+    let text = text.trim();
+    if let Some(start) = text.find("https://").or_else(|| text.find("http://")) {
+        let url: String = text[start..]
+            .chars()
+            .take_while(|c| !c.is_whitespace() && !matches!(c, '"' | '\'' | '<' | '>'))
+            .collect();
+        let has_u8_token = tauri::Url::parse(&url)
+            .map(|u| u.query_pairs().any(|(k, v)| k == "u8_token" && !v.is_empty()))
+            .unwrap_or(false);
+        if has_u8_token {
+            return Some(ClipboardToken::GachaUrl(url));
+        }
+    }
+
+    let token = match serde_json::from_str::<serde_json::Value>(text) {
+        Ok(json) => ["/data/content", "/data/token", "/content", "/token"]
+            .iter()
+            .find_map(|ptr| json_str(&json, ptr))?,
+        Err(_) => text.to_owned(),
+    };
+    let looks_like_token = (16..=512).contains(&token.len())
+        && token.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '/' | '=' | '-' | '_' | '.'));
+    looks_like_token.then_some(ClipboardToken::UserToken(token))
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ClipboardImport {
+    /// A user token, added through [`add_account_by_token`]
+    UserToken { accounts: Vec<AddedAccount> },
+    /// A gacha record URL, synced like a log import
+    GachaUrl { result: LogSyncResult },
+}
+
+/// Read the clipboard and import whatever HG credential it holds. `provider` applies to
+/// user tokens; `mode` ("incremental" by default) to gacha URLs.
+#[tauri::command]
+pub async fn hg_import_token_from_clipboard(
+    app: AppHandle,
+    pool: State<'_, DbPool>,
+    client: State<'_, reqwest::Client>,
+    tasks: State<'_, ActiveTasks>,
+    locks: State<'_, SyncLocks>,
+    provider: Option<String>,
+    mode: Option<String>,
+) -> Result<ClipboardImport, String> {
+    use tauri_plugin_clipboard_manager::ClipboardExt;
+
+    let text = app.clipboard().read_text().map_err(|e| format!("读取剪贴板失败: {e}"))?;
+    match detect_clipboard_token(&text).ok_or("剪贴板中没有识别到 token 或抽卡记录链接")? {
+        ClipboardToken::UserToken(token) => {
+            tracing::info!("[sync] clipboard import: user token len={}", token.len());
+            let added = add_account_by_token(pool, client, token, provider).await?;
+            Ok(ClipboardImport::UserToken { accounts: added.accounts })
+        }
+        ClipboardToken::GachaUrl(url) => {
+            tracing::info!("[sync] clipboard import: gacha url");
+            let _running = tasks.begin();
+            let mode = mode.unwrap_or_else(|| "incremental".to_owned());
+            let started_at = now_secs();
+            let result = sync_from_gacha_url(pool.inner(), client.inner(), locks.inner(), &url, &mode).await;
+            let (uid, count) = match &result {
+                Ok(r) => (Some(r.uid.as_str()), Some(r.count)),
+                Err(_) => (None, None),
+            };
+            record_sync_log(pool.inner(), uid, started_at, &format!("clipboard:{mode}"), count, result.as_ref().err()).await;
+            Ok(ClipboardImport::GachaUrl { result: result? })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_clipboard_token_recognizes_urls_and_tokens() {
+        let url = "https://ef-webview.hypergryph.com/record?u8_token=abc%2B1&server_id=1";
+        assert_eq!(
+            detect_clipboard_token(&format!("  \"{url}\"\n")),
+            Some(ClipboardToken::GachaUrl(url.to_owned()))
+        );
+        assert_eq!(
+            detect_clipboard_token(r#"{"code":0,"data":{"content":"AbCdEf0123456789+/=="}}"#),
+            Some(ClipboardToken::UserToken("AbCdEf0123456789+/==".to_owned()))
+        );
+        assert_eq!(
+            detect_clipboard_token("AbCdEf0123456789xyz"),
+            Some(ClipboardToken::UserToken("AbCdEf0123456789xyz".to_owned()))
+        );
+        assert_eq!(detect_clipboard_token("https://example.com/?a=1"), None);
+        assert_eq!(detect_clipboard_token("hello world, not a token"), None);
+    }
+
+    #[test]
+    fn resolve_server_id_prefers_explicit_then_stored() {
+        assert_eq!(resolve_server_id(Some("2"), Some("1")), "2");
+        assert_eq!(resolve_server_id(None, Some("3")), "3");
+        assert_eq!(resolve_server_id(None, Some("")), "1");
+        assert_eq!(resolve_server_id(None, None), "1");
+    }
+
+    #[test]
+    fn sync_locks_reject_second_sync_until_released() {
+        let locks = SyncLocks::default();
+        let first = locks.try_lock("uid_1").unwrap();
+        assert!(locks.try_lock("uid_1").is_none());
+        assert!(locks.try_lock("uid_2").is_some());
+        drop(first);
+        assert!(locks.try_lock("uid_1").is_some());
+    }
+
+    #[test]
+    fn provider_from_channel_id_maps_known_channels() {
+        assert_eq!(provider_from_channel_id(Some(1)).unwrap(), "hypergryph");
+        assert_eq!(provider_from_channel_id(Some(2)).unwrap(), "hypergryph");
+        assert_eq!(provider_from_channel_id(Some(6)).unwrap(), "gryphline");
+        assert_eq!(provider_from_channel_id(None).unwrap(), "hypergryph");
+        assert!(provider_from_channel_id(Some(99)).is_err());
+    }
+
+    #[tokio::test]
+    async fn dedup_records_drops_seq_id_repeated_across_pages() {
+        let pages = [
+            serde_json::json!({ "code": 0, "data": { "hasMore": true, "list": [
+                { "seqId": "20", "charName": "A", "rarity": 6 },
+                { "seqId": "19", "charName": "B", "rarity": 4 }
+            ] } }),
+            serde_json::json!({ "code": 0, "data": { "hasMore": false, "list": [
+                { "seqId": "19", "charName": "B", "rarity": 4 },
+                { "seqId": "18", "charName": "C", "rarity": 5 }
+            ] } }),
+        ];
+        let mut fetched = paginate_records(
+            |seq_id| {
+                let page = pages[usize::from(seq_id.is_some())].clone();
+                async move { Ok(page) }
+            },
+            |item| char_record_from_item(item, "E_CharacterGachaPoolType_Special"),
+            None,
+            None,
+            Duration::ZERO,
+            None,
+        )
+        .await
+        .unwrap()
+        .records;
+        assert_eq!(fetched.len(), 4);
+
+        dedup_records(&mut fetched);
+        let seq_ids: Vec<_> = fetched.iter().map(|r| r.seq_id.as_str()).collect();
+        assert_eq!(seq_ids, ["20", "19", "18"]);
+    }
+
+    fn api_record(seq_id: &str, pool_type: &str, name: &str) -> ApiGachaRecord {
+        ApiGachaRecord {
+            name: name.to_owned(),
+            item_id: Some("char_001".to_owned()),
+            rarity: 6,
+            pool_id: "pool_a".to_owned(),
+            pool_name: "Pool A".to_owned(),
+            seq_id: seq_id.to_owned(),
+            pulled_at: 1_700_000_000,
+            pool_type: pool_type.to_owned(),
+            is_free: false,
+            is_new: true,
+        }
+    }
+
+    fn stored_row(r: &ApiGachaRecord) -> StoredGachaRow {
+        StoredGachaRow {
+            seq_id: r.seq_id.clone(),
+            pool_type: r.pool_type.clone(),
+            banner_id: r.pool_id.clone(),
+            banner_name: r.pool_name.clone(),
+            item_name: r.name.clone(),
+            item_id: r.item_id.clone(),
+            rarity: r.rarity,
+            pulled_at: r.pulled_at,
+            is_free: Some(r.is_free),
+            is_new: Some(r.is_new),
+        }
+    }
+
+    #[test]
+    fn u8_token_freshness_respects_ttl() {
+        assert!(u8_token_is_fresh(Some(1_000), 1_000 + 1_799, 1_800));
+        assert!(!u8_token_is_fresh(Some(1_000), 1_000 + 1_800, 1_800));
+        assert!(!u8_token_is_fresh(None, 1_000, 1_800));
+        // A timestamp from the future (clock change) is not trusted.
+        assert!(!u8_token_is_fresh(Some(2_000), 1_000, 1_800));
+    }
+
+    #[test]
+    fn classify_records_splits_new_updated_unchanged() {
+        let same = api_record("1", "E_CharacterGachaPoolType_Special", "A");
+        let renamed = api_record("2", "E_CharacterGachaPoolType_Special", "B");
+        let mut existing = HashMap::new();
+        existing.insert((same.seq_id.clone(), same.pool_type.clone()), stored_row(&same));
+        let mut old = stored_row(&renamed);
+        old.item_name = "char_001".to_owned();
+        existing.insert((renamed.seq_id.clone(), renamed.pool_type.clone()), old);
+
+        // Same seq_id in a different pool_type is a different record.
+        let other_pool = api_record("1", "E_CharacterGachaPoolType_Standard", "A");
+        let fresh = api_record("3", "E_CharacterGachaPoolType_Special", "C");
+
+        let preview = classify_records(&existing, &[same, renamed, other_pool, fresh]);
+        assert_eq!(
+            preview,
+            SyncPreview { new_records: 2, updated_records: 1, unchanged: 1 }
+        );
+    }
+
+    #[tokio::test]
+    async fn item_names_i18n_merge_across_syncs() {
+        let pool = crate::database::test_pool().await;
+        sqlx::query("INSERT INTO gacha_pulls (uid, banner_id, banner_name, item_name, rarity, pulled_at, seq_id, pool_type) VALUES ('u', 'b', 'b', '佩丽卡', 6, 1, '1', 'p')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let key = ("1".to_string(), "p".to_string());
+        let zh = BTreeMap::from([("zh-cn".to_string(), "佩丽卡".to_string())]);
+        let en = BTreeMap::from([("en-us".to_string(), "Perlica".to_string())]);
+        save_item_names_i18n(&pool, "u", &HashMap::from([(key.clone(), zh)])).await.unwrap();
+        save_item_names_i18n(&pool, "u", &HashMap::from([(key, en)])).await.unwrap();
+
+        let name = |path: Option<&'static str>| {
+            let pool = pool.clone();
+            async move {
+                sqlx::query_scalar::<_, String>("SELECT COALESCE(json_extract(item_name_i18n, ?), item_name) FROM gacha_pulls")
+                    .bind(path)
+                    .fetch_one(&pool)
+                    .await
+                    .unwrap()
+            }
+        };
+        assert_eq!(name(Some("$.\"en-us\"")).await, "Perlica");
+        assert_eq!(name(Some("$.\"zh-cn\"")).await, "佩丽卡");
+        assert_eq!(name(Some("$.\"ja-jp\"")).await, "佩丽卡");
+        assert_eq!(name(None).await, "佩丽卡");
+    }
+
+    #[tokio::test]
+    async fn save_sets_source_without_overwriting_known_provenance() {
+        let pool = crate::database::test_pool().await;
+        sqlx::query("INSERT INTO gacha_pulls (uid, banner_id, banner_name, item_name, rarity, pulled_at, seq_id, pool_type) VALUES ('u', 'b', 'b', 'A', 4, 1, '1', 'p')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        save_gacha_records_internal(&pool, "u", vec![api_record("1", "p", "A"), api_record("2", "p", "B")], SOURCE_LOG)
+            .await
+            .unwrap();
+        save_gacha_records_internal(&pool, "u", vec![api_record("2", "p", "B"), api_record("3", "p", "C")], SOURCE_TOKEN)
+            .await
+            .unwrap();
+
+        let sources: Vec<(String, String)> = sqlx::query_as("SELECT seq_id, source FROM gacha_pulls ORDER BY seq_id")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        let expected = [("1", "log"), ("2", "log"), ("3", "token")].map(|(a, b)| (a.to_owned(), b.to_owned()));
+        assert_eq!(sources, expected);
+    }
+}
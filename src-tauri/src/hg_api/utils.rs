@@ -4,6 +4,15 @@ pub fn json_str(value: &Value, pointer: &str) -> Option<String> {
     value.pointer(pointer).and_then(|v| v.as_str()).map(ToOwned::to_owned)
 }
 
+/// OAuth token from a grant response. The endpoint has returned it as `data.token`,
+/// top-level `token` and `data.content`; blank values count as missing.
+pub fn parse_oauth_token(json: &Value) -> Option<String> {
+    ["/data/token", "/token", "/data/content"]
+        .iter()
+        .filter_map(|p| json_str(json, p))
+        .find(|t| !t.trim().is_empty())
+}
+
 pub fn json_i64(value: &Value, key: &str) -> Option<i64> {
     let v = value.get(key)?;
 
@@ -28,10 +37,142 @@ pub fn json_i64(value: &Value, key: &str) -> Option<i64> {
     None
 }
 
+const BODY_SNIPPET_CHARS: usize = 200;
+
+/// Parse a response body as JSON. Non-JSON bodies (e.g. a CDN's HTML 502 page)
+/// become an error carrying the HTTP status and the start of the body instead of
+/// serde's "expected value at line 1 column 1".
+pub fn parse_json_body(status: reqwest::StatusCode, body: &str) -> Result<Value, String> {
+    serde_json::from_str(body).map_err(|_| {
+        let trimmed = body.trim();
+        let mut snippet: String = trimmed.chars().take(BODY_SNIPPET_CHARS).collect();
+        if trimmed.chars().count() > BODY_SNIPPET_CHARS {
+            snippet.push('…');
+        }
+        format!("HTTP {status}: 响应不是有效的 JSON: {snippet}")
+    })
+}
+
+/// Read a response as text and parse it with [`parse_json_body`].
+pub async fn read_json(res: reqwest::Response) -> Result<Value, String> {
+    let status = res.status();
+    let body = res.text().await.map_err(|e| e.to_string())?;
+    parse_json_body(status, &body)
+}
+
+/// Params sorted by key (then value) and joined as `k=v&k2=v2`, the string HG signatures cover.
+#[cfg_attr(not(feature = "hg-sign"), allow(dead_code))]
+pub fn canonical_params<K: AsRef<str>, V: AsRef<str>>(params: &[(K, V)]) -> String {
+    let mut pairs: Vec<(&str, &str)> = params.iter().map(|(k, v)| (k.as_ref(), v.as_ref())).collect();
+    pairs.sort();
+    pairs
+        .iter()
+        .map(|(k, v)| format!("{k}={v}"))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// `md5(hex(hmac_sha256(secret, canonical_params + timestamp)))`, the scheme other HG services use.
+#[cfg(feature = "hg-sign")]
+fn sign_with_timestamp<K: AsRef<str>, V: AsRef<str>>(params: &[(K, V)], secret: &str, timestamp: i64) -> String {
+    use hmac::{Hmac, Mac};
+    use md5::{Digest, Md5};
+
+    let mut mac = Hmac::<sha2::Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(canonical_params(params).as_bytes());
+    mac.update(timestamp.to_string().as_bytes());
+    let hmac_hex: String = mac.finalize().into_bytes().iter().map(|b| format!("{b:02x}")).collect();
+    Md5::digest(hmac_hex.as_bytes()).iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Sign `params` with the current unix time. Returns `(timestamp, sign)`.
+#[cfg(feature = "hg-sign")]
+pub fn sign_request<K: AsRef<str>, V: AsRef<str>>(params: &[(K, V)], secret: &str) -> (String, String) {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    (timestamp.to_string(), sign_with_timestamp(params, secret, timestamp))
+}
+
+/// Query params for the gacha endpoints. With the `hg-sign` feature, `timestamp` and `sign`
+/// are appended, keyed by the request's `token`.
+pub fn signed_query<K: AsRef<str>, V: AsRef<str>>(params: &[(K, V)]) -> Vec<(String, String)> {
+    #[allow(unused_mut)]
+    let mut query: Vec<(String, String)> = params
+        .iter()
+        .map(|(k, v)| (k.as_ref().to_owned(), v.as_ref().to_owned()))
+        .collect();
+    #[cfg(feature = "hg-sign")]
+    {
+        let secret = query.iter().find(|(k, _)| k == "token").map(|(_, v)| v.clone()).unwrap_or_default();
+        let (timestamp, sign) = sign_request(params, &secret);
+        query.push(("timestamp".to_owned(), timestamp));
+        query.push(("sign".to_owned(), sign));
+    }
+    query
+}
+
+/// Mask a secret for logging, keeping only the first and last 4 chars.
+pub fn redact(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    if chars.len() <= 8 {
+        return format!("***(len={})", chars.len());
+    }
+    let head: String = chars[..4].iter().collect();
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("{head}***{tail}(len={})", chars.len())
+}
+
+const SENSITIVE_KEYS: &[&str] = &[
+    "token",
+    "uid",
+    "roleid",
+    "role_id",
+    "nickname",
+    "nick_name",
+    "content",
+    "cookie",
+];
+
+/// Copy of `value` with tokens, uids and nicknames masked via [`redact`], for debug dumps.
+pub fn redact_json(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| {
+                    let sensitive = SENSITIVE_KEYS.contains(&k.to_lowercase().as_str());
+                    let v = match v {
+                        Value::String(s) if sensitive => Value::String(redact(s)),
+                        Value::Number(n) if sensitive => Value::String(redact(&n.to_string())),
+                        _ => redact_json(v),
+                    };
+                    (k.clone(), v)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(redact_json).collect()),
+        _ => value.clone(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn parse_json_body_reports_status_and_snippet() {
+        let ok = parse_json_body(reqwest::StatusCode::OK, r#"{"code":0}"#).unwrap();
+        assert_eq!(json_i64(&ok, "code"), Some(0));
+
+        let html = format!("<html>{}</html>", "x".repeat(500));
+        let err = parse_json_body(reqwest::StatusCode::BAD_GATEWAY, &html).unwrap_err();
+        assert!(err.starts_with("HTTP 502 Bad Gateway"));
+        assert!(err.contains("<html>"));
+        assert!(err.ends_with('…'));
+        assert!(err.len() < 300);
+    }
+
     #[test]
     fn json_i64_accepts_number_and_string() {
         let v: Value = serde_json::json!({
@@ -47,4 +188,56 @@ mod tests {
         assert_eq!(json_i64(&v, "d"), Some(42));
         assert_eq!(json_i64(&v, "missing"), None);
     }
+
+    #[test]
+    fn redact_masks_tokens_and_nested_fields() {
+        assert_eq!(redact("abcd1234efgh"), "abcd***efgh(len=12)");
+        assert_eq!(redact("short"), "***(len=5)");
+
+        let v = serde_json::json!({
+            "status": 0,
+            "data": {
+                "token": "abcd1234efgh",
+                "list": [{ "uid": "1234567890", "roles": [{ "nickName": "Doctor", "serverId": "1" }] }]
+            }
+        });
+        let r = redact_json(&v);
+        assert_eq!(r["status"], 0);
+        assert_eq!(r["data"]["token"], "abcd***efgh(len=12)");
+        assert_eq!(r["data"]["list"][0]["uid"], "1234***7890(len=10)");
+        assert_eq!(r["data"]["list"][0]["roles"][0]["nickName"], "***(len=6)");
+        assert_eq!(r["data"]["list"][0]["roles"][0]["serverId"], "1");
+    }
+
+    #[test]
+    fn canonical_params_sorts_by_key_then_value() {
+        let params = [("token", "t"), ("pool_type", "b"), ("lang", "zh-cn"), ("pool_type", "a")];
+        assert_eq!(canonical_params(&params), "lang=zh-cn&pool_type=a&pool_type=b&token=t");
+
+        let reordered = [("pool_type", "a"), ("token", "t"), ("pool_type", "b"), ("lang", "zh-cn")];
+        assert_eq!(canonical_params(&reordered), canonical_params(&params));
+        assert_eq!(canonical_params::<&str, &str>(&[]), "");
+    }
+
+    #[cfg(feature = "hg-sign")]
+    #[test]
+    fn sign_is_order_independent_and_keyed() {
+        let a = sign_with_timestamp(&[("b", "2"), ("a", "1")], "secret", 1_700_000_000);
+        let b = sign_with_timestamp(&[("a", "1"), ("b", "2")], "secret", 1_700_000_000);
+        assert_eq!(a, b);
+        assert_eq!(a.len(), 32);
+        assert_ne!(a, sign_with_timestamp(&[("a", "1"), ("b", "2")], "other", 1_700_000_000));
+        assert_ne!(a, sign_with_timestamp(&[("a", "1"), ("b", "2")], "secret", 1_700_000_001));
+    }
+
+
+    #[test]
+    fn parse_oauth_token_checks_known_shapes() {
+        assert_eq!(parse_oauth_token(&serde_json::json!({ "data": { "token": "a" } })), Some("a".into()));
+        assert_eq!(parse_oauth_token(&serde_json::json!({ "token": "b" })), Some("b".into()));
+        assert_eq!(parse_oauth_token(&serde_json::json!({ "data": { "content": "c" } })), Some("c".into()));
+        assert_eq!(parse_oauth_token(&serde_json::json!({ "data": { "token": "", "content": "c" } })), Some("c".into()));
+        assert_eq!(parse_oauth_token(&serde_json::json!({ "data": {} })), None);
+    }
+
 }
@@ -2,29 +2,32 @@ use tauri::utils::config::WebviewUrl;
 use tauri::WebviewWindowBuilder;
 use tauri::{AppHandle, Emitter, Manager, Url, WebviewWindow};
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tokio::sync::oneshot;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::path::{Path, PathBuf};
 use reqwest::header;
 
-macro_rules! log_dev {
-    ($($arg:tt)*) => {
-        if cfg!(debug_assertions) {
-            println!($($arg)*);
-        }
-    };
-}
-
 #[derive(Clone, Copy, PartialEq, Eq)]
 enum LoginProvider {
     Hypergryph,
     Gryphline,
+    /// Bilibili-channel (B 服) accounts: log in through Bilibili, token from the hypergryph web-api.
+    Bilibili,
 }
 
-fn normalize_provider(provider: Option<String>) -> Result<LoginProvider, String> {
+fn normalize_provider(provider: Option<String>, channel: Option<String>) -> Result<LoginProvider, String> {
     let raw = provider.unwrap_or_else(|| "hypergryph".to_owned());
     let p = raw.trim().to_lowercase();
-    match p.as_str() {
-        "hypergryph" => Ok(LoginProvider::Hypergryph),
-        "gryphline" => Ok(LoginProvider::Gryphline),
+    let bilibili = channel
+        .as_deref()
+        .map(|c| c.trim().eq_ignore_ascii_case("bilibili"))
+        .unwrap_or(false);
+    match (p.as_str(), bilibili) {
+        ("hypergryph", false) => Ok(LoginProvider::Hypergryph),
+        ("hypergryph", true) => Ok(LoginProvider::Bilibili),
+        ("gryphline", false) => Ok(LoginProvider::Gryphline),
+        ("gryphline", true) => Err("bilibili channel is only available for hypergryph".to_owned()),
         _ => Err(format!("unsupported provider: {raw}")),
     }
 }
@@ -33,6 +36,7 @@ fn provider_login_url(provider: LoginProvider) -> &'static str {
     match provider {
         LoginProvider::Hypergryph => "https://user.hypergryph.com/",
         LoginProvider::Gryphline => "https://user.gryphline.com/login",
+        LoginProvider::Bilibili => "https://user.hypergryph.com/bilibili/login",
     }
 }
 
@@ -40,6 +44,7 @@ fn provider_userinfo_url(provider: LoginProvider) -> &'static str {
     match provider {
         LoginProvider::Hypergryph => "https://user.hypergryph.com/userInfo",
         LoginProvider::Gryphline => "https://user.gryphline.com/userInfo",
+        LoginProvider::Bilibili => "https://user.hypergryph.com/bilibili/userInfo",
     }
 }
 
@@ -47,6 +52,7 @@ fn provider_token_url(provider: LoginProvider) -> &'static str {
     match provider {
         LoginProvider::Hypergryph => "https://web-api.hypergryph.com/account/info/hg",
         LoginProvider::Gryphline => "https://web-api.gryphline.com/cookie_store/account_token",
+        LoginProvider::Bilibili => "https://web-api.hypergryph.com/account/info/ef-b",
     }
 }
 
@@ -54,6 +60,7 @@ fn provider_id(provider: LoginProvider) -> u64 {
     match provider {
         LoginProvider::Hypergryph => 0,
         LoginProvider::Gryphline => 1,
+        LoginProvider::Bilibili => 2,
     }
 }
 
@@ -61,6 +68,12 @@ fn host_allowed(provider: LoginProvider, host: &str) -> bool {
     match provider {
         LoginProvider::Hypergryph => host.contains("hypergryph.com") || host.contains("hycdn.cn"),
         LoginProvider::Gryphline => host.contains("gryphline.com") || host.contains("hg-cdn.com"),
+        LoginProvider::Bilibili => {
+            host.contains("hypergryph.com")
+                || host.contains("hycdn.cn")
+                || host.contains("bilibili.com")
+                || host.contains("biligame.com")
+        }
     }
 }
 
@@ -68,6 +81,7 @@ fn is_userinfo_request(provider: LoginProvider, host: &str, path: &str) -> bool
     match provider {
         LoginProvider::Hypergryph => host.contains("user.hypergryph.com") && path.starts_with("/userInfo"),
         LoginProvider::Gryphline => host.contains("user.gryphline.com") && path.starts_with("/userInfo"),
+        LoginProvider::Bilibili => host.contains("user.hypergryph.com") && path.starts_with("/bilibili/userInfo"),
     }
 }
 
@@ -75,21 +89,57 @@ fn is_token_request(provider: LoginProvider, host: &str, path: &str) -> bool {
     match provider {
         LoginProvider::Hypergryph => host.contains("web-api.hypergryph.com") && path.starts_with("/account/info/hg"),
         LoginProvider::Gryphline => host.contains("web-api.gryphline.com") && path.starts_with("/cookie_store/account_token"),
+        LoginProvider::Bilibili => host.contains("web-api.hypergryph.com") && path.starts_with("/account/info/ef-b"),
     }
 }
 
 const ENDCAT_SCHEME: &str = "endcat";
-const AUTH_UA: &str =
-    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36 Edg/120.0.0.0";
 
 fn clear_hg_webview(win: &WebviewWindow) {
     if let Err(e) = win.clear_all_browsing_data() {
-        log_dev!("[hg-auth] clear_all_browsing_data failed: {e}");
+        tracing::warn!("[hg-auth] clear_all_browsing_data failed: {e}");
     }
     let _ = win.eval(
         "try { localStorage.clear?.(); sessionStorage.clear?.(); if (window.indexedDB?.databases) { indexedDB.databases().then(dbs => dbs.forEach(db => indexedDB.deleteDatabase(db.name))).catch(() => {}); } } catch (_) {}",
     );
 }
+/// Pending `hg_wait_for_token` caller; managed as Tauri state.
+#[derive(Default)]
+pub struct TokenWaiter(Mutex<Option<oneshot::Sender<String>>>);
+
+/// Drop a pending `hg_wait_for_token` sender so the caller fails right away.
+fn cancel_token_waiter(app: &AppHandle) {
+    if let Some(waiter) = app.try_state::<TokenWaiter>() {
+        if let Ok(mut slot) = waiter.0.lock() {
+            slot.take();
+        }
+    }
+}
+
+/// `Destroyed` handler for the auth window: a window closed by the user ends a pending
+/// `hg_wait_for_token` with "登录已取消" instead of leaving it to run out its timeout.
+/// Skipped when a replacement window (provider switch) is already open.
+pub fn on_auth_window_destroyed(app: &AppHandle) {
+    if app.get_webview_window("hg-auth").is_none() {
+        cancel_token_waiter(app);
+    }
+}
+
+/// Hand a captured token to the frontend (`hg:auto-token` event and any pending
+/// `hg_wait_for_token` call), then close the auth window.
+fn deliver_token(app: &AppHandle, token: String) {
+    let _ = app.emit_to("main", "hg:auto-token", token.clone());
+    if let Some(waiter) = app.try_state::<TokenWaiter>() {
+        if let Some(tx) = waiter.0.lock().ok().and_then(|mut slot| slot.take()) {
+            let _ = tx.send(token);
+        }
+    }
+    if let Some(win) = app.get_webview_window("hg-auth") {
+        clear_hg_webview(&win);
+        let _ = win.close();
+    }
+}
+
 fn auth_init_js(provider: LoginProvider) -> String {
     let userinfo_url = provider_userinfo_url(provider);
     let token_url = provider_token_url(provider);
@@ -167,17 +217,17 @@ fn maybe_set_disable_gpu() {
             .map(|v| v.contains("--disable-gpu"))
             .unwrap_or(false)
         {
-            log_dev!("[hg-auth] clearing WEBVIEW2_ADDITIONAL_BROWSER_ARGUMENTS (contained --disable-gpu)");
+            tracing::debug!("[hg-auth] clearing WEBVIEW2_ADDITIONAL_BROWSER_ARGUMENTS (contained --disable-gpu)");
             env::remove_var("WEBVIEW2_ADDITIONAL_BROWSER_ARGUMENTS");
         } else {
-            log_dev!("[hg-auth] WEBVIEW2 disable-gpu not forced (set ENDCAT_FORCE_WEBVIEW_DISABLE_GPU=1 to enable)");
+            tracing::debug!("[hg-auth] WEBVIEW2 disable-gpu not forced (set ENDCAT_FORCE_WEBVIEW_DISABLE_GPU=1 to enable)");
         }
         return;
     }
 
     let args = env::var("WEBVIEW2_ADDITIONAL_BROWSER_ARGUMENTS").unwrap_or_default();
     if args.contains("--disable-gpu") {
-        log_dev!("[hg-auth] WEBVIEW2_ADDITIONAL_BROWSER_ARGUMENTS already has --disable-gpu");
+        tracing::debug!("[hg-auth] WEBVIEW2_ADDITIONAL_BROWSER_ARGUMENTS already has --disable-gpu");
         return;
     }
 
@@ -187,39 +237,28 @@ fn maybe_set_disable_gpu() {
         format!("{args} --disable-gpu")
     };
     env::set_var("WEBVIEW2_ADDITIONAL_BROWSER_ARGUMENTS", &merged);
-    log_dev!("[hg-auth] set WEBVIEW2_ADDITIONAL_BROWSER_ARGUMENTS={}", merged);
+    tracing::debug!("[hg-auth] set WEBVIEW2_ADDITIONAL_BROWSER_ARGUMENTS={}", merged);
 }
 
 #[cfg(not(target_os = "windows"))]
 fn maybe_set_disable_gpu() {}
 
-async fn fetch_token_with_cookie(cookie_header: String, provider: LoginProvider) -> Option<String> {
-    log_dev!(
-        "[hg-auth] fetch_token_with_cookie: len={} preview={}",
-        cookie_header.len(),
-        cookie_header
-            .chars()
-            .take(120)
-            .collect::<String>()
-            .replace('\n', "")
-    );
-    let client = reqwest::Client::builder()
-        .user_agent(AUTH_UA)
-        .timeout(Duration::from_secs(10))
-        .build()
-        .ok()?;
+async fn fetch_token_with_cookie(app: &AppHandle, cookie_header: String, provider: LoginProvider) -> Option<String> {
+    tracing::debug!("[hg-auth] fetch_token_with_cookie: len={}", cookie_header.len());
+    let client = app.state::<reqwest::Client>();
 
     let token_url = provider_token_url(provider);
 
     let res = client
         .get(token_url)
         .header(reqwest::header::COOKIE, cookie_header)
+        .timeout(Duration::from_secs(10))
         .send()
         .await
         .ok()?;
 
     if !res.status().is_success() {
-        log_dev!("[hg-auth] token fetch failed status {}", res.status());
+        tracing::warn!("[hg-auth] token fetch failed status {}", res.status());
         return None;
     }
 
@@ -242,7 +281,11 @@ async fn fetch_token_with_cookie(cookie_header: String, provider: LoginProvider)
         })
         .or_else(|| json.get("content").and_then(|v| v.as_str()).map(|s| s.to_string()));
     if token.as_deref().unwrap_or("").is_empty() {
-        log_dev!("[hg-auth] token fetch json missing token: {:?}", json);
+        let keys: Vec<&str> = json
+            .as_object()
+            .map(|o| o.keys().map(|k| k.as_str()).collect())
+            .unwrap_or_default();
+        tracing::warn!("[hg-auth] token fetch json missing token, keys={:?}", keys);
     }
     token
 }
@@ -258,6 +301,68 @@ static LAST_COOKIE_FETCH_MS: AtomicU64 = AtomicU64::new(0);
 static LAST_REQ_LOG_MS: AtomicU64 = AtomicU64::new(0);
 static LAST_USERINFO_NAV_MS: AtomicU64 = AtomicU64::new(0);
 static LAST_LOGIN_PROVIDER: AtomicU64 = AtomicU64::new(0);
+/// Bumped each time the auth window is built, so a stale auto-close timer can tell it is outdated.
+static AUTH_WINDOW_GEN: AtomicU64 = AtomicU64::new(0);
+
+const DEFAULT_AUTH_TIMEOUT_SECS: u64 = 5 * 60;
+
+/// How long the auth window may stay open without a token, from config `authTimeoutSeconds`.
+fn auth_timeout() -> Duration {
+    let secs = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.to_path_buf()))
+        .and_then(|dir| crate::services::config::read_config_field::<u64>(&dir, "authTimeoutSeconds"))
+        .filter(|secs| *secs > 0)
+        .unwrap_or(DEFAULT_AUTH_TIMEOUT_SECS);
+    Duration::from_secs(secs)
+}
+
+/// Close the auth window and emit `hg:auth-timeout` if it is still open after `auth_timeout()`.
+fn schedule_auth_timeout(app: &AppHandle) {
+    let generation = AUTH_WINDOW_GEN.fetch_add(1, Ordering::Relaxed) + 1;
+    let app = app.clone();
+    let timeout = auth_timeout();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(timeout).await;
+        if AUTH_WINDOW_GEN.load(Ordering::Relaxed) != generation {
+            return;
+        }
+        if let Some(win) = app.get_webview_window("hg-auth") {
+            tracing::info!("[hg-auth] no token after {:?}, closing auth window", timeout);
+            clear_hg_webview(&win);
+            let _ = win.close();
+            let _ = app.emit_to("main", "hg:auth-timeout", ());
+            // Release a pending hg_wait_for_token instead of leaving it to its own timeout.
+            cancel_token_waiter(&app);
+        }
+    });
+}
+
+/// Per-session webview profiles live in `<data_dir>/webview/hg-auth-<millis>`.
+const AUTH_PROFILE_PREFIX: &str = "hg-auth-";
+
+/// Remove profiles left by earlier auth windows. Best effort: a profile still locked by a
+/// closing webview is retried on the next open.
+fn prune_auth_profiles(root: &Path) {
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if entry.file_name().to_string_lossy().starts_with(AUTH_PROFILE_PREFIX) {
+            if let Err(e) = std::fs::remove_dir_all(entry.path()) {
+                tracing::debug!("[hg-auth] keep profile {}: {e}", entry.path().display());
+            }
+        }
+    }
+}
+
+/// 每次登录使用全新的 WebView 数据目录，避免上一个账号的 Cookie 在清理失败时被复用。
+fn new_auth_profile_dir() -> Option<PathBuf> {
+    let exe_dir = std::env::current_exe().ok()?.parent()?.to_path_buf();
+    let root = crate::services::config::resolve_data_dir(&exe_dir).join("webview");
+    prune_auth_profiles(&root);
+    Some(root.join(format!("{AUTH_PROFILE_PREFIX}{}", now_millis())))
+}
 
 fn open_hg_auth_window(app: &AppHandle, provider: LoginProvider) -> Result<(), String> {
     if let Some(win) = app.get_webview_window("hg-auth") {
@@ -286,7 +391,7 @@ fn open_hg_auth_window(app: &AppHandle, provider: LoginProvider) -> Result<(), S
     let app_for_nav = app.clone();
     let provider_for_nav = provider;
 
-    log_dev!(
+    tracing::debug!(
         "[hg-auth] building webview: target={}, gpu_flag={:?}",
         login_url_str,
         std::env::var("WEBVIEW2_ADDITIONAL_BROWSER_ARGUMENTS").ok()
@@ -303,8 +408,13 @@ fn open_hg_auth_window(app: &AppHandle, provider: LoginProvider) -> Result<(), S
         .resizable(true)
         .decorations(true)
         .closable(true)
-        .user_agent(AUTH_UA)
-        .initialization_script_for_all_frames(init_js)
+        .user_agent(&crate::services::http::user_agent())
+        .initialization_script_for_all_frames(init_js);
+    if let Some(dir) = new_auth_profile_dir() {
+        tracing::debug!("[hg-auth] webview profile {}", dir.display());
+        builder = builder.data_directory(dir);
+    }
+    builder = builder
         .on_web_resource_request(move |request, _response| {
             let uri = request.uri();
             let host = uri.host().unwrap_or_default();
@@ -322,7 +432,7 @@ fn open_hg_auth_window(app: &AppHandle, provider: LoginProvider) -> Result<(), S
             let log_now = now_millis();
             if log_now.saturating_sub(LAST_REQ_LOG_MS.load(Ordering::Relaxed)) > 1500 {
                 LAST_REQ_LOG_MS.store(log_now, Ordering::Relaxed);
-                log_dev!("[hg-auth] web_request {}{}", host, path);
+                tracing::debug!("[hg-auth] web_request {}{}", host, path);
             }
 
             // Throttle to avoid hammering.
@@ -332,7 +442,7 @@ fn open_hg_auth_window(app: &AppHandle, provider: LoginProvider) -> Result<(), S
                 let last_nav = LAST_USERINFO_NAV_MS.load(Ordering::Relaxed);
                 if now.saturating_sub(last_nav) > 1200 {
                     LAST_USERINFO_NAV_MS.store(now, Ordering::Relaxed);
-                    log_dev!("[hg-auth] detected userInfo navigation, forcing token URL");
+                    tracing::info!("[hg-auth] detected userInfo navigation, forcing token URL");
                     if let Some(win) = app_for_req.get_webview_window("hg-auth") {
                         let _ = win.eval(&format!(
                             "try {{ location.href = '{}'; }} catch (_) {{}}",
@@ -359,13 +469,13 @@ fn open_hg_auth_window(app: &AppHandle, provider: LoginProvider) -> Result<(), S
 
             if cookies_combined.trim().is_empty() {
                 if is_token_req {
-                    log_dev!("[hg-auth] token request observed but cookie header empty");
+                    tracing::warn!("[hg-auth] token request observed but cookie header empty");
                 }
                 return;
             }
 
             LAST_COOKIE_FETCH_MS.store(now, Ordering::Relaxed);
-            log_dev!(
+            tracing::debug!(
                 "[hg-auth] on_web_resource_request cookies from {}{} len={} (token_req={})",
                 host,
                 path,
@@ -374,17 +484,19 @@ fn open_hg_auth_window(app: &AppHandle, provider: LoginProvider) -> Result<(), S
             );
             let app_for_fetch = app_for_req.clone();
             tauri::async_runtime::spawn(async move {
-                if let Some(token) = fetch_token_with_cookie(cookies_combined, provider_for_req).await {
-                    let _ = app_for_fetch.emit_to("main", "hg:auto-token", token);
-                    if let Some(win) = app_for_fetch.get_webview_window("hg-auth") {
-                        clear_hg_webview(&win);
-                        let _ = win.close();
-                    }
+                if let Some(token) = fetch_token_with_cookie(&app_for_fetch, cookies_combined, provider_for_req).await {
+                    deliver_token(&app_for_fetch, token);
                 }
             });
         })
         .on_navigation(move |url| {
-            log_dev!("[hg-auth] navigating {}", url);
+            // Query strings may carry tokens, so only the path is logged.
+            tracing::debug!(
+                "[hg-auth] navigating {}://{}{}",
+                url.scheme(),
+                url.host_str().unwrap_or_default(),
+                url.path()
+            );
             if url.scheme() != ENDCAT_SCHEME {
                 return true;
             }
@@ -407,11 +519,7 @@ fn open_hg_auth_window(app: &AppHandle, provider: LoginProvider) -> Result<(), S
                     .unwrap_or_default();
 
                 if !token.trim().is_empty() {
-                    let _ = app_for_nav.emit_to("main", "hg:auto-token", token);
-                    if let Some(win) = app_for_nav.get_webview_window("hg-auth") {
-                        clear_hg_webview(&win);
-                        let _ = win.close();
-                    }
+                    deliver_token(&app_for_nav, token);
                 }
             }
             if host == "hg-cookies" {
@@ -423,12 +531,8 @@ fn open_hg_auth_window(app: &AppHandle, provider: LoginProvider) -> Result<(), S
                     let app_for_fetch = app_for_nav.clone();
                     let provider_for_fetch = provider_for_nav;
                     tauri::async_runtime::spawn(async move {
-                        if let Some(token) = fetch_token_with_cookie(cookies, provider_for_fetch).await {
-                            let _ = app_for_fetch.emit_to("main", "hg:auto-token", token);
-                            if let Some(win) = app_for_fetch.get_webview_window("hg-auth") {
-                                clear_hg_webview(&win);
-                                let _ = win.close();
-                            }
+                        if let Some(token) = fetch_token_with_cookie(&app_for_fetch, cookies, provider_for_fetch).await {
+                            deliver_token(&app_for_fetch, token);
                         }
                     });
                 }
@@ -443,8 +547,11 @@ fn open_hg_auth_window(app: &AppHandle, provider: LoginProvider) -> Result<(), S
         })
         .on_page_load(move |window, payload| {
             let url = payload.url();
-            let url_str = url.as_str();
-            log_dev!("[hg-auth] page loaded {}", url_str);
+            tracing::debug!(
+                "[hg-auth] page loaded {}{}",
+                url.host_str().unwrap_or_default(),
+                url.path()
+            );
             let _ = window.eval("window.__ENDCAT_PAGE_LOADED__ = true;");
         });
 
@@ -453,10 +560,11 @@ fn open_hg_auth_window(app: &AppHandle, provider: LoginProvider) -> Result<(), S
 
     let win = builder.build().map_err(|e| e.to_string())?;
     LAST_LOGIN_PROVIDER.store(provider_id(provider), Ordering::Relaxed);
+    schedule_auth_timeout(app);
 
     match win.navigate(login_url) {
-        Ok(()) => log_dev!("[hg-auth] navigate() issued to {}", login_url_str),
-        Err(err) => log_dev!("[hg-auth] navigate() failed to {}: {}", login_url_str, err),
+        Ok(()) => tracing::debug!("[hg-auth] navigate() issued to {}", login_url_str),
+        Err(err) => tracing::warn!("[hg-auth] navigate() failed to {}: {}", login_url_str, err),
     }
 
     // Fallback: if stuck on about:blank, navigate to login page
@@ -477,17 +585,57 @@ fn open_hg_auth_window(app: &AppHandle, provider: LoginProvider) -> Result<(), S
 }
 
 #[tauri::command]
-pub async fn hg_open_token_webview(app: AppHandle, provider: Option<String>) -> Result<(), String> {
-    let provider = normalize_provider(provider)?;
+pub async fn hg_open_token_webview(
+    app: AppHandle,
+    provider: Option<String>,
+    channel: Option<String>,
+) -> Result<(), String> {
+    let provider = normalize_provider(provider, channel)?;
     let handle = app.clone();
     app.run_on_main_thread(move || {
         if let Err(e) = open_hg_auth_window(&handle, provider) {
-            log_dev!("[hg-auth] open window failed: {e}");
+            tracing::warn!("[hg-auth] open window failed: {e}");
         }
     })
     .map_err(|e| e.to_string())
 }
 
+/// Open the auth webview and resolve with the captured token, so the frontend can `await`
+/// the login. The `hg:auto-token` event is still emitted as before.
+#[tauri::command]
+pub async fn hg_wait_for_token(
+    app: AppHandle,
+    waiter: tauri::State<'_, TokenWaiter>,
+    provider: Option<String>,
+    channel: Option<String>,
+    timeout_ms: Option<u64>,
+) -> Result<String, String> {
+    let provider = normalize_provider(provider, channel)?;
+    let (tx, rx) = oneshot::channel();
+    // A newer call replaces an older pending one; the older receiver then errors out.
+    *waiter.0.lock().map_err(|e| e.to_string())? = Some(tx);
+
+    let handle = app.clone();
+    app.run_on_main_thread(move || {
+        if let Err(e) = open_hg_auth_window(&handle, provider) {
+            tracing::warn!("[hg-auth] open window failed: {e}");
+        }
+    })
+    .map_err(|e| e.to_string())?;
+
+    let timeout = timeout_ms.map(Duration::from_millis).unwrap_or_else(auth_timeout);
+    match tokio::time::timeout(timeout, rx).await {
+        Ok(Ok(token)) => Ok(token),
+        Ok(Err(_)) => Err("登录已取消".to_owned()),
+        Err(_) => {
+            if let Ok(mut slot) = waiter.0.lock() {
+                slot.take();
+            }
+            Err("等待登录超时".to_owned())
+        }
+    }
+}
+
 #[tauri::command]
 pub fn hg_close_token_webview(app: AppHandle) -> Result<(), String> {
     if let Some(win) = app.get_webview_window("hg-auth") {
@@ -502,21 +650,40 @@ pub fn hg_close_token_webview(app: AppHandle) -> Result<(), String> {
 }
 
 #[tauri::command]
-pub async fn hg_push_cookies(app: AppHandle, cookie: String, provider: Option<String>) -> Result<(), String> {
+pub async fn hg_push_cookies(
+    app: AppHandle,
+    cookie: String,
+    provider: Option<String>,
+    channel: Option<String>,
+) -> Result<(), String> {
     if cookie.trim().is_empty() {
         return Err("cookie is empty".into());
     }
-    let provider = normalize_provider(provider)?;
-    log_dev!("[hg-auth] hg_push_cookies len={}", cookie.len());
+    let provider = normalize_provider(provider, channel)?;
+    tracing::debug!("[hg-auth] hg_push_cookies len={}", cookie.len());
     let app_for_fetch = app.clone();
     tauri::async_runtime::spawn(async move {
-        if let Some(token) = fetch_token_with_cookie(cookie, provider).await {
-            let _ = app_for_fetch.emit_to("main", "hg:auto-token", token);
-            if let Some(win) = app_for_fetch.get_webview_window("hg-auth") {
-                clear_hg_webview(&win);
-                let _ = win.close();
-            }
+        if let Some(token) = fetch_token_with_cookie(&app_for_fetch, cookie, provider).await {
+            deliver_token(&app_for_fetch, token);
         }
     });
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prune_auth_profiles_only_removes_auth_sessions() {
+        let root = std::env::temp_dir().join(format!("endcat-webview-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("hg-auth-1").join("Default")).unwrap();
+        std::fs::create_dir_all(root.join("other")).unwrap();
+
+        prune_auth_profiles(&root);
+        assert!(!root.join("hg-auth-1").exists());
+        assert!(root.join("other").exists());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
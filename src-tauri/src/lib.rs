@@ -10,12 +10,19 @@ use tauri::Manager;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // Keep the guard alive until the app exits so buffered log lines get flushed.
+    let _log_guard = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.to_path_buf()))
+        .and_then(|dir| services::logging::init_logging(&dir).ok());
+
     // Directories are created in database::init_db now, ensuring they exist before DB access.
     // We can skip duplicate checks here or just ensure app starts cleanly.
 
     tauri::Builder::default()
         .plugin(tauri_plugin_sql::Builder::default().build())
         .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
         .setup(|app| {
             let handle = app.handle().clone();
             let pool = tauri::async_runtime::block_on(async move {
@@ -24,49 +31,116 @@ pub fn run() {
             app.manage(pool);
             
             // Create shared HTTP client to avoid blocking main thread
-            let http_client = reqwest::Client::builder()
-                .user_agent("endfield-cat")
+            let http_client = services::http::client_builder()
                 .build()
                 .expect("Failed to build HTTP client");
             app.manage(http_client);
+            app.manage(hg_auth::TokenWaiter::default());
+            app.manage(services::shutdown::ActiveTasks::default());
+            app.manage(hg_api::sync::SyncLocks::default());
+            app.manage(services::reset::ResetTokens::default());
             
             Ok(())
         })
+        .on_window_event(|window, event| {
+            // Closing the main window goes through the same drain-and-close path as `quit`.
+            if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+                if window.label() == "main" && !services::shutdown::is_shutting_down() {
+                    api.prevent_close();
+                    let app = window.app_handle().clone();
+                    tauri::async_runtime::spawn(async move {
+                        services::shutdown::shutdown(&app).await;
+                    });
+                }
+            }
+            if let tauri::WindowEvent::Destroyed = event {
+                if window.label() == "hg-auth" {
+                    hg_auth::on_auth_window_destroyed(window.app_handle());
+                }
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             app_cmd::greet,
             app_cmd::quit,
             app_cmd::get_app_version,
             app_cmd::get_storage_paths,
+            app_cmd::app_health,
+            app_cmd::relocate_data_dir,
+            app_cmd::factory_reset_token,
+            app_cmd::factory_reset,
+            app_cmd::export_backup_bundle,
+            app_cmd::import_backup_bundle,
+            app_cmd::import_gacha_generic,
             app_cmd::read_config,
             app_cmd::save_config,
+            app_cmd::reset_config,
             app_cmd::reset_metadata,
             app_cmd::update_metadata,
             app_cmd::fetch_metadata_manifest,
+            app_cmd::fetch_metadata_entries,
+            app_cmd::metadata_update_available,
+            app_cmd::metadata_diff,
             app_cmd::check_metadata,
+            app_cmd::compute_file_checksum,
+            app_cmd::is_online,
             app_cmd::fetch_latest_release,
             app_cmd::fetch_latest_prerelease,
+            app_cmd::check_for_update,
+            app_cmd::fetch_channel_release,
             app_cmd::download_and_apply_update,
             app_cmd::test_github_mirror,
+            app_cmd::validate_mirror_config,
+            app_cmd::test_mirror,
+            app_cmd::auto_select_mirror,
             hg_api::auth::hg_exchange_user_token,
             hg_api::auth::hg_u8_token_by_uid,
             hg_api::log::hg_gacha_auth_from_log,
+            hg_api::log::hg_list_log_gacha_urls,
             hg_api::log::hg_query_role_list,
             hg_api::gacha::hg_fetch_char_records,
             hg_api::gacha::hg_fetch_weapon_pools,
             hg_api::gacha::hg_fetch_weapon_records,
             hg_auth::hg_open_token_webview,
+            hg_auth::hg_wait_for_token,
             hg_auth::hg_close_token_webview,
             hg_auth::hg_push_cookies,
             database::db_delete_invalid_gacha_records,
             database::db_list_gacha_pulls,
+            database::db_list_gacha_pulls_with_tags,
+            database::db_tag_pull,
+            database::db_untag_pull,
+            database::db_list_tags,
+            database::db_list_gacha_pulls_page,
+            database::db_search_gacha,
+            database::db_gacha_after,
+            database::db_list_pools,
+            database::db_gacha_timeline,
+            database::db_gacha_report,
+            database::db_limited_analysis,
             database::db_save_gacha_records,
             database::db_list_accounts,
             database::db_upsert_account,
+            database::db_reorder_accounts,
+            database::db_set_account_alias,
             database::db_delete_account,
+            database::db_restore_account,
+            database::db_purge_account,
+            database::db_delete_account_records,
+            database::db_delete_pool_records,
+            database::db_insert_manual_pull,
+            database::db_backfill_item_names,
+            database::db_cleanup_orphans,
+            database::db_stats,
             database::db_get_account_tokens,
+            database::db_list_sync_log,
+            database::db_incremental_cursors,
             hg_api::sync::sync_gacha_by_token,
             hg_api::sync::sync_gacha_from_log,
-            hg_api::sync::add_account_by_token
+            hg_api::sync::hg_import_token_from_clipboard,
+            hg_api::sync::sync_all_accounts,
+            hg_api::sync::refresh_all_u8_tokens,
+            hg_api::sync::add_account_by_token,
+            hg_api::sync::hg_validate_token
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
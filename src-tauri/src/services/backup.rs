@@ -0,0 +1,267 @@
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+
+use super::{config, metadata};
+use crate::database::DbPool;
+
+/// Bump when the bundle layout changes incompatibly
+pub const BACKUP_SCHEMA_VERSION: u32 = 1;
+
+const MANIFEST_ENTRY: &str = "manifest.json";
+const DB_ENTRY: &str = "endcat.db";
+const CONFIG_ENTRY: &str = "config.json";
+
+/// Tables restored from a bundle, in insert order
+const RESTORED_TABLES: &[&str] = &["accounts", "gacha_pulls", "gacha_tags", "sync_log"];
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupManifest {
+    pub schema_version: u32,
+    pub app_version: String,
+    /// `PRAGMA user_version` of the exported database
+    pub db_version: i32,
+    pub metadata_version: Option<String>,
+    pub created_at: i64,
+}
+
+fn temp_db_path(tag: &str) -> PathBuf {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    std::env::temp_dir().join(format!("endcat-{tag}-{}-{nanos}.db", std::process::id()))
+}
+
+fn now_secs() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+fn validate_manifest(manifest: &BackupManifest) -> Result<(), String> {
+    if manifest.schema_version == 0 || manifest.schema_version > BACKUP_SCHEMA_VERSION {
+        return Err(format!(
+            "不支持的备份格式版本 {}（当前支持 {}）",
+            manifest.schema_version, BACKUP_SCHEMA_VERSION
+        ));
+    }
+    Ok(())
+}
+
+/// 导出备份包：`VACUUM INTO` 得到一致的数据库快照，连同 config.json 与清单打包为 zip
+pub async fn export_backup_bundle(
+    pool: &DbPool,
+    exe_dir: &Path,
+    app_version: &str,
+    dest_zip: &Path,
+) -> Result<BackupManifest, String> {
+    let snapshot = temp_db_path("backup");
+    sqlx::query("VACUUM INTO ?")
+        .bind(snapshot.to_string_lossy().to_string())
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    let db_bytes = fs::read(&snapshot);
+    let _ = fs::remove_file(&snapshot);
+    let db_bytes = db_bytes.map_err(|e| e.to_string())?;
+
+    let db_version: i32 = sqlx::query_scalar("PRAGMA user_version")
+        .fetch_one(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // The data directory pointer is machine specific and stays out of the bundle.
+    let mut config_json = config::read_config(exe_dir)?;
+    if let Some(obj) = config_json.as_object_mut() {
+        obj.remove("dataDir");
+    }
+
+    let manifest = BackupManifest {
+        schema_version: BACKUP_SCHEMA_VERSION,
+        app_version: app_version.to_string(),
+        db_version,
+        metadata_version: metadata::check_metadata_status(exe_dir)?.current_version,
+        created_at: now_secs(),
+    };
+
+    let file = fs::File::create(dest_zip).map_err(|e| e.to_string())?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    let entries: [(&str, Vec<u8>); 3] = [
+        (MANIFEST_ENTRY, serde_json::to_vec_pretty(&manifest).map_err(|e| e.to_string())?),
+        (DB_ENTRY, db_bytes),
+        (CONFIG_ENTRY, serde_json::to_vec_pretty(&config_json).map_err(|e| e.to_string())?),
+    ];
+    for (name, bytes) in entries {
+        zip.start_file(name, options).map_err(|e| e.to_string())?;
+        zip.write_all(&bytes).map_err(|e| e.to_string())?;
+    }
+    zip.finish().map_err(|e| e.to_string())?;
+
+    Ok(manifest)
+}
+
+fn read_entry(archive: &mut zip::ZipArchive<fs::File>, name: &str) -> Result<Option<Vec<u8>>, String> {
+    let mut entry = match archive.by_name(name) {
+        Ok(entry) => entry,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(e.to_string()),
+    };
+    let mut bytes = Vec::new();
+    entry.read_to_end(&mut bytes).map_err(|e| e.to_string())?;
+    Ok(Some(bytes))
+}
+
+async fn table_columns(conn: &mut sqlx::SqliteConnection, schema: &str, table: &str) -> Result<Vec<String>, String> {
+    let rows = sqlx::query(&format!("PRAGMA {schema}.table_info({table})"))
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(rows.iter().map(|r| r.get::<String, _>("name")).collect())
+}
+
+/// 导入备份包：校验清单后，用备份中的数据替换账号、抽卡记录、标签与同步日志，并恢复 config.json。
+/// 仅复制两边都存在的列，因此旧版本导出的数据库也能导入。
+pub async fn import_backup_bundle(
+    pool: &DbPool,
+    exe_dir: &Path,
+    src_zip: &Path,
+) -> Result<BackupManifest, String> {
+    let file = fs::File::open(src_zip).map_err(|e| e.to_string())?;
+    let mut archive = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
+
+    let manifest_bytes = read_entry(&mut archive, MANIFEST_ENTRY)?.ok_or("备份包缺少 manifest.json")?;
+    let manifest: BackupManifest = serde_json::from_slice(&manifest_bytes).map_err(|e| e.to_string())?;
+    validate_manifest(&manifest)?;
+
+    let db_bytes = read_entry(&mut archive, DB_ENTRY)?.ok_or("备份包缺少数据库文件")?;
+    let config_bytes = read_entry(&mut archive, CONFIG_ENTRY)?;
+
+    let restored = temp_db_path("restore");
+    fs::write(&restored, &db_bytes).map_err(|e| e.to_string())?;
+    let result = restore_tables(pool, &restored).await;
+    let _ = fs::remove_file(&restored);
+    result?;
+
+    if let Some(bytes) = config_bytes {
+        let mut imported: serde_json::Value = serde_json::from_slice(&bytes).map_err(|e| e.to_string())?;
+        // Keep this machine's data directory pointer.
+        if let Some(data_dir) = config::read_config(exe_dir)?.get("dataDir").cloned() {
+            if let Some(obj) = imported.as_object_mut() {
+                obj.insert("dataDir".to_string(), data_dir);
+            }
+        }
+        config::save_config(exe_dir, imported)?;
+    }
+
+    Ok(manifest)
+}
+
+async fn restore_tables(pool: &DbPool, restored: &Path) -> Result<(), String> {
+    // ATTACH is per connection, so everything runs on one pooled connection.
+    let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+    sqlx::query("ATTACH DATABASE ? AS backup")
+        .bind(restored.to_string_lossy().to_string())
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let result = async {
+        sqlx::query("BEGIN").execute(&mut *conn).await.map_err(|e| e.to_string())?;
+        for table in RESTORED_TABLES {
+            let backup_cols = table_columns(&mut conn, "backup", table).await?;
+            if backup_cols.is_empty() {
+                continue;
+            }
+            let main_cols = table_columns(&mut conn, "main", table).await?;
+            let shared: Vec<&String> = main_cols.iter().filter(|c| backup_cols.contains(c)).collect();
+            let cols = shared.iter().map(|c| format!("\"{c}\"")).collect::<Vec<_>>().join(", ");
+
+            sqlx::query(&format!("DELETE FROM main.{table}"))
+                .execute(&mut *conn)
+                .await
+                .map_err(|e| e.to_string())?;
+            sqlx::query(&format!("INSERT INTO main.{table} ({cols}) SELECT {cols} FROM backup.{table}"))
+                .execute(&mut *conn)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        sqlx::query("COMMIT").execute(&mut *conn).await.map_err(|e| e.to_string())?;
+        Ok::<(), String>(())
+    }
+    .await;
+
+    if result.is_err() {
+        let _ = sqlx::query("ROLLBACK").execute(&mut *conn).await;
+    }
+    let _ = sqlx::query("DETACH DATABASE backup").execute(&mut *conn).await;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_manifest_rejects_unknown_schema() {
+        let mut manifest = BackupManifest {
+            schema_version: BACKUP_SCHEMA_VERSION,
+            app_version: "0.2.1".to_string(),
+            db_version: 2,
+            metadata_version: None,
+            created_at: 0,
+        };
+        assert!(validate_manifest(&manifest).is_ok());
+
+        manifest.schema_version = BACKUP_SCHEMA_VERSION + 1;
+        assert!(validate_manifest(&manifest).is_err());
+
+        manifest.schema_version = 0;
+        assert!(validate_manifest(&manifest).is_err());
+    }
+
+    async fn file_pool(path: &Path) -> DbPool {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect(&format!("sqlite:{}?mode=rwc", path.display()))
+            .await
+            .unwrap();
+        crate::database::migrate_schema(&pool).await.unwrap();
+        pool
+    }
+
+    #[tokio::test]
+    async fn restore_tables_brings_back_tags() {
+        // ATTACH from an in-memory main database opens the file as memory too, so both are files.
+        let (path, main_path) = (temp_db_path("restore-src"), temp_db_path("restore-dst"));
+        let backup = file_pool(&path).await;
+        sqlx::query("INSERT INTO gacha_pulls (uid, banner_id, banner_name, item_name, rarity, pulled_at, seq_id, pool_type) VALUES ('u', 'b', 'b', 'A', 6, 1, '1', 'p')")
+            .execute(&backup)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO gacha_tags (uid, seq_id, pool_type, tag) VALUES ('u', '1', 'p', 'favorite')")
+            .execute(&backup)
+            .await
+            .unwrap();
+        backup.close().await;
+
+        let pool = file_pool(&main_path).await;
+        restore_tables(&pool, &path).await.unwrap();
+        let tags: Vec<String> = sqlx::query_scalar("SELECT tag FROM gacha_tags WHERE uid = 'u'")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(tags, ["favorite"]);
+
+        pool.close().await;
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(&main_path);
+    }
+}
@@ -1,15 +1,99 @@
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 #[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct StoragePaths {
     pub config: String,
     pub database: String,
+    /// Data lives in `data/` next to the executable rather than a `dataDir` set by the user
+    pub is_portable: bool,
 }
 
-pub fn ensure_paths(exe_dir: &Path) -> Result<StoragePaths, String> {
-    let config_dir = exe_dir.join("data").join("config");
-    let db_dir = exe_dir.join("data").join("database");
+/// config.json 的已知字段。保存前用于校验类型，未列出的字段通过 `extra` 原样保留。
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AppConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_dir: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub github_mirror: Option<super::mirror::GithubMirrorConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_level: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub update_channel: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata_mirrors: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gacha_request_delay_ms: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub u8_token_ttl_minutes: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auth_timeout_seconds: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_tail_size_mb: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub use_system_proxy: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repo_owner: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repo_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preserve_extra_files: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// 校验配置类型；出错时定位到具体字段，便于前端提示
+pub fn validate_config(config: &serde_json::Value) -> Result<AppConfig, String> {
+    let Some(obj) = config.as_object() else {
+        return Err("配置必须是 JSON 对象".to_string());
+    };
+    serde_json::from_value::<AppConfig>(config.clone()).map_err(|e| {
+        let field = obj.iter().find(|(key, value)| {
+            let single = serde_json::json!({ key.as_str(): value });
+            serde_json::from_value::<AppConfig>(single).is_err()
+        });
+        match field {
+            Some((key, _)) => format!("配置字段 {key} 类型无效：{e}"),
+            None => format!("配置格式无效：{e}"),
+        }
+    })
+}
+
+/// Data directory next to the executable. Its config also holds the `dataDir` pointer.
+fn portable_data_dir(exe_dir: &Path) -> PathBuf {
+    exe_dir.join("data")
+}
+
+fn read_json_file(path: &Path) -> Option<serde_json::Value> {
+    let content = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// 数据目录：exe 同级 data/config/config.json 中的 `dataDir`，未设置时为 exe 同级的 data 目录
+pub fn resolve_data_dir(exe_dir: &Path) -> PathBuf {
+    let pointer = portable_data_dir(exe_dir).join("config").join("config.json");
+    read_json_file(&pointer)
+        .and_then(|json| json.get("dataDir").and_then(|v| v.as_str()).map(|s| s.trim().to_string()))
+        .filter(|s| !s.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| portable_data_dir(exe_dir))
+}
+
+fn is_portable_dir(exe_dir: &Path, data_dir: &Path) -> bool {
+    let portable = portable_data_dir(exe_dir);
+    match (portable.canonicalize(), data_dir.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => portable == data_dir,
+    }
+}
+
+fn storage_paths_in(exe_dir: &Path, data_dir: &Path) -> Result<StoragePaths, String> {
+    let config_dir = data_dir.join("config");
+    let db_dir = data_dir.join("database");
 
     if !config_dir.exists() {
         fs::create_dir_all(&config_dir).map_err(|e| e.to_string())?;
@@ -21,11 +105,99 @@ pub fn ensure_paths(exe_dir: &Path) -> Result<StoragePaths, String> {
     Ok(StoragePaths {
         config: config_dir.join("config.json").to_string_lossy().to_string(),
         database: db_dir.join("endcat.db").to_string_lossy().to_string(),
+        is_portable: is_portable_dir(exe_dir, data_dir),
     })
 }
 
+pub fn ensure_paths(exe_dir: &Path) -> Result<StoragePaths, String> {
+    storage_paths_in(exe_dir, &resolve_data_dir(exe_dir))
+}
+
+/// Copy the SQLite database together with its `-wal` / `-shm` side files.
+fn copy_sqlite_files(from_dir: &Path, to_dir: &Path) -> Result<(), String> {
+    fs::create_dir_all(to_dir).map_err(|e| e.to_string())?;
+    for name in ["endcat.db", "endcat.db-wal", "endcat.db-shm"] {
+        let src = from_dir.join(name);
+        let dest = to_dir.join(name);
+        if src.exists() {
+            fs::copy(&src, &dest).map_err(|e| e.to_string())?;
+        } else if dest.exists() {
+            // A stale side file would be replayed against the copied database.
+            fs::remove_file(&dest).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+fn copy_dir_recursive(from: &Path, to: &Path) -> Result<(), String> {
+    if !from.exists() {
+        return Ok(());
+    }
+    for entry in walkdir::WalkDir::new(from).into_iter().flatten() {
+        let Ok(rel) = entry.path().strip_prefix(from) else {
+            continue;
+        };
+        let dest = to.join(rel);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&dest).map_err(|e| e.to_string())?;
+        } else {
+            fs::copy(entry.path(), &dest).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+fn has_app_data(data_dir: &Path) -> bool {
+    let metadata_dir = data_dir.join("metadata");
+    data_dir.join("database").join("endcat.db").exists()
+        || data_dir.join("config").join("config.json").exists()
+        || fs::read_dir(&metadata_dir).map(|mut d| d.next().is_some()).unwrap_or(false)
+}
+
+/// 将数据库、元数据与配置复制到 `new_dir` 并更新 `dataDir` 指向，重启后生效。
+/// 目标目录已有数据时，除非 `overwrite` 否则拒绝。
+pub fn relocate_data_dir(exe_dir: &Path, new_dir: &Path, overwrite: bool) -> Result<StoragePaths, String> {
+    if !new_dir.is_absolute() {
+        return Err("目标目录必须是绝对路径".to_string());
+    }
+
+    let current = resolve_data_dir(exe_dir);
+    fs::create_dir_all(new_dir).map_err(|e| e.to_string())?;
+    let same_dir = match (current.canonicalize(), new_dir.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => current == new_dir,
+    };
+    if same_dir {
+        return Err("目标目录与当前数据目录相同".to_string());
+    }
+    if has_app_data(new_dir) && !overwrite {
+        return Err("目标目录已存在数据，如需覆盖请确认".to_string());
+    }
+
+    copy_sqlite_files(&current.join("database"), &new_dir.join("database"))?;
+    copy_dir_recursive(&current.join("metadata"), &new_dir.join("metadata"))?;
+    copy_dir_recursive(&current.join("config"), &new_dir.join("config"))?;
+
+    // The pointer always lives in the portable config so it can be found before the data dir is known.
+    let pointer_dir = portable_data_dir(exe_dir).join("config");
+    fs::create_dir_all(&pointer_dir).map_err(|e| e.to_string())?;
+    let pointer_path = pointer_dir.join("config.json");
+    let mut pointer = read_json_file(&pointer_path).unwrap_or_else(|| serde_json::json!({}));
+    if let Some(obj) = pointer.as_object_mut() {
+        if is_portable_dir(exe_dir, new_dir) {
+            obj.remove("dataDir");
+        } else {
+            obj.insert("dataDir".to_string(), serde_json::json!(new_dir.to_string_lossy()));
+        }
+    }
+    let content = serde_json::to_string_pretty(&pointer).map_err(|e| e.to_string())?;
+    fs::write(&pointer_path, content).map_err(|e| e.to_string())?;
+
+    storage_paths_in(exe_dir, new_dir)
+}
+
 pub fn read_config(exe_dir: &Path) -> Result<serde_json::Value, String> {
-    let config_path = exe_dir.join("data").join("config").join("config.json");
+    let config_path = resolve_data_dir(exe_dir).join("config").join("config.json");
 
     if !config_path.exists() {
         return Ok(serde_json::json!({}));
@@ -36,8 +208,18 @@ pub fn read_config(exe_dir: &Path) -> Result<serde_json::Value, String> {
     Ok(config)
 }
 
+/// 读取配置中的单个字段；文件缺失、解析失败或类型不符时返回 None
+pub fn read_config_field<T: serde::de::DeserializeOwned>(exe_dir: &Path, key: &str) -> Option<T> {
+    let config = read_config(exe_dir).ok()?;
+    config
+        .get(key)
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+}
+
 pub fn save_config(exe_dir: &Path, config: serde_json::Value) -> Result<(), String> {
-    let config_dir = exe_dir.join("data").join("config");
+    validate_config(&config)?;
+
+    let config_dir = resolve_data_dir(exe_dir).join("config");
     if !config_dir.exists() {
         fs::create_dir_all(&config_dir).map_err(|e| e.to_string())?;
     }
@@ -48,3 +230,87 @@ pub fn save_config(exe_dir: &Path, config: serde_json::Value) -> Result<(), Stri
     fs::write(&config_path, content).map_err(|e| e.to_string())?;
     Ok(())
 }
+
+/// 将当前 config.json 备份为 config.json.bak 并写入默认配置（空对象）。
+/// 可读取的 `dataDir` 会被保留，避免重置后找不到已迁移的数据。
+pub fn reset_config(exe_dir: &Path) -> Result<serde_json::Value, String> {
+    let config_dir = resolve_data_dir(exe_dir).join("config");
+    fs::create_dir_all(&config_dir).map_err(|e| e.to_string())?;
+    let config_path = config_dir.join("config.json");
+
+    let mut fresh = serde_json::json!({});
+    if config_path.exists() {
+        fs::copy(&config_path, config_dir.join("config.json.bak")).map_err(|e| e.to_string())?;
+        if let Some(data_dir) = read_json_file(&config_path).and_then(|json| json.get("dataDir").cloned()) {
+            if data_dir.is_string() {
+                fresh["dataDir"] = data_dir;
+            }
+        }
+    }
+
+    save_config(exe_dir, fresh.clone())?;
+    Ok(fresh)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relocate_copies_data_and_updates_pointer() {
+        let root = std::env::temp_dir().join(format!("ef-relocate-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let exe_dir = root.join("app");
+        let target = root.join("elsewhere");
+
+        assert_eq!(resolve_data_dir(&exe_dir), exe_dir.join("data"));
+        let old = ensure_paths(&exe_dir).unwrap();
+        assert!(old.is_portable);
+        fs::write(&old.database, b"db").unwrap();
+        save_config(&exe_dir, serde_json::json!({ "theme": "dark" })).unwrap();
+
+        let paths = relocate_data_dir(&exe_dir, &target, false).unwrap();
+        assert!(!paths.is_portable);
+        assert_eq!(resolve_data_dir(&exe_dir), target);
+        assert_eq!(fs::read(&paths.database).unwrap(), b"db");
+        assert_eq!(read_config_field::<String>(&exe_dir, "theme").as_deref(), Some("dark"));
+
+        let back = exe_dir.join("data");
+        assert!(relocate_data_dir(&exe_dir, &back, false).is_err());
+        assert!(relocate_data_dir(&exe_dir, &back, true).unwrap().is_portable);
+        assert_eq!(resolve_data_dir(&exe_dir), back);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn reset_config_backs_up_and_keeps_data_dir() {
+        let exe_dir = std::env::temp_dir().join(format!("ef-reset-config-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&exe_dir);
+        let config_dir = exe_dir.join("data").join("config");
+        fs::create_dir_all(&config_dir).unwrap();
+        fs::write(config_dir.join("config.json"), "{ not json").unwrap();
+
+        assert_eq!(reset_config(&exe_dir).unwrap(), serde_json::json!({}));
+        assert_eq!(fs::read_to_string(config_dir.join("config.json.bak")).unwrap(), "{ not json");
+        assert_eq!(read_config(&exe_dir).unwrap(), serde_json::json!({}));
+
+        let _ = fs::remove_dir_all(&exe_dir);
+    }
+
+    #[test]
+    fn validate_config_rejects_mistyped_fields_and_keeps_unknown() {
+        let ok = validate_config(&serde_json::json!({
+            "dataDir": "D:/EndCat",
+            "appVersion": null,
+            "theme": "dark",
+        }))
+        .unwrap();
+        assert_eq!(ok.data_dir.as_deref(), Some("D:/EndCat"));
+        assert_eq!(ok.extra.get("theme"), Some(&serde_json::json!("dark")));
+
+        let err = validate_config(&serde_json::json!({ "theme": "dark", "dataDir": 42 })).unwrap_err();
+        assert!(err.contains("dataDir"), "{err}");
+        assert!(validate_config(&serde_json::json!([])).is_err());
+    }
+}
@@ -0,0 +1,92 @@
+use serde::Serialize;
+use std::path::Path;
+
+use crate::database::DbPool;
+use crate::services::{config, metadata};
+
+/// 诊断页用的应用状态汇总，支持人员让用户一次性复制粘贴
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppHealth {
+    pub db_ok: bool,
+    /// `PRAGMA user_version`; 0 when the database could not be queried
+    pub db_version: i32,
+    /// Metadata has a manifest and the local files match its checksum
+    pub metadata_ok: bool,
+    pub metadata_version: Option<String>,
+    pub account_count: i64,
+    pub config_path: String,
+    pub data_dir: String,
+    pub app_version: String,
+    /// Failures hit while collecting the fields above, so `false` flags come with a reason
+    pub errors: Vec<String>,
+}
+
+/// Collect app state without failing: each probe that errors is recorded in `errors`
+/// and leaves its fields at their defaults.
+pub async fn app_health(pool: &DbPool, exe_dir: &Path, app_version: &str) -> AppHealth {
+    let mut errors = Vec::new();
+
+    let db_version = sqlx::query_scalar::<_, i32>("PRAGMA user_version")
+        .fetch_one(pool)
+        .await
+        .map_err(|e| errors.push(format!("database: {e}")))
+        .ok();
+    let account_count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM accounts")
+        .fetch_one(pool)
+        .await
+        .map_err(|e| errors.push(format!("accounts: {e}")))
+        .ok();
+
+    let status = metadata::check_metadata_status(exe_dir)
+        .map_err(|e| errors.push(format!("metadata: {e}")))
+        .ok();
+    let config_path = config::ensure_paths(exe_dir)
+        .map(|p| p.config)
+        .unwrap_or_else(|e| {
+            errors.push(format!("paths: {e}"));
+            String::new()
+        });
+
+    AppHealth {
+        db_ok: db_version.is_some() && account_count.is_some(),
+        db_version: db_version.unwrap_or(0),
+        metadata_ok: status.as_ref().is_some_and(|s| s.has_manifest && s.matches_manifest),
+        metadata_version: status.and_then(|s| s.current_version),
+        account_count: account_count.unwrap_or(0),
+        config_path,
+        data_dir: config::resolve_data_dir(exe_dir).to_string_lossy().to_string(),
+        app_version: app_version.to_string(),
+        errors,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn app_health_reports_failures_instead_of_erroring() {
+        let exe_dir = std::env::temp_dir().join(format!("endcat-health-{}", std::process::id()));
+        std::fs::create_dir_all(&exe_dir).unwrap();
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+
+        let health = app_health(&pool, &exe_dir, "1.2.3").await;
+        assert!(!health.db_ok, "accounts table is missing");
+        assert!(!health.metadata_ok);
+        assert_eq!(health.app_version, "1.2.3");
+        assert!(health.errors.iter().any(|e| e.starts_with("accounts:")));
+
+        crate::database::migrate_schema(&pool).await.unwrap();
+        sqlx::query("INSERT INTO accounts (uid) VALUES ('1')").execute(&pool).await.unwrap();
+        let health = app_health(&pool, &exe_dir, "1.2.3").await;
+        assert!(health.db_ok);
+        assert_eq!(health.account_count, 1);
+
+        let _ = std::fs::remove_dir_all(&exe_dir);
+    }
+}
@@ -0,0 +1,155 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// 配置键：是否使用系统代理（`HTTPS_PROXY` / `HTTP_PROXY` / `NO_PROXY` 等），默认开启
+pub const USE_SYSTEM_PROXY_KEY: &str = "useSystemProxy";
+
+/// 配置键：网络请求与登录 webview 使用的 User-Agent，留空时使用 [`DEFAULT_USER_AGENT`]
+pub const USER_AGENT_KEY: &str = "userAgent";
+
+/// Desktop browser UA, so HG's webview API endpoints see the same client as the login page.
+pub const DEFAULT_USER_AGENT: &str =
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36 Edg/120.0.0.0";
+
+/// Redirects followed before a request fails, so a looping mirror can't hang downloads.
+const MAX_REDIRECTS: usize = 5;
+
+fn exe_dir() -> Option<PathBuf> {
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.to_path_buf()))
+}
+
+pub fn use_system_proxy() -> bool {
+    exe_dir()
+        .and_then(|dir| super::config::read_config_field::<bool>(&dir, USE_SYSTEM_PROXY_KEY))
+        .unwrap_or(true)
+}
+
+/// Configured `userAgent`, read at startup; a blank value falls back to the default.
+pub fn user_agent() -> String {
+    exe_dir()
+        .and_then(|dir| super::config::read_config_field::<String>(&dir, USER_AGENT_KEY))
+        .map(|ua| ua.trim().to_string())
+        .filter(|ua| !ua.is_empty())
+        .unwrap_or_else(|| DEFAULT_USER_AGENT.to_string())
+}
+
+/// Shared client builder. reqwest picks up the proxy env vars on its own (honouring `NO_PROXY`);
+/// `useSystemProxy: false` turns that off for setups where the proxy breaks requests.
+pub fn client_builder() -> reqwest::ClientBuilder {
+    let builder = reqwest::Client::builder()
+        .user_agent(user_agent())
+        .redirect(reqwest::redirect::Policy::limited(MAX_REDIRECTS));
+    if use_system_proxy() {
+        builder
+    } else {
+        tracing::info!("system proxy disabled by config");
+        builder.no_proxy()
+    }
+}
+
+/// Hosts probed by [`is_online`]; any one answering is enough.
+const ONLINE_PROBES: &[&str] = &["https://www.baidu.com", "https://www.cloudflare.com"];
+const ONLINE_PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Error returned by commands that were asked to check connectivity first and found none.
+pub const OFFLINE_ERROR: &str = "offline";
+
+/// HEAD a few well-known hosts in parallel; any HTTP response, even an error status, means
+/// the network is up.
+pub async fn is_online(client: &reqwest::Client) -> bool {
+    let probes = ONLINE_PROBES.iter().map(|url| {
+        Box::pin(async move {
+            client
+                .head(*url)
+                .timeout(ONLINE_PROBE_TIMEOUT)
+                .send()
+                .await
+                .map(|_| ())
+        })
+    });
+    futures_util::future::select_ok(probes).await.is_ok()
+}
+
+/// Attempts made by [`retry_with_backoff`], including the first one.
+pub const RETRY_ATTEMPTS: u32 = 4;
+/// Delay before the first retry; doubles after each further failure.
+pub const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(8);
+
+/// Statuses a CDN or mirror may return for a moment before recovering: 408, 429 and 5xx.
+pub fn is_retryable_status(status: u16) -> bool {
+    status == 408 || status == 429 || (500..600).contains(&status)
+}
+
+/// Run `op` up to [`RETRY_ATTEMPTS`] times, sleeping `base_delay`, `2 * base_delay`, …
+/// (capped at 8s) between attempts while `retryable` accepts the error.
+pub async fn retry_with_backoff<T, E, F, Fut>(
+    base_delay: Duration,
+    mut op: F,
+    retryable: impl Fn(&E) -> bool,
+) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<T, E>>,
+{
+    let mut attempt = 1;
+    loop {
+        match op().await {
+            Err(err) if attempt < RETRY_ATTEMPTS && retryable(&err) => {
+                let delay = base_delay.saturating_mul(1 << (attempt - 1)).min(RETRY_MAX_DELAY);
+                tracing::debug!("[http] attempt {}/{} failed, retrying in {:?}", attempt, RETRY_ATTEMPTS, delay);
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[tokio::test]
+    async fn retry_with_backoff_stops_on_success_or_fatal_error() {
+        let calls = Cell::new(0);
+        let result: Result<u32, u16> = retry_with_backoff(
+            Duration::ZERO,
+            || {
+                calls.set(calls.get() + 1);
+                let n = calls.get();
+                async move { if n < 3 { Err(502) } else { Ok(n) } }
+            },
+            |status| is_retryable_status(*status),
+        )
+        .await;
+        assert_eq!(result, Ok(3));
+
+        calls.set(0);
+        let result: Result<u32, u16> = retry_with_backoff(
+            Duration::ZERO,
+            || {
+                calls.set(calls.get() + 1);
+                async { Err(404) }
+            },
+            |status| is_retryable_status(*status),
+        )
+        .await;
+        assert_eq!((result, calls.get()), (Err(404), 1));
+
+        calls.set(0);
+        let result: Result<u32, u16> = retry_with_backoff(
+            Duration::ZERO,
+            || {
+                calls.set(calls.get() + 1);
+                async { Err(503) }
+            },
+            |status| is_retryable_status(*status),
+        )
+        .await;
+        assert_eq!((result, calls.get()), (Err(503), RETRY_ATTEMPTS));
+    }
+}
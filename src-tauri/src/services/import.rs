@@ -0,0 +1,229 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::database::{ApiGachaRecord, DbPool};
+
+/// Header names of the CSV columns holding each field. `seq_id`, `item_id`, `pool_id`
+/// and `pool_name` are optional; the rest must exist in the header row.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ColumnMapping {
+    pub item_name: String,
+    pub rarity: String,
+    pub pulled_at: String,
+    pub pool_type: String,
+    pub seq_id: Option<String>,
+    pub item_id: Option<String>,
+    pub pool_id: Option<String>,
+    pub pool_name: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GenericImportResult {
+    /// Rows handed to the save path; rows already stored are updated, not duplicated
+    pub imported: usize,
+    /// Rows without a `seq_id` column value that got a synthesized `csv-…` one. Such rows are
+    /// dropped when a synced row covers the same pull (see `drop_synthetic_duplicates`).
+    pub synthesized_seq_ids: usize,
+}
+
+struct Columns {
+    item_name: usize,
+    rarity: usize,
+    pulled_at: usize,
+    pool_type: usize,
+    seq_id: Option<usize>,
+    item_id: Option<usize>,
+    pool_id: Option<usize>,
+    pool_name: Option<usize>,
+}
+
+fn resolve_columns(
+    headers: &csv::StringRecord,
+    mapping: &ColumnMapping,
+) -> Result<Columns, String> {
+    let find = |name: &str| headers.iter().position(|h| h.trim() == name.trim());
+    let required = |name: &str| find(name).ok_or_else(|| format!("CSV 缺少列: {name}"));
+    let optional = |name: &Option<String>| -> Result<Option<usize>, String> {
+        name.as_deref()
+            .filter(|n| !n.trim().is_empty())
+            .map(required)
+            .transpose()
+    };
+
+    Ok(Columns {
+        item_name: required(&mapping.item_name)?,
+        rarity: required(&mapping.rarity)?,
+        pulled_at: required(&mapping.pulled_at)?,
+        pool_type: required(&mapping.pool_type)?,
+        seq_id: optional(&mapping.seq_id)?,
+        item_id: optional(&mapping.item_id)?,
+        pool_id: optional(&mapping.pool_id)?,
+        pool_name: optional(&mapping.pool_name)?,
+    })
+}
+
+/// Leading digits of a rarity cell, so `6`, `6★` and ` 6 ` all read as 6.
+fn parse_rarity(value: &str) -> Option<i64> {
+    let digits: String = value
+        .trim()
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().ok().filter(|r| (1..=6).contains(r))
+}
+
+/// Parse CSV text into records. Any row with a missing or malformed required field fails
+/// the whole import with its line number, so a bad mapping never half-imports a file.
+fn parse_records(
+    reader: impl std::io::Read,
+    mapping: &ColumnMapping,
+) -> Result<(Vec<ApiGachaRecord>, usize), String> {
+    let mut csv = csv::ReaderBuilder::new().flexible(true).from_reader(reader);
+    let headers = csv.headers().map_err(|e| e.to_string())?.clone();
+    let cols = resolve_columns(&headers, mapping)?;
+
+    let mut records = Vec::new();
+    let mut synthesized = 0;
+    // Synthesized seq_ids count rows per (pool_type, pulled_at) so re-importing is stable.
+    let mut same_time: HashMap<(String, i64), usize> = HashMap::new();
+
+    for (index, row) in csv.records().enumerate() {
+        let line = index + 2;
+        let row = row.map_err(|e| format!("第 {line} 行: {e}"))?;
+        let cell = |i: usize| row.get(i).map(str::trim).unwrap_or("");
+        let opt_cell = |i: Option<usize>| i.map(cell).filter(|v| !v.is_empty()).map(str::to_owned);
+
+        let name = cell(cols.item_name);
+        if name.is_empty() {
+            return Err(format!("第 {line} 行: 缺少物品名"));
+        }
+        let rarity = parse_rarity(cell(cols.rarity))
+            .ok_or_else(|| format!("第 {line} 行: 无效的星级 {:?}", cell(cols.rarity)))?;
+        let pulled_at: i64 = cell(cols.pulled_at)
+            .parse()
+            .map_err(|_| format!("第 {line} 行: 无效的时间戳 {:?}", cell(cols.pulled_at)))?;
+        let pool_type = cell(cols.pool_type);
+        if pool_type.is_empty() {
+            return Err(format!("第 {line} 行: 缺少卡池类型"));
+        }
+
+        let seq_id = match opt_cell(cols.seq_id) {
+            Some(seq_id) => seq_id,
+            None => {
+                let n = same_time
+                    .entry((pool_type.to_owned(), pulled_at))
+                    .or_insert(0);
+                *n += 1;
+                synthesized += 1;
+                format!("csv-{pulled_at}-{n}")
+            }
+        };
+        let pool_id = opt_cell(cols.pool_id).unwrap_or_else(|| pool_type.to_owned());
+        let pool_name = opt_cell(cols.pool_name).unwrap_or_else(|| pool_id.clone());
+
+        records.push(ApiGachaRecord {
+            name: name.to_owned(),
+            item_id: opt_cell(cols.item_id),
+            rarity,
+            pool_id,
+            pool_name,
+            seq_id,
+            pulled_at,
+            pool_type: pool_type.to_owned(),
+            is_free: false,
+            is_new: false,
+        });
+    }
+
+    Ok((records, synthesized))
+}
+
+/// Import a CSV export (ours, the game's or a community sheet) for `uid` using `mapping`.
+pub async fn import_gacha_generic(
+    pool: &DbPool,
+    uid: &str,
+    path: &Path,
+    mapping: &ColumnMapping,
+) -> Result<GenericImportResult, String> {
+    let file = std::fs::File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let (records, synthesized_seq_ids) = parse_records(file, mapping)?;
+    let imported = records.len();
+    crate::hg_api::sync::save_gacha_records_internal(pool, uid, records, crate::database::SOURCE_IMPORT).await?;
+    Ok(GenericImportResult {
+        imported,
+        synthesized_seq_ids,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapping() -> ColumnMapping {
+        ColumnMapping {
+            item_name: "名称".into(),
+            rarity: "星级".into(),
+            pulled_at: "时间".into(),
+            pool_type: "卡池".into(),
+            seq_id: None,
+            item_id: None,
+            pool_id: None,
+            pool_name: None,
+        }
+    }
+
+    #[test]
+    fn parse_records_maps_columns_and_synthesizes_seq_ids() {
+        let csv =
+            "时间,名称,星级,卡池\n1700000000,Perlica,6★,special\n1700000000,Ember,5,special\n";
+        let (records, synthesized) = parse_records(csv.as_bytes(), &mapping()).unwrap();
+
+        assert_eq!(synthesized, 2);
+        assert_eq!(records[0].name, "Perlica");
+        assert_eq!(records[0].rarity, 6);
+        assert_eq!(records[0].pool_id, "special");
+        assert_eq!(records[0].seq_id, "csv-1700000000-1");
+        assert_eq!(records[1].seq_id, "csv-1700000000-2");
+    }
+
+    #[tokio::test]
+    async fn synthesized_rows_give_way_to_synced_rows() {
+        use crate::database::{SOURCE_IMPORT, SOURCE_TOKEN};
+        use crate::hg_api::sync::save_gacha_records_internal;
+
+        let pool = crate::database::test_pool().await;
+        let csv = "时间,名称,星级,卡池\n1700000000,Perlica,6,special\n1700000000,Ember,5,special\n1700000500,Avywenna,5,special\n";
+        let (records, _) = parse_records(csv.as_bytes(), &mapping()).unwrap();
+        save_gacha_records_internal(&pool, "u", records, SOURCE_IMPORT).await.unwrap();
+
+        // The token sync later returns the first ten-pull with real seq_ids.
+        let synced = "时间,seq,名称,星级,卡池\n1700000000,101,Perlica,6,special\n1700000000,102,Ember,5,special\n";
+        let (records, _) = parse_records(synced.as_bytes(), &ColumnMapping { seq_id: Some("seq".into()), ..mapping() }).unwrap();
+        save_gacha_records_internal(&pool, "u", records, SOURCE_TOKEN).await.unwrap();
+
+        let seq_ids: Vec<String> = sqlx::query_scalar("SELECT seq_id FROM gacha_pulls ORDER BY seq_id")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(seq_ids, ["101", "102", "csv-1700000500-1"]);
+    }
+
+    #[test]
+    fn parse_records_validates_headers_and_rows() {
+        let err = parse_records("名称,星级\nA,6\n".as_bytes(), &mapping())
+            .err()
+            .unwrap();
+        assert!(err.contains("缺少列"));
+
+        let err = parse_records(
+            "时间,名称,星级,卡池\n1,A,6,p\nx,B,6,p\n".as_bytes(),
+            &mapping(),
+        )
+        .err()
+        .unwrap();
+        assert!(err.starts_with("第 3 行"), "{err}");
+    }
+}
@@ -0,0 +1,42 @@
+use std::fs;
+use std::path::Path;
+
+use tracing::level_filters::LevelFilter;
+use tracing_appender::non_blocking::WorkerGuard;
+
+use super::config;
+
+/// 从配置 `logLevel` 解析日志级别；缺失或无法识别时 release 用 info，debug 用 debug
+fn level_from_config(exe_dir: &Path) -> LevelFilter {
+    let default = if cfg!(debug_assertions) {
+        LevelFilter::DEBUG
+    } else {
+        LevelFilter::INFO
+    };
+    config::read_config_field::<String>(exe_dir, "logLevel")
+        .and_then(|s| s.trim().parse::<LevelFilter>().ok())
+        .unwrap_or(default)
+}
+
+/// Install the global tracing subscriber writing daily-rolling files to `data/logs/`.
+///
+/// The returned guard flushes buffered lines on drop, so keep it alive for the whole run.
+pub fn init_logging(exe_dir: &Path) -> Result<WorkerGuard, String> {
+    let log_dir = config::resolve_data_dir(exe_dir).join("logs");
+    if !log_dir.exists() {
+        fs::create_dir_all(&log_dir).map_err(|e| e.to_string())?;
+    }
+
+    let appender = tracing_appender::rolling::daily(&log_dir, "endfield-cat.log");
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+
+    tracing_subscriber::fmt()
+        .with_writer(writer)
+        .with_ansi(false)
+        .with_target(false)
+        .with_max_level(level_from_config(exe_dir))
+        .try_init()
+        .map_err(|e| e.to_string())?;
+
+    Ok(guard)
+}
@@ -1,9 +1,11 @@
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io::Read;
 use std::path::{Path, PathBuf};
+
+use crate::services::{config, http};
 use walkdir::WalkDir;
 
 #[derive(Serialize)]
@@ -14,17 +16,39 @@ pub struct MetadataStatus {
     pub file_count: usize,
     pub has_manifest: bool,
     pub current_version: Option<String>,
+    /// Base URL the manifest was fetched from, when this status comes from a download
+    pub mirror: Option<String>,
+    /// Hash-of-hashes over the local files, see [`compute_metadata_checksum`]
+    pub metadata_checksum: Option<String>,
+    /// Whether `metadata_checksum` equals the local manifest's `metadata_checksum`
+    pub matches_manifest: bool,
+    /// Files outside the manifest left in place because `preserveExtraFiles` is on
+    pub skipped_cleanup: usize,
+    /// Files that existed locally but failed checksum verification and were fetched again
+    pub redownloaded: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RemoteManifest {
+    /// Manifest layout version; `None` for manifests published before the field existed
+    pub schema_version: Option<u64>,
     pub package_version: Option<String>,
     pub metadata_checksum: Option<String>,
     pub item_count: Option<usize>,
     pub total_size: Option<usize>,
 }
 
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetadataUpdateCheck {
+    pub local: Option<String>,
+    pub remote: Option<String>,
+    pub update_available: bool,
+    pub item_count: Option<usize>,
+    pub total_size: Option<usize>,
+}
+
 #[derive(Clone, Serialize)]
 pub struct DownloadProgress {
     pub current: usize,
@@ -39,10 +63,27 @@ pub enum UpdateProgress {
     Verifying { current: usize, total: usize, path: String },
     Downloading { current: usize, total: usize, path: String },
     Cleaning { current: usize, total: usize, path: String },
+    /// Terminal: fetching or verifying `path` failed; `status` is set for HTTP errors
+    Failed { path: String, url: String, status: Option<u16> },
+}
+
+/// Highest manifest `schemaVersion` this build understands
+pub const SUPPORTED_MANIFEST_SCHEMA: u64 = 1;
+
+/// Read `schemaVersion` and reject manifests newer than [`SUPPORTED_MANIFEST_SCHEMA`],
+/// which would otherwise parse as an empty `entries` list and download nothing.
+fn check_manifest_schema(json: &serde_json::Value) -> Result<Option<u64>, String> {
+    let version = json.get("schemaVersion").and_then(|v| v.as_u64());
+    match version {
+        Some(v) if v > SUPPORTED_MANIFEST_SCHEMA => Err(format!(
+            "manifest schemaVersion {v} is newer than supported {SUPPORTED_MANIFEST_SCHEMA}, please update the app"
+        )),
+        _ => Ok(version),
+    }
 }
 
 /// Compute SHA256 hash of a file, returns uppercase hex string
-fn compute_sha256(path: &Path) -> Result<String, String> {
+pub fn compute_sha256(path: &Path) -> Result<String, String> {
     let mut file = fs::File::open(path).map_err(|e| e.to_string())?;
     let mut hasher = Sha256::new();
     let mut buffer = [0u8; 8192];
@@ -59,6 +100,100 @@ fn compute_sha256(path: &Path) -> Result<String, String> {
     Ok(format!("{:X}", result))
 }
 
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileChecksum {
+    pub path: String,
+    /// Uppercase hex SHA256, comparable to a manifest entry's `checksum`
+    pub checksum: String,
+    pub size: u64,
+}
+
+/// Checksum of one file; a relative `path` is resolved against the metadata directory
+/// so it can be given exactly as it appears in the manifest.
+pub fn file_checksum(exe_dir: &Path, path: &str) -> Result<FileChecksum, String> {
+    let path = Path::new(path);
+    let full = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        config::resolve_data_dir(exe_dir).join("metadata").join(path)
+    };
+    let size = fs::metadata(&full).map_err(|e| format!("{}: {}", full.display(), e))?.len();
+    Ok(FileChecksum {
+        path: full.to_string_lossy().to_string(),
+        checksum: compute_sha256(&full)?,
+        size,
+    })
+}
+
+/// Normalize a hex SHA256 string for comparison with `compute_sha256` output:
+/// trims, drops a `sha256:` prefix (any case) and uppercases. Use it on both sides.
+pub fn normalize_sha256(checksum: &str) -> String {
+    let trimmed = checksum.trim();
+    let hex = match trimmed.get(..7) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("sha256:") => &trimmed[7..],
+        _ => trimmed,
+    };
+    hex.trim().to_uppercase()
+}
+
+/// Paths (relative, `/`-separated) of the files in a metadata directory, `manifest.json` excluded
+fn local_metadata_paths(metadata_dir: &Path) -> Vec<String> {
+    WalkDir::new(metadata_dir)
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry.file_type().is_file() && entry.file_name() != "manifest.json")
+        .filter_map(|entry| {
+            entry
+                .path()
+                .strip_prefix(metadata_dir)
+                .ok()
+                .map(|rel| rel.to_string_lossy().replace('\\', "/"))
+        })
+        .collect()
+}
+
+/// Aggregate checksum of the given files of a metadata directory: SHA256 over the
+/// per-file SHA256 hex digests, in path order. Listed files missing on disk are left
+/// out, so the result no longer matches the manifest. Returns `None` for no paths.
+pub fn compute_metadata_checksum(metadata_dir: &Path, paths: &[String]) -> Result<Option<String>, String> {
+    if paths.is_empty() {
+        return Ok(None);
+    }
+
+    let mut sorted: Vec<&String> = paths.iter().collect();
+    sorted.sort();
+    sorted.dedup();
+    let mut hasher = Sha256::new();
+    for rel in sorted {
+        let path = metadata_dir.join(rel);
+        if path.is_file() {
+            hasher.update(compute_sha256(&path)?.as_bytes());
+        }
+    }
+    Ok(Some(format!("{:X}", hasher.finalize())))
+}
+
+/// Compute the local aggregate checksum and compare it to the manifest's `metadata_checksum`.
+/// Only the manifest's entries are hashed, so files kept by `preserveExtraFiles` don't
+/// count; without a manifest listing entries every local file is hashed.
+fn verify_metadata_checksum(
+    metadata_dir: &Path,
+    manifest_json: Option<&serde_json::Value>,
+) -> Result<(Option<String>, bool), String> {
+    let listed: Vec<String> = manifest_json
+        .map(|json| manifest_entries(json).into_iter().map(|e| e.path).collect())
+        .unwrap_or_default();
+    let paths = if listed.is_empty() { local_metadata_paths(metadata_dir) } else { listed };
+    let local = compute_metadata_checksum(metadata_dir, &paths)?;
+    let expected = manifest_json
+        .and_then(|json| json.get("metadata_checksum"))
+        .and_then(|v| v.as_str())
+        .map(normalize_sha256);
+    let matches = matches!((&local, &expected), (Some(l), Some(e)) if l == e);
+    Ok((local, matches))
+}
+
 pub fn build_manifest_url(base_url: &str, version: &str) -> Result<String, String> {
     let mut url = base_url.trim().to_string();
     if url.is_empty() {
@@ -112,6 +247,209 @@ pub fn build_manifest_url(base_url: &str, version: &str) -> Result<String, Strin
     Ok(url)
 }
 
+/// Build the ordered mirror list: the caller's `base_url` first, then the
+/// `metadataMirrors` config entries, skipping blanks and duplicates.
+fn merge_mirror_chain(primary: Option<String>, configured: Vec<String>) -> Vec<String> {
+    let mut chain: Vec<String> = Vec::new();
+    for url in primary.into_iter().chain(configured) {
+        let trimmed = url.trim();
+        if trimmed.is_empty() || chain.iter().any(|u| u == trimmed) {
+            continue;
+        }
+        chain.push(trimmed.to_string());
+    }
+    chain
+}
+
+pub fn metadata_mirror_chain(exe_dir: &Path, base_url: Option<String>) -> Vec<String> {
+    let configured: Vec<String> =
+        config::read_config_field(exe_dir, "metadataMirrors").unwrap_or_default();
+    merge_mirror_chain(base_url, configured)
+}
+
+/// Directory URL that manifest entry paths are resolved against
+fn manifest_dir_url(manifest_url: &str) -> Result<String, String> {
+    manifest_url
+        .rsplit_once('/')
+        .map(|(head, _)| {
+            let mut h = head.to_string();
+            if !h.ends_with('/') {
+                h.push('/');
+            }
+            h
+        })
+        .ok_or_else(|| "Invalid manifest url".to_string())
+}
+
+/// Folder behind a `file://` URL or plain filesystem path; `None` for http(s) sources.
+fn local_source_path(source: &str) -> Option<PathBuf> {
+    let source = source.trim();
+    if source.is_empty() || source.starts_with("http://") || source.starts_with("https://") {
+        return None;
+    }
+    let path = match source.strip_prefix("file://") {
+        // file:///C:/meta → C:/meta
+        Some(rest) if rest.len() >= 3 && rest.starts_with('/') && rest.as_bytes()[2] == b':' => &rest[1..],
+        Some(rest) => rest,
+        None => source,
+    };
+    Some(PathBuf::from(path))
+}
+
+/// Read `manifest.json` from a local metadata folder (or the manifest file itself).
+/// Returns the bytes and the base that entry paths are appended to.
+fn read_local_manifest(dir: &Path) -> Result<(Vec<u8>, String), String> {
+    let (manifest_path, base) = if dir.file_name().is_some_and(|n| n == "manifest.json") {
+        (dir.to_path_buf(), dir.parent().map(Path::to_path_buf).unwrap_or_default())
+    } else {
+        (dir.join("manifest.json"), dir.to_path_buf())
+    };
+    let bytes = fs::read(&manifest_path).map_err(|e| format!("{}: {}", manifest_path.display(), e))?;
+    let mut base = base.to_string_lossy().to_string();
+    if !base.ends_with('/') && !base.ends_with('\\') {
+        base.push('/');
+    }
+    Ok((bytes, base))
+}
+
+/// A manifest entry that could not be fetched, with the resolved URL for the error report.
+struct EntryFetchError {
+    url: String,
+    /// HTTP status, when the server answered
+    status: Option<u16>,
+    message: String,
+}
+
+/// Bytes of one manifest entry, from disk for local sources or over HTTP otherwise.
+async fn fetch_entry_bytes(client: &reqwest::Client, manifest_base: &str, path: &str) -> Result<Vec<u8>, EntryFetchError> {
+    let file_url = format!("{}{}", manifest_base, path);
+    let failed = |status: Option<u16>, message: String| EntryFetchError { url: file_url.clone(), status, message };
+
+    if let Some(local) = local_source_path(&file_url) {
+        return fs::read(&local).map_err(|e| failed(None, format!("{}: {}", local.display(), e)));
+    }
+
+    // Connection drops and 5xx from the CDN are retried; a 404 fails right away.
+    http::retry_with_backoff(
+        http::RETRY_BASE_DELAY,
+        || async {
+            let file_resp = client
+                .get(&file_url)
+                .send()
+                .await
+                .map_err(|e| failed(None, format!("{file_url}: {e}")))?;
+
+            let status = file_resp.status();
+            if !status.is_success() {
+                return Err(failed(Some(status.as_u16()), format!("HTTP {status} when fetching {file_url}")));
+            }
+
+            file_resp
+                .bytes()
+                .await
+                .map(|b| b.to_vec())
+                .map_err(|e| failed(None, format!("{file_url}: {e}")))
+        },
+        |err: &EntryFetchError| err.status.is_none_or(http::is_retryable_status),
+    )
+    .await
+}
+
+/// Manifest fetched from the first mirror that answered with a success status
+struct MirrorManifest {
+    mirror: String,
+    manifest_base: String,
+    bytes: Vec<u8>,
+}
+
+async fn fetch_manifest_from_mirrors(
+    client: &reqwest::Client,
+    mirrors: &[String],
+    version: &str,
+) -> Result<MirrorManifest, String> {
+    let mut errors: Vec<String> = Vec::new();
+
+    for mirror in mirrors {
+        if let Some(dir) = local_source_path(mirror) {
+            match read_local_manifest(&dir) {
+                Ok((bytes, manifest_base)) => {
+                    return Ok(MirrorManifest { mirror: mirror.clone(), manifest_base, bytes });
+                }
+                Err(e) => {
+                    errors.push(e);
+                    continue;
+                }
+            }
+        }
+
+        let manifest_url = match build_manifest_url(mirror, version) {
+            Ok(url) => url,
+            Err(e) => {
+                errors.push(format!("{mirror}: {e}"));
+                continue;
+            }
+        };
+
+        // Transient failures are retried on the same mirror before moving on to the next.
+        let fetched = http::retry_with_backoff(
+            http::RETRY_BASE_DELAY,
+            || async {
+                let resp = client
+                    .get(&manifest_url)
+                    .header("Cache-Control", "no-cache, no-store, must-revalidate")
+                    .header("Pragma", "no-cache")
+                    .send()
+                    .await
+                    .map_err(|e| (None, format!("{manifest_url}: {e}")))?;
+
+                let status = resp.status();
+                if !status.is_success() {
+                    return Err((Some(status.as_u16()), format!("HTTP {} when fetching manifest: {}", status, manifest_url)));
+                }
+
+                resp.bytes().await.map_err(|e| (None, format!("{manifest_url}: {e}")))
+            },
+            |(status, _): &(Option<u16>, String)| status.is_none_or(http::is_retryable_status),
+        )
+        .await;
+
+        match fetched {
+            Ok(bytes) => {
+                return Ok(MirrorManifest {
+                    mirror: mirror.clone(),
+                    manifest_base: manifest_dir_url(&manifest_url)?,
+                    bytes: bytes.to_vec(),
+                });
+            }
+            Err((_, message)) => errors.push(message),
+        }
+    }
+
+    Err(format!("all metadata mirrors failed: {}", errors.join("; ")))
+}
+
+/// Reject a download whose length differs from the manifest `size`, catching truncated
+/// proxy responses before they are written.
+fn check_download_size(path: &str, expected: Option<u64>, actual: usize) -> Result<(), String> {
+    match expected {
+        Some(expected) if expected != actual as u64 => Err(format!(
+            "size mismatch for {path}: expected {expected} bytes, got {actual}"
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Write via a sibling `.tmp` file and rename into place, so a crash mid-write leaves
+/// either the old file or none rather than a truncated one.
+fn write_atomic(dest: &Path, bytes: &[u8]) -> Result<(), String> {
+    let tmp = dest.with_extension("tmp");
+    if let Err(e) = fs::write(&tmp, bytes).and_then(|_| fs::rename(&tmp, dest)) {
+        let _ = fs::remove_file(&tmp);
+        return Err(format!("failed to write {}: {}", dest.display(), e));
+    }
+    Ok(())
+}
+
 fn count_files(dir: &Path) -> Result<usize, String> {
     let mut count = 0usize;
     for entry in fs::read_dir(dir).map_err(|e| e.to_string())? {
@@ -128,7 +466,7 @@ fn count_files(dir: &Path) -> Result<usize, String> {
 }
 
 pub fn check_metadata_status(exe_dir: &Path) -> Result<MetadataStatus, String> {
-    let metadata_dir = exe_dir.join("data").join("metadata");
+    let metadata_dir = config::resolve_data_dir(exe_dir).join("metadata");
 
     if !metadata_dir.exists() {
         fs::create_dir_all(&metadata_dir).map_err(|e| e.to_string())?;
@@ -139,67 +477,303 @@ pub fn check_metadata_status(exe_dir: &Path) -> Result<MetadataStatus, String> {
     let has_manifest = manifest_path.exists();
     
     let mut current_version = None;
+    let mut manifest_json: Option<serde_json::Value> = None;
     if has_manifest {
        if let Ok(content) = fs::read(&manifest_path) {
            if let Ok(json) = serde_json::from_slice::<serde_json::Value>(&content) {
                current_version = json.get("package_version")
                    .and_then(|v| v.as_str())
                    .map(|s| s.to_string());
+               manifest_json = Some(json);
            }
        }
     }
 
+    let (metadata_checksum, matches_manifest) =
+        verify_metadata_checksum(&metadata_dir, manifest_json.as_ref())?;
+
     Ok(MetadataStatus {
         path: metadata_dir.to_string_lossy().to_string(),
         is_empty: file_count == 0,
         file_count,
         has_manifest,
         current_version,
+        mirror: None,
+        metadata_checksum,
+        matches_manifest,
+        skipped_cleanup: 0,
+        redownloaded: Vec::new(),
     })
 }
 
-pub async fn fetch_manifest(
+/// 本地化名称文件（`locale/<lang>/` 下），`charater.json` 为历史拼写
+const ITEM_NAME_FILES: &[&str] = &["character.json", "charater.json", "weapon.json"];
+const FALLBACK_LANG: &str = "zh-CN";
+
+#[derive(Deserialize)]
+struct LocaleItem {
+    itemid: Option<String>,
+    name: Option<String>,
+}
+
+fn read_locale_names(locale_dir: &Path, names: &mut HashMap<String, String>) {
+    for file in ITEM_NAME_FILES {
+        let Ok(bytes) = fs::read(locale_dir.join(file)) else {
+            continue;
+        };
+        let Ok(items) = serde_json::from_slice::<Vec<LocaleItem>>(&bytes) else {
+            continue;
+        };
+        for item in items {
+            if let (Some(id), Some(name)) = (item.itemid, item.name) {
+                if !id.is_empty() && !name.is_empty() {
+                    names.entry(id).or_insert(name);
+                }
+            }
+        }
+    }
+}
+
+/// Item id → localized name from the local metadata, falling back to zh-CN for ids missing in `lang`.
+pub fn load_item_names(exe_dir: &Path, lang: &str) -> HashMap<String, String> {
+    let locale_root = config::resolve_data_dir(exe_dir).join("metadata").join("locale");
+    let mut names = HashMap::new();
+    read_locale_names(&locale_root.join(lang), &mut names);
+    if lang != FALLBACK_LANG {
+        read_locale_names(&locale_root.join(FALLBACK_LANG), &mut names);
+    }
+    names
+}
+
+/// Raw manifest JSON from `base_url`, a local folder or a `file://` URL.
+async fn fetch_manifest_json(
     client: &reqwest::Client,
     base_url: &str,
     version: &str,
-) -> Result<RemoteManifest, String> {
-    let url = build_manifest_url(base_url, version)?;
+) -> Result<serde_json::Value, String> {
+    Ok(if let Some(dir) = local_source_path(base_url) {
+        let (bytes, _) = read_local_manifest(&dir)?;
+        serde_json::from_slice(&bytes).map_err(|e| e.to_string())?
+    } else {
+        let url = build_manifest_url(base_url, version)?;
+
+        // The error carries the HTTP status (if any) so only transient failures are retried.
+        http::retry_with_backoff(
+            http::RETRY_BASE_DELAY,
+            || async {
+                let resp = client
+                    .get(&url)
+                    .header("Cache-Control", "no-cache, no-store, must-revalidate")
+                    .header("Pragma", "no-cache")
+                    .send()
+                    .await
+                    .map_err(|e| (None, e.to_string()))?;
+
+                let status = resp.status();
+                if !status.is_success() {
+                    return Err((Some(status.as_u16()), format!("HTTP {} when fetching manifest: {}", status, url)));
+                }
 
-    let resp = client
-        .get(&url)
-        .header("Cache-Control", "no-cache, no-store, must-revalidate")
-        .header("Pragma", "no-cache")
-        .send()
+                resp.json::<serde_json::Value>().await.map_err(|e| (None, e.to_string()))
+            },
+            |(status, _): &(Option<u16>, String)| status.is_none_or(http::is_retryable_status),
+        )
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(|(_, message)| message)?
+    })
+}
+
+/// One file listed in a manifest
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestEntry {
+    pub path: String,
+    pub size: Option<u64>,
+    /// Normalized with [`normalize_sha256`]; `None` when the manifest has none
+    pub checksum: Option<String>,
+}
+
+fn manifest_entry(entry: &serde_json::Value, key: Option<&str>) -> Option<ManifestEntry> {
+    let path = entry.get("path").and_then(|v| v.as_str()).or(key)?;
+    Some(ManifestEntry {
+        path: path.to_string(),
+        size: entry.get("size").and_then(|v| v.as_u64()),
+        checksum: entry
+            .get("checksum")
+            .and_then(|v| v.as_str())
+            .map(normalize_sha256)
+            .filter(|c| !c.is_empty()),
+    })
+}
+
+/// Entries of a manifest. Generators emit `entries` either as an array or as an object
+/// keyed by path; for the latter the key stands in for a missing `path`. Array entries
+/// without a `path` are skipped.
+fn manifest_entries(json: &serde_json::Value) -> Vec<ManifestEntry> {
+    match json.get("entries") {
+        Some(serde_json::Value::Array(entries)) => entries.iter().filter_map(|e| manifest_entry(e, None)).collect(),
+        Some(serde_json::Value::Object(entries)) => entries
+            .iter()
+            .filter_map(|(key, e)| manifest_entry(e, Some(key)))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
 
-    if !resp.status().is_success() {
-        return Err(format!("HTTP {} when fetching manifest: {}", resp.status(), url));
+/// [`manifest_entries`] for a manifest about to be downloaded: one without an `entries`
+/// array or object is an error rather than an update that silently fetches nothing.
+fn require_manifest_entries(json: &serde_json::Value) -> Result<Vec<ManifestEntry>, String> {
+    match json.get("entries") {
+        Some(serde_json::Value::Array(_) | serde_json::Value::Object(_)) => Ok(manifest_entries(json)),
+        _ => Err("manifest has no entries array or object".to_string()),
     }
+}
+
+/// File list of a remote manifest, for previewing what an update downloads
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetadataEntries {
+    pub package_version: Option<String>,
+    pub entries: Vec<ManifestEntry>,
+    /// Sum of the entries' `size`; entries without one count as 0
+    pub total_size: u64,
+}
+
+pub async fn fetch_metadata_entries(
+    client: &reqwest::Client,
+    base_url: &str,
+    version: &str,
+) -> Result<MetadataEntries, String> {
+    let json = fetch_manifest_json(client, base_url, version).await?;
+    check_manifest_schema(&json)?;
+    let entries = manifest_entries(&json);
+    Ok(MetadataEntries {
+        package_version: json.get("package_version").and_then(|v| v.as_str()).map(|s| s.to_string()),
+        total_size: entries.iter().filter_map(|e| e.size).sum(),
+        entries,
+    })
+}
 
-    let json: serde_json::Value = resp.json().await.map_err(|e| e.to_string())?;
+pub async fn fetch_manifest(
+    client: &reqwest::Client,
+    base_url: &str,
+    version: &str,
+) -> Result<RemoteManifest, String> {
+    let json = fetch_manifest_json(client, base_url, version).await?;
+    let schema_version = check_manifest_schema(&json)?;
     let package_version = json.get("package_version").and_then(|v| v.as_str()).map(|s| s.to_string());
     let metadata_checksum = json.get("metadata_checksum").and_then(|v| v.as_str()).map(|s| s.to_string());
     let item_count = json.get("item_count").and_then(|v| v.as_u64()).map(|v| v as usize);
 
     let total_size = json
         .get("entries")
-        .and_then(|v| v.as_array())
-        .map(|arr| {
-            arr.iter()
-                .filter_map(|e| e.get("size").and_then(|s| s.as_u64()))
-                .sum::<u64>() as usize
-        });
+        .is_some()
+        .then(|| manifest_entries(&json).iter().filter_map(|e| e.size).sum::<u64>() as usize);
 
-    Ok(RemoteManifest { package_version, metadata_checksum, item_count, total_size })
+    Ok(RemoteManifest { schema_version, package_version, metadata_checksum, item_count, total_size })
 }
 
-fn cleanup_extra_files(metadata_dir: &Path, allowed: &HashSet<String>) {
+/// Entry paths that differ between two manifest versions, each list sorted.
+#[derive(Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetadataDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// Present in both with a different checksum
+    pub changed: Vec<String>,
+}
+
+fn manifest_checksums(json: &serde_json::Value) -> HashMap<String, String> {
+    manifest_entries(json)
+        .into_iter()
+        .map(|e| (e.path, e.checksum.unwrap_or_default()))
+        .collect()
+}
+
+fn diff_manifests(old: &serde_json::Value, new: &serde_json::Value) -> MetadataDiff {
+    let old = manifest_checksums(old);
+    let new = manifest_checksums(new);
+    let mut diff = MetadataDiff::default();
+    for (path, checksum) in &new {
+        match old.get(path) {
+            None => diff.added.push(path.clone()),
+            Some(prev) if prev != checksum => diff.changed.push(path.clone()),
+            Some(_) => {}
+        }
+    }
+    diff.removed = old.keys().filter(|p| !new.contains_key(*p)).cloned().collect();
+    diff.added.sort();
+    diff.removed.sort();
+    diff.changed.sort();
+    diff
+}
+
+/// Fetch the manifests of two package versions and compare their entries.
+pub async fn metadata_diff(
+    exe_dir: &Path,
+    client: &reqwest::Client,
+    base_url: Option<String>,
+    old_version: &str,
+    new_version: &str,
+) -> Result<MetadataDiff, String> {
+    let mirrors = metadata_mirror_chain(exe_dir, base_url);
+    if mirrors.is_empty() {
+        return Err("no metadata mirror configured".to_string());
+    }
+
+    let mut manifests = Vec::with_capacity(2);
+    for version in [old_version, new_version] {
+        let fetched = fetch_manifest_from_mirrors(client, &mirrors, version).await?;
+        let json: serde_json::Value = serde_json::from_slice(&fetched.bytes).map_err(|e| e.to_string())?;
+        check_manifest_schema(&json)?;
+        manifests.push(json);
+    }
+
+    Ok(diff_manifests(&manifests[0], &manifests[1]))
+}
+
+/// Any difference from the remote `package_version` counts as stale, so a
+/// republished or rolled-back package is picked up too.
+fn metadata_is_stale(local: Option<&str>, remote: Option<&str>) -> bool {
+    let normalize = |v: &str| v.trim().trim_start_matches(['v', 'V']).to_string();
+    match (local, remote) {
+        (_, None) => false,
+        (None, Some(_)) => true,
+        (Some(local), Some(remote)) => normalize(local) != normalize(remote),
+    }
+}
+
+/// Compare the local manifest's `package_version` with the remote manifest in one call.
+pub async fn metadata_update_available(
+    exe_dir: &Path,
+    client: &reqwest::Client,
+    base_url: &str,
+    version: &str,
+) -> Result<MetadataUpdateCheck, String> {
+    let local = check_metadata_status(exe_dir)?.current_version;
+    let remote = fetch_manifest(client, base_url, version).await?;
+
+    Ok(MetadataUpdateCheck {
+        update_available: metadata_is_stale(local.as_deref(), remote.package_version.as_deref()),
+        local,
+        remote: remote.package_version,
+        item_count: remote.item_count,
+        total_size: remote.total_size,
+    })
+}
+
+/// Config `preserveExtraFiles`: keep local files that are not in the manifest (user overlays)
+fn preserve_extra_files(exe_dir: &Path) -> bool {
+    config::read_config_field(exe_dir, "preserveExtraFiles").unwrap_or(false)
+}
+
+/// Local files (other than `manifest.json`) whose relative path is not in `allowed`
+fn extra_files(metadata_dir: &Path, allowed: &HashSet<String>) -> Vec<PathBuf> {
     if !metadata_dir.exists() {
-        return;
+        return Vec::new();
     }
 
-    let mut to_remove: Vec<PathBuf> = Vec::new();
+    let mut extra: Vec<PathBuf> = Vec::new();
 
     for entry in WalkDir::new(metadata_dir).into_iter().flatten() {
         let path = entry.path();
@@ -212,14 +786,12 @@ fn cleanup_extra_files(metadata_dir: &Path, allowed: &HashSet<String>) {
         if let Some(rel) = path.strip_prefix(metadata_dir).ok() {
             let rel_str = rel.to_string_lossy().replace('\\', "/");
             if !allowed.contains(&rel_str) {
-                to_remove.push(path.to_path_buf());
+                extra.push(path.to_path_buf());
             }
         }
     }
 
-    for file in to_remove {
-        let _ = fs::remove_file(&file);
-    }
+    extra
 }
 
 async fn download_metadata<F>(
@@ -233,7 +805,7 @@ async fn download_metadata<F>(
 where
     F: FnMut(DownloadProgress),
 {
-    let metadata_dir = exe_dir.join("data").join("metadata");
+    let metadata_dir = config::resolve_data_dir(exe_dir).join("metadata");
 
     if clean_first && metadata_dir.exists() {
         fs::remove_dir_all(&metadata_dir).map_err(|e| e.to_string())?;
@@ -249,101 +821,85 @@ where
         file_count: 0,
         has_manifest: false,
         current_version: None,
+        mirror: None,
+        metadata_checksum: None,
+        matches_manifest: false,
+        skipped_cleanup: 0,
+        redownloaded: Vec::new(),
     };
 
-    let Some(base) = base_url.and_then(|s| {
-        let trimmed = s.trim().to_string();
-        if trimmed.is_empty() { None } else { Some(trimmed) }
-    }) else {
+    let mirrors = metadata_mirror_chain(exe_dir, base_url);
+    if mirrors.is_empty() {
         return Ok(status);
-    };
+    }
 
     let ver = version.unwrap_or_else(|| "latest".to_string());
-    let manifest_url = build_manifest_url(&base, &ver)?;
-    let manifest_base = manifest_url
-        .rsplit_once('/')
-        .map(|(head, _)| {
-            let mut h = head.to_string();
-            if !h.ends_with('/') {
-                h.push('/');
-            }
-            h
-        })
-        .ok_or_else(|| "Invalid manifest url".to_string())?;
 
-    let resp = client
-        .get(&manifest_url)
-        .header("Cache-Control", "no-cache, no-store, must-revalidate")
-        .header("Pragma", "no-cache")
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    if !resp.status().is_success() {
-        return Err(format!("HTTP {} when fetching manifest: {}", resp.status(), manifest_url));
-    }
+    let MirrorManifest { mirror, manifest_base, bytes: manifest_bytes } =
+        fetch_manifest_from_mirrors(client, &mirrors, &ver).await?;
+    let manifest_json: serde_json::Value = serde_json::from_slice(&manifest_bytes).map_err(|e| e.to_string())?;
+    check_manifest_schema(&manifest_json)?;
 
-    let manifest_bytes = resp.bytes().await.map_err(|e| e.to_string())?;
     let manifest_path = metadata_dir.join("manifest.json");
     if let Some(parent) = manifest_path.parent() {
         fs::create_dir_all(parent).map_err(|e| e.to_string())?;
     }
-    fs::write(&manifest_path, &manifest_bytes).map_err(|e| e.to_string())?;
-
-    let manifest_json: serde_json::Value = serde_json::from_slice(&manifest_bytes).map_err(|e| e.to_string())?;
+    write_atomic(&manifest_path, &manifest_bytes)?;
 
     let mut manifest_paths: Vec<String> = Vec::new();
 
-    if let Some(entries) = manifest_json.get("entries").and_then(|v| v.as_array()) {
-        let total = entries.len();
-        for (i, entry) in entries.iter().enumerate() {
-            let Some(path) = entry.get("path").and_then(|v| v.as_str()) else {
-                continue;
-            };
-
-            manifest_paths.push(path.to_string());
-
-            on_progress(DownloadProgress {
-                current: i + 1,
-                total,
-                filename: path.to_string(),
-            });
-
-            let file_url = format!("{}{}", manifest_base, path);
-            let dest_path = metadata_dir.join(path);
-            if let Some(parent) = dest_path.parent() {
-                fs::create_dir_all(parent).map_err(|e| e.to_string())?;
-            }
+    let entries = require_manifest_entries(&manifest_json)?;
+    let total = entries.len();
+    for (i, entry) in entries.iter().enumerate() {
+        let path = entry.path.as_str();
 
-            let file_resp = client
-                .get(&file_url)
-                .send()
-                .await
-                .map_err(|e| e.to_string())?;
+        manifest_paths.push(path.to_string());
 
-            if !file_resp.status().is_success() {
-                return Err(format!("HTTP {} when fetching {}", file_resp.status(), path));
-            }
+        on_progress(DownloadProgress {
+            current: i + 1,
+            total,
+            filename: path.to_string(),
+        });
 
-            let bytes = file_resp.bytes().await.map_err(|e| e.to_string())?;
-            fs::write(&dest_path, &bytes).map_err(|e| e.to_string())?;
+        let dest_path = metadata_dir.join(path);
+        if let Some(parent) = dest_path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
         }
+
+        let bytes = fetch_entry_bytes(client, &manifest_base, path).await.map_err(|e| e.message)?;
+        write_atomic(&dest_path, &bytes)?;
     }
 
+    let mut skipped_cleanup = 0;
     if !manifest_paths.is_empty() {
         let allowed: HashSet<String> = manifest_paths.into_iter().collect();
-        cleanup_extra_files(&metadata_dir, &allowed);
+        let extra = extra_files(&metadata_dir, &allowed);
+        if preserve_extra_files(exe_dir) {
+            skipped_cleanup = extra.len();
+        } else {
+            for file in extra {
+                let _ = fs::remove_file(&file);
+            }
+        }
     }
 
     let file_count = count_files(&metadata_dir)?;
     let has_manifest = metadata_dir.join("manifest.json").exists();
 
+    let (metadata_checksum, matches_manifest) =
+        verify_metadata_checksum(&metadata_dir, Some(&manifest_json))?;
+
     status = MetadataStatus {
         path: metadata_dir.to_string_lossy().to_string(),
         is_empty: file_count == 0,
         file_count,
         has_manifest,
         current_version: manifest_json.get("package_version").and_then(|v| v.as_str()).map(|s| s.to_string()),
+        mirror: Some(mirror),
+        metadata_checksum,
+        matches_manifest,
+        skipped_cleanup,
+        redownloaded: Vec::new(),
     };
 
     Ok(status)
@@ -372,7 +928,7 @@ pub async fn update_metadata<F>(
 where
     F: FnMut(UpdateProgress),
 {
-    let metadata_dir = exe_dir.join("data").join("metadata");
+    let metadata_dir = config::resolve_data_dir(exe_dir).join("metadata");
 
     if !metadata_dir.exists() {
         fs::create_dir_all(&metadata_dir).map_err(|e| e.to_string())?;
@@ -384,27 +940,19 @@ where
         file_count: 0,
         has_manifest: false,
         current_version: None,
+        mirror: None,
+        metadata_checksum: None,
+        matches_manifest: false,
+        skipped_cleanup: 0,
+        redownloaded: Vec::new(),
     };
 
-    let Some(base) = base_url.and_then(|s| {
-        let trimmed = s.trim().to_string();
-        if trimmed.is_empty() { None } else { Some(trimmed) }
-    }) else {
+    let mirrors = metadata_mirror_chain(exe_dir, base_url);
+    if mirrors.is_empty() {
         return Ok(status);
-    };
+    }
 
     let ver = version.unwrap_or_else(|| "latest".to_string());
-    let manifest_url = build_manifest_url(&base, &ver)?;
-    let manifest_base = manifest_url
-        .rsplit_once('/')
-        .map(|(head, _)| {
-            let mut h = head.to_string();
-            if !h.ends_with('/') {
-                h.push('/');
-            }
-            h
-        })
-        .ok_or_else(|| "Invalid manifest url".to_string())?;
 
     // Emit an early progress event so the UI won't be stuck at "preparing" if the manifest request is slow.
     on_progress(UpdateProgress::Verifying {
@@ -413,42 +961,23 @@ where
         path: "manifest.json".to_string(),
     });
 
-    // Fetch remote manifest
-    let resp = client
-        .get(&manifest_url)
-        .header("Cache-Control", "no-cache, no-store, must-revalidate")
-        .header("Pragma", "no-cache")
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    if !resp.status().is_success() {
-        return Err(format!("HTTP {} when fetching manifest", resp.status()));
-    }
-
-    let manifest_bytes = resp.bytes().await.map_err(|e| e.to_string())?;
+    // Fetch remote manifest from the first reachable mirror; files come from the same base
+    let MirrorManifest { mirror, manifest_base, bytes: manifest_bytes } =
+        fetch_manifest_from_mirrors(client, &mirrors, &ver).await?;
     let manifest_json: serde_json::Value = serde_json::from_slice(&manifest_bytes).map_err(|e| e.to_string())?;
+    check_manifest_schema(&manifest_json)?;
 
-    let entries = manifest_json
-        .get("entries")
-        .and_then(|v| v.as_array())
-        .cloned()
-        .unwrap_or_default();
+    let entries = require_manifest_entries(&manifest_json)?;
 
     let total_entries = entries.len();
     let mut manifest_paths: HashSet<String> = HashSet::new();
-    let mut to_download: Vec<(String, String)> = Vec::new(); // (path, expected_checksum)
+    let mut to_download: Vec<(String, String, Option<u64>)> = Vec::new(); // (path, expected_checksum, expected_size)
+    let mut redownloaded: Vec<String> = Vec::new();
 
     // Phase 1: Verify existing files
     for (i, entry) in entries.iter().enumerate() {
-        let Some(path) = entry.get("path").and_then(|v| v.as_str()) else {
-            continue;
-        };
-        let expected_checksum = entry
-            .get("checksum")
-            .and_then(|v| v.as_str())
-            .unwrap_or("")
-            .to_uppercase();
+        let path = entry.path.as_str();
+        let expected_checksum = entry.checksum.clone().unwrap_or_default();
 
         manifest_paths.insert(path.to_string());
 
@@ -465,68 +994,105 @@ where
                 // No checksum in manifest, skip verification
                 false
             } else {
-                match compute_sha256(&local_path) {
-                    Ok(local_hash) => local_hash.to_uppercase() != expected_checksum,
+                let stale = match compute_sha256(&local_path) {
+                    Ok(local_hash) => {
+                        let stale = normalize_sha256(&local_hash) != expected_checksum;
+                        if stale {
+                            tracing::warn!(
+                                "[metadata] checksum mismatch for {}: expected {}, local {}",
+                                path, expected_checksum, local_hash
+                            );
+                        }
+                        stale
+                    }
                     Err(_) => true, // Cannot read file, re-download
+                };
+                if stale {
+                    redownloaded.push(path.to_string());
                 }
+                stale
             }
         } else {
             true // File doesn't exist
         };
 
         if needs_download {
-            to_download.push((path.to_string(), expected_checksum));
+            let expected_size = entry.size;
+            to_download.push((path.to_string(), expected_checksum, expected_size));
         }
     }
 
     // Phase 2: Download missing/changed files (only if there are files to download)
     let download_total = to_download.len();
     if download_total > 0 {
-        for (i, (path, _expected_checksum)) in to_download.iter().enumerate() {
+        for (i, (path, expected_checksum, expected_size)) in to_download.iter().enumerate() {
             on_progress(UpdateProgress::Downloading {
                 current: i + 1,
                 total: download_total,
                 path: path.clone(),
             });
 
-            let file_url = format!("{}{}", manifest_base, path);
             let dest_path = metadata_dir.join(path);
             
             if let Some(parent) = dest_path.parent() {
                 fs::create_dir_all(parent).map_err(|e| e.to_string())?;
             }
 
-            let file_resp = client
-                .get(&file_url)
-                .send()
-                .await
-                .map_err(|e| e.to_string())?;
+            // Verify right after writing so a corrupt download is caught in this run;
+            // a mismatch is downloaded once more before giving up.
+            let mut attempt = 0;
+            loop {
+                attempt += 1;
+
+                let failed = |url: String, status: Option<u16>| UpdateProgress::Failed {
+                    path: path.clone(),
+                    url,
+                    status,
+                };
+
+                let bytes = match fetch_entry_bytes(client, &manifest_base, path).await {
+                    Ok(bytes) => bytes,
+                    Err(e) => {
+                        on_progress(failed(e.url, e.status));
+                        return Err(e.message);
+                    }
+                };
+                if let Err(e) = check_download_size(path, *expected_size, bytes.len()) {
+                    on_progress(failed(format!("{manifest_base}{path}"), None));
+                    return Err(e);
+                }
+                write_atomic(&dest_path, &bytes)?;
 
-            if !file_resp.status().is_success() {
-                return Err(format!("HTTP {} when fetching {}", file_resp.status(), path));
-            }
+                if expected_checksum.is_empty() {
+                    break;
+                }
 
-            let bytes = file_resp.bytes().await.map_err(|e| e.to_string())?;
-            fs::write(&dest_path, &bytes).map_err(|e| e.to_string())?;
+                on_progress(UpdateProgress::Verifying {
+                    current: i + 1,
+                    total: download_total,
+                    path: path.clone(),
+                });
+
+                let actual = normalize_sha256(&compute_sha256(&dest_path)?);
+                if actual == *expected_checksum {
+                    break;
+                }
+                if attempt >= 2 {
+                    on_progress(failed(format!("{manifest_base}{path}"), None));
+                    return Err(format!(
+                        "checksum mismatch for {path}: expected {expected_checksum}, got {actual}"
+                    ));
+                }
+            }
         }
     }
 
     // Phase 3: Clean up extra files
-    let mut to_remove: Vec<PathBuf> = Vec::new();
-    for entry in WalkDir::new(&metadata_dir).into_iter().flatten() {
-        let path = entry.path();
-        if path.is_dir() {
-            continue;
-        }
-        if path.file_name().map(|n| n == "manifest.json").unwrap_or(false) {
-            continue;
-        }
-        if let Some(rel) = path.strip_prefix(&metadata_dir).ok() {
-            let rel_str = rel.to_string_lossy().replace('\\', "/");
-            if !manifest_paths.contains(&rel_str) {
-                to_remove.push(path.to_path_buf());
-            }
-        }
+    let mut to_remove = extra_files(&metadata_dir, &manifest_paths);
+    let mut skipped_cleanup = 0;
+    if preserve_extra_files(exe_dir) {
+        skipped_cleanup = to_remove.len();
+        to_remove.clear();
     }
 
     // Only send clean progress if there are files to remove
@@ -547,19 +1113,455 @@ where
     if let Some(parent) = manifest_path.parent() {
         fs::create_dir_all(parent).map_err(|e| e.to_string())?;
     }
-    fs::write(&manifest_path, &manifest_bytes).map_err(|e| e.to_string())?;
+    write_atomic(&manifest_path, &manifest_bytes)?;
 
     // Build final status
     let file_count = count_files(&metadata_dir)?;
     let has_manifest = manifest_path.exists();
 
+    let (metadata_checksum, matches_manifest) =
+        verify_metadata_checksum(&metadata_dir, Some(&manifest_json))?;
+
     status = MetadataStatus {
         path: metadata_dir.to_string_lossy().to_string(),
         is_empty: file_count == 0,
         file_count,
         has_manifest,
         current_version: manifest_json.get("package_version").and_then(|v| v.as_str()).map(|s| s.to_string()),
+        mirror: Some(mirror),
+        metadata_checksum,
+        matches_manifest,
+        skipped_cleanup,
+        redownloaded,
     };
 
     Ok(status)
 }
+
+/// Rate-up items of one banner run, from `locale/<lang>/gacha_pool.json`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RateUpWindow {
+    #[serde(rename = "poolId", default)]
+    pub pool_id: String,
+    #[serde(default)]
+    pub up: Vec<String>,
+    /// Seconds or milliseconds; missing / 0 means unbounded
+    pub start_time: Option<i64>,
+    pub end_time: Option<i64>,
+}
+
+fn to_secs(ts: i64) -> i64 {
+    if ts < 1_000_000_000_000 { ts } else { ts / 1000 }
+}
+
+impl RateUpWindow {
+    /// Whether `item_id` was on rate-up when pulled at `pulled_at` (seconds or ms).
+    pub fn is_rate_up(&self, item_id: &str, pulled_at: i64) -> bool {
+        if !self.up.iter().any(|id| id == item_id) {
+            return false;
+        }
+        let ts = to_secs(pulled_at);
+        let after_start = self.start_time.filter(|t| *t > 0).is_none_or(|t| ts >= to_secs(t));
+        let before_end = self.end_time.filter(|t| *t > 0).is_none_or(|t| ts <= to_secs(t));
+        after_start && before_end
+    }
+}
+
+/// Rate-up windows for `pool_id` from the local metadata; empty when the banner is unknown.
+pub fn load_rate_ups(exe_dir: &Path, pool_id: &str) -> Vec<RateUpWindow> {
+    let path = config::resolve_data_dir(exe_dir)
+        .join("metadata")
+        .join("locale")
+        .join(FALLBACK_LANG)
+        .join("gacha_pool.json");
+    let Ok(bytes) = fs::read(path) else {
+        return Vec::new();
+    };
+    serde_json::from_slice::<Vec<RateUpWindow>>(&bytes)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|w| w.pool_id == pool_id && !w.up.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_locale_names_keeps_first_name_per_id() {
+        let dir = std::env::temp_dir().join(format!("endcat-locale-names-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("character.json"),
+            r#"[{"itemid":"char_1012","name":"Perlica"},{"itemid":"char_x","name":""}]"#,
+        )
+        .unwrap();
+        fs::write(dir.join("weapon.json"), r#"[{"itemid":"wpn_01","name":"Blade"}]"#).unwrap();
+
+        let mut names = HashMap::new();
+        names.insert("wpn_01".to_string(), "Localized".to_string());
+        read_locale_names(&dir, &mut names);
+
+        assert_eq!(names.get("char_1012").map(String::as_str), Some("Perlica"));
+        assert_eq!(names.get("wpn_01").map(String::as_str), Some("Localized"));
+        assert!(!names.contains_key("char_x"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn extra_files_skips_manifest_and_listed_paths() {
+        let dir = std::env::temp_dir().join(format!("endcat-extra-files-{}", std::process::id()));
+        fs::create_dir_all(dir.join("locale")).unwrap();
+        for name in ["manifest.json", "locale/a.json", "locale/overlay.json"] {
+            fs::write(dir.join(name), b"{}").unwrap();
+        }
+
+        let allowed: HashSet<String> = ["locale/a.json".to_string()].into_iter().collect();
+        assert_eq!(extra_files(&dir, &allowed), vec![dir.join("locale").join("overlay.json")]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn write_atomic_replaces_file_without_leftovers() {
+        let dir = std::env::temp_dir().join(format!("endcat-write-atomic-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let dest = dir.join("manifest.json");
+
+        write_atomic(&dest, b"old").unwrap();
+        write_atomic(&dest, b"new").unwrap();
+
+        assert_eq!(fs::read(&dest).unwrap(), b"new");
+        assert!(!dest.with_extension("tmp").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn check_manifest_schema_rejects_newer_versions() {
+        assert_eq!(check_manifest_schema(&serde_json::json!({ "entries": [] })), Ok(None));
+        assert_eq!(check_manifest_schema(&serde_json::json!({ "schemaVersion": 1 })), Ok(Some(1)));
+        let err = check_manifest_schema(&serde_json::json!({ "schemaVersion": SUPPORTED_MANIFEST_SCHEMA + 1 })).unwrap_err();
+        assert!(err.contains("please update the app"));
+    }
+
+    #[test]
+    fn local_source_path_accepts_file_urls_and_paths() {
+        assert_eq!(local_source_path("https://cdn.example/meta/"), None);
+        assert_eq!(local_source_path("  "), None);
+        assert_eq!(local_source_path("file:///home/me/meta"), Some(PathBuf::from("/home/me/meta")));
+        assert_eq!(local_source_path("file:///C:/meta"), Some(PathBuf::from("C:/meta")));
+        assert_eq!(local_source_path("D:\\meta"), Some(PathBuf::from("D:\\meta")));
+    }
+
+    #[test]
+    fn manifest_entries_reads_array_shape() {
+        let manifest = serde_json::json!({ "entries": [
+            { "path": "a.json", "size": 1, "checksum": "aa" },
+            { "size": 2 }
+        ] });
+        assert_eq!(
+            manifest_entries(&manifest),
+            vec![ManifestEntry { path: "a.json".into(), size: Some(1), checksum: Some("AA".into()) }]
+        );
+        assert!(require_manifest_entries(&serde_json::json!({ "entries": "a.json" })).is_err());
+        assert!(require_manifest_entries(&serde_json::json!({})).is_err());
+    }
+
+    /// A `source/` mirror directory and an `app/` exe dir under one temp root, removed on
+    /// drop so a failing assert doesn't leave it behind.
+    struct LocalSource {
+        root: PathBuf,
+        source: PathBuf,
+        exe_dir: PathBuf,
+    }
+
+    impl LocalSource {
+        fn write_manifest(&self, manifest: &serde_json::Value) {
+            fs::write(self.source.join("manifest.json"), manifest.to_string()).unwrap();
+        }
+
+        fn checksum(&self, path: &str) -> String {
+            compute_sha256(&self.source.join(path)).unwrap()
+        }
+
+        fn base_url(&self) -> String {
+            format!("file://{}", self.source.to_string_lossy())
+        }
+
+        fn metadata_dir(&self) -> PathBuf {
+            config::resolve_data_dir(&self.exe_dir).join("metadata")
+        }
+    }
+
+    impl Drop for LocalSource {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+
+    /// Write `entries` (relative path, contents) into a fresh local metadata source;
+    /// the caller adds the manifest.
+    fn local_source_fixture(name: &str, entries: &[(&str, &[u8])]) -> LocalSource {
+        let root = std::env::temp_dir().join(format!("endcat-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let fixture = LocalSource { source: root.join("source"), exe_dir: root.join("app"), root };
+        fs::create_dir_all(&fixture.exe_dir).unwrap();
+        for (path, contents) in entries {
+            let dest = fixture.source.join(path);
+            fs::create_dir_all(dest.parent().unwrap()).unwrap();
+            fs::write(dest, contents).unwrap();
+        }
+        fixture
+    }
+
+    #[tokio::test]
+    async fn update_metadata_accepts_map_style_entries() {
+        let fixture = local_source_fixture("map-meta", &[("locale/a.json", b"{}"), ("b.json", b"[]")]);
+        // Keyed by path; an explicit `path` wins over the key.
+        fixture.write_manifest(&serde_json::json!({
+            "package_version": "2.0.0",
+            "entries": {
+                "locale/a.json": { "checksum": fixture.checksum("locale/a.json"), "size": 2 },
+                "ignored-key": { "path": "b.json" }
+            }
+        }));
+
+        let client = reqwest::Client::new();
+        let status = update_metadata(&fixture.exe_dir, &client, Some(fixture.base_url()), None, |_| {})
+            .await
+            .unwrap();
+
+        let metadata_dir = fixture.metadata_dir();
+        assert_eq!(status.current_version.as_deref(), Some("2.0.0"));
+        assert_eq!(fs::read(metadata_dir.join("locale/a.json")).unwrap(), b"{}");
+        assert_eq!(fs::read(metadata_dir.join("b.json")).unwrap(), b"[]");
+    }
+
+    #[tokio::test]
+    async fn update_metadata_copies_and_verifies_local_source() {
+        let fixture = local_source_fixture("local-meta", &[("locale/a.json", b"{}")]);
+        fixture.write_manifest(&serde_json::json!({
+            "package_version": "1.0.0",
+            "entries": [{ "path": "locale/a.json", "checksum": fixture.checksum("locale/a.json"), "size": 2 }]
+        }));
+
+        let client = reqwest::Client::new();
+        let status = update_metadata(&fixture.exe_dir, &client, Some(fixture.base_url()), None, |_| {})
+            .await
+            .unwrap();
+
+        assert_eq!(status.current_version.as_deref(), Some("1.0.0"));
+        let copied = fixture.metadata_dir().join("locale/a.json");
+        assert_eq!(fs::read(&copied).unwrap(), b"{}");
+        assert!(status.redownloaded.is_empty());
+
+        // A stale local file is reported once it has been fetched again.
+        fs::write(&copied, b"stale").unwrap();
+        let status = update_metadata(&fixture.exe_dir, &client, Some(fixture.base_url()), None, |_| {})
+            .await
+            .unwrap();
+        assert_eq!(status.redownloaded, vec!["locale/a.json".to_string()]);
+        assert_eq!(fs::read(&copied).unwrap(), b"{}");
+
+        // A corrupted local copy fails checksum verification the same way a download would.
+        fs::write(fixture.source.join("locale/a.json"), b"[]").unwrap();
+        fs::remove_file(&copied).unwrap();
+        let mut failed_url = None;
+        let result = update_metadata(&fixture.exe_dir, &client, Some(fixture.base_url()), None, |p| {
+            if let UpdateProgress::Failed { url, status, .. } = p {
+                assert_eq!(status, None);
+                failed_url = Some(url);
+            }
+        })
+        .await;
+        let Err(err) = result else {
+            panic!("corrupted local entry should be rejected");
+        };
+        assert!(err.contains("checksum mismatch"), "{err}");
+        assert!(failed_url.is_some_and(|url| url.ends_with("locale/a.json")));
+    }
+
+    #[test]
+    fn build_manifest_url_fills_version_placeholder() {
+        assert_eq!(
+            build_manifest_url("https://cdn.example/meta/{version}/", "1.2.0").unwrap(),
+            "https://cdn.example/meta/1.2.0/manifest.json"
+        );
+        assert_eq!(
+            build_manifest_url("https://cdn.example/meta/{version}", " ").unwrap(),
+            "https://cdn.example/meta/latest/manifest.json"
+        );
+    }
+
+    #[test]
+    fn build_manifest_url_inserts_repo_version() {
+        assert_eq!(
+            build_manifest_url("https://cdn.jsdelivr.net/gh/BoxCatTeam/endfield-cat-metadata", "1.0.0").unwrap(),
+            "https://cdn.jsdelivr.net/gh/BoxCatTeam/endfield-cat-metadata@v1.0.0/manifest.json"
+        );
+        assert_eq!(
+            build_manifest_url("https://cdn.jsdelivr.net/gh/BoxCatTeam/endfield-cat-metadata@main/data", "2.0.0").unwrap(),
+            "https://cdn.jsdelivr.net/gh/BoxCatTeam/endfield-cat-metadata@v2.0.0/data/manifest.json"
+        );
+    }
+
+    #[test]
+    fn build_manifest_url_trims_existing_manifest() {
+        assert_eq!(
+            build_manifest_url("https://cdn.example/meta/manifest.json", "1.0.0").unwrap(),
+            "https://cdn.example/meta/manifest.json"
+        );
+        assert!(build_manifest_url("  ", "1.0.0").is_err());
+    }
+
+    #[test]
+    fn normalize_sha256_strips_prefix_and_case() {
+        let upper = "ABCDEF0123456789";
+        assert_eq!(normalize_sha256("abcdef0123456789"), upper);
+        assert_eq!(normalize_sha256("sha256:abcdef0123456789"), upper);
+        assert_eq!(normalize_sha256("  SHA256:AbCdEf0123456789\n"), upper);
+        assert_eq!(normalize_sha256(""), "");
+    }
+
+    #[tokio::test]
+    async fn update_metadata_accepts_prefixed_lowercase_checksums() {
+        let fixture = local_source_fixture("prefixed-sum", &[("a.json", b"{}")]);
+        let checksum = format!("sha256:{}", fixture.checksum("a.json").to_lowercase());
+        fixture.write_manifest(&serde_json::json!({ "entries": [{ "path": "a.json", "checksum": checksum }] }));
+
+        let client = reqwest::Client::new();
+        let base = fixture.source.to_string_lossy().to_string();
+        update_metadata(&fixture.exe_dir, &client, Some(base.clone()), None, |_| {}).await.unwrap();
+        // A second run must find the file up to date instead of fetching it again.
+        let status = update_metadata(&fixture.exe_dir, &client, Some(base), None, |_| {}).await.unwrap();
+        assert!(status.redownloaded.is_empty());
+    }
+
+    #[tokio::test]
+    async fn fetch_metadata_entries_lists_files_and_total_size() {
+        let dir = std::env::temp_dir().join(format!("endcat-meta-entries-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let manifest = serde_json::json!({ "package_version": "1.0.0", "entries": [
+            { "path": "a.json", "size": 10, "checksum": "sha256:abc" },
+            { "path": "b.json" },
+            { "size": 99 }
+        ] });
+        fs::write(dir.join("manifest.json"), manifest.to_string()).unwrap();
+
+        let listed = fetch_metadata_entries(&reqwest::Client::new(), &dir.to_string_lossy(), "latest")
+            .await
+            .unwrap();
+        assert_eq!(listed.package_version.as_deref(), Some("1.0.0"));
+        assert_eq!(listed.total_size, 10);
+        assert_eq!(
+            listed.entries,
+            vec![
+                ManifestEntry { path: "a.json".into(), size: Some(10), checksum: Some("ABC".into()) },
+                ManifestEntry { path: "b.json".into(), size: None, checksum: None },
+            ]
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn diff_manifests_reports_added_removed_changed() {
+        let old = serde_json::json!({ "entries": [
+            { "path": "a.json", "checksum": "aa" },
+            { "path": "b.json", "checksum": "bb" },
+            { "path": "c.json", "checksum": "cc" }
+        ]});
+        let new = serde_json::json!({ "entries": [
+            { "path": "a.json", "checksum": "AA" },
+            { "path": "c.json", "checksum": "c2" },
+            { "path": "d.json", "checksum": "dd" }
+        ]});
+        assert_eq!(
+            diff_manifests(&old, &new),
+            MetadataDiff {
+                added: vec!["d.json".into()],
+                removed: vec!["b.json".into()],
+                changed: vec!["c.json".into()],
+            }
+        );
+    }
+
+    #[test]
+    fn metadata_is_stale_compares_versions() {
+        assert!(metadata_is_stale(None, Some("1.0.0")));
+        assert!(metadata_is_stale(Some("1.0.0"), Some("1.0.1")));
+        assert!(!metadata_is_stale(Some("v1.0.0"), Some("1.0.0")));
+        assert!(!metadata_is_stale(Some("1.0.0"), None));
+    }
+
+    #[test]
+    fn merge_mirror_chain_keeps_order_and_dedupes() {
+        let chain = merge_mirror_chain(
+            Some(" https://a.example/ ".to_string()),
+            vec![
+                "https://b.example/".to_string(),
+                "".to_string(),
+                "https://a.example/".to_string(),
+            ],
+        );
+        assert_eq!(chain, vec!["https://a.example/", "https://b.example/"]);
+        assert!(merge_mirror_chain(None, Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn metadata_checksum_ignores_manifest_and_is_order_stable() {
+        let dir = std::env::temp_dir().join(format!("ef-meta-sum-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        assert!(local_metadata_paths(&dir).is_empty());
+        assert_eq!(compute_metadata_checksum(&dir, &[]).unwrap(), None);
+
+        fs::write(dir.join("b.json"), b"b").unwrap();
+        fs::write(dir.join("sub").join("a.json"), b"a").unwrap();
+        let before = compute_metadata_checksum(&dir, &local_metadata_paths(&dir)).unwrap().unwrap();
+
+        let manifest = serde_json::json!({ "metadata_checksum": before.to_lowercase() });
+        fs::write(dir.join("manifest.json"), manifest.to_string()).unwrap();
+        let (local, matches) = verify_metadata_checksum(&dir, Some(&manifest)).unwrap();
+        assert_eq!(local.as_deref(), Some(before.as_str()));
+        assert!(matches);
+
+        fs::write(dir.join("b.json"), b"changed").unwrap();
+        let (_, matches) = verify_metadata_checksum(&dir, Some(&manifest)).unwrap();
+        assert!(!matches);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn metadata_checksum_covers_only_manifest_entries() {
+        let dir = std::env::temp_dir().join(format!("ef-meta-sum-entries-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.json"), b"a").unwrap();
+        let paths = vec!["a.json".to_string()];
+        let expected = compute_metadata_checksum(&dir, &paths).unwrap().unwrap();
+        let manifest = serde_json::json!({
+            "metadata_checksum": expected,
+            "entries": [{ "path": "a.json" }],
+        });
+
+        fs::write(dir.join("overlay.json"), b"local").unwrap();
+        let (_, matches) = verify_metadata_checksum(&dir, Some(&manifest)).unwrap();
+        assert!(matches);
+
+        fs::remove_file(dir.join("a.json")).unwrap();
+        let (_, matches) = verify_metadata_checksum(&dir, Some(&manifest)).unwrap();
+        assert!(!matches);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn check_download_size_reports_mismatch() {
+        assert!(check_download_size("a.json", Some(10), 10).is_ok());
+        assert!(check_download_size("a.json", None, 3).is_ok());
+        let err = check_download_size("dir/a.json", Some(10), 7).unwrap_err();
+        assert!(err.contains("dir/a.json") && err.contains("10") && err.contains('7'));
+    }
+}
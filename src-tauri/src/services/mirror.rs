@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use std::path::Path;
+use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "kebab-case")]
@@ -11,6 +12,16 @@ pub enum GithubMirrorSource {
     Custom,
 }
 
+impl GithubMirrorSource {
+    /// 内置镜像源（不含自定义）
+    pub const BUILT_IN: [GithubMirrorSource; 4] = [
+        Self::GhProxyCf,
+        Self::GhProxyFastly,
+        Self::GhProxyEdgeone,
+        Self::Ghfast,
+    ];
+}
+
 impl Default for GithubMirrorSource {
     fn default() -> Self {
         Self::GhProxyCf
@@ -28,8 +39,62 @@ pub struct GithubMirrorConfig {
     pub custom_template: Option<String>,
 }
 
+/// GitHub URL 类型，不同类型在镜像上的写法不同
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum UrlKind {
+    /// `github.com/<owner>/<repo>/releases/download/...`
+    Release,
+    /// `raw.githubusercontent.com/<owner>/<repo>/<ref>/<path>`
+    Raw,
+    Other,
+}
+
+fn url_kind(url: &str) -> UrlKind {
+    let rest = strip_scheme(url);
+    if rest.starts_with("raw.githubusercontent.com/") {
+        UrlKind::Raw
+    } else if rest.starts_with("github.com/") && rest.contains("/releases/download/") {
+        UrlKind::Release
+    } else {
+        UrlKind::Other
+    }
+}
+
+fn strip_scheme(url: &str) -> &str {
+    url.strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .unwrap_or(url)
+}
+
+/// `raw.githubusercontent.com/<owner>/<repo>/<ref>/<path>` → `https://github.com/<owner>/<repo>/raw/<ref>/<path>`,
+/// the raw form gh-proxy style mirrors serve (they 404 on the raw host for some paths)
+fn raw_to_github_raw(url: &str) -> Option<String> {
+    let rest = strip_scheme(url).strip_prefix("raw.githubusercontent.com/")?;
+    let mut parts = rest.splitn(3, '/');
+    let (owner, repo, tail) = (parts.next()?, parts.next()?, parts.next()?);
+    Some(format!("https://github.com/{owner}/{repo}/raw/{tail}"))
+}
+
 impl GithubMirrorConfig {
+    /// 校验自定义模板：必须非空且包含 `{url}` / `{path}` 占位符，否则镜像会静默失效
+    pub fn validate(&self) -> Result<(), String> {
+        if self.source != GithubMirrorSource::Custom {
+            return Ok(());
+        }
+        let template = self.custom_template.as_deref().map(str::trim).unwrap_or("");
+        if template.is_empty() {
+            return Err("自定义镜像模板不能为空".to_string());
+        }
+        if !template.contains("{url}") && !template.contains("{path}") {
+            return Err("自定义镜像模板必须包含 {url} 或 {path} 占位符".to_string());
+        }
+        Ok(())
+    }
+
     /// 根据镜像配置转换 GitHub URL
+    ///
+    /// 自定义模板中 `{url}` 为完整地址，`{path}` 为去掉协议头的地址。
+    /// 内置镜像会把 raw.githubusercontent.com 地址改写为 github.com/.../raw/... 形式。
     pub fn transform_url(&self, original_url: &str) -> String {
         if !self.enabled {
             return original_url.to_string();
@@ -41,17 +106,89 @@ impl GithubMirrorConfig {
             GithubMirrorSource::GhProxyEdgeone => "https://edgeone.gh-proxy.org/{url}",
             GithubMirrorSource::Ghfast => "https://ghfast.top/{url}",
             GithubMirrorSource::Custom => {
-                self.custom_template.as_deref().unwrap_or("{url}")
+                let template = self.custom_template.as_deref().unwrap_or("{url}");
+                return template
+                    .replace("{url}", original_url)
+                    .replace("{path}", strip_scheme(original_url));
             }
         };
 
-        template.replace("{url}", original_url)
+        let url = match url_kind(original_url) {
+            UrlKind::Raw => raw_to_github_raw(original_url).unwrap_or_else(|| original_url.to_string()),
+            UrlKind::Release | UrlKind::Other => original_url.to_string(),
+        };
+        template.replace("{url}", &url)
     }
 }
 
+/// 镜像连通性测试使用的小文件
+pub const MIRROR_TEST_URL: &str =
+    "https://raw.githubusercontent.com/BoxCatTeam/endfield-cat/master/package.json";
+
+const MIRROR_TEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MirrorTestResult {
+    pub ok: bool,
+    pub latency_ms: u64,
+    /// HTTP status, `None` when the request itself failed (DNS, TLS, timeout)
+    pub status: Option<u16>,
+}
+
+/// 通过镜像请求测试文件：先 HEAD，不支持 HEAD (405) 时改用 GET
+pub async fn probe_mirror(
+    client: &reqwest::Client,
+    config: &GithubMirrorConfig,
+    timeout: Duration,
+) -> MirrorTestResult {
+    let config = GithubMirrorConfig { enabled: true, ..config.clone() };
+    let url = config.transform_url(MIRROR_TEST_URL);
+
+    let start = Instant::now();
+    let mut resp = client.head(&url).timeout(timeout).send().await;
+    if let Ok(r) = &resp {
+        if r.status() == reqwest::StatusCode::METHOD_NOT_ALLOWED {
+            resp = client.get(&url).timeout(timeout).send().await;
+        }
+    }
+    let latency_ms = start.elapsed().as_millis() as u64;
+
+    match resp {
+        Ok(r) => MirrorTestResult {
+            ok: r.status().is_success(),
+            latency_ms,
+            status: Some(r.status().as_u16()),
+        },
+        Err(_) => MirrorTestResult { ok: false, latency_ms, status: None },
+    }
+}
+
+pub async fn test_mirror(client: &reqwest::Client, config: &GithubMirrorConfig) -> Result<MirrorTestResult, String> {
+    config.validate()?;
+    Ok(probe_mirror(client, config, MIRROR_TEST_TIMEOUT).await)
+}
+
+const AUTO_SELECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// 并发探测所有内置镜像，返回最快可用的一个；全部失败时返回 None
+pub async fn auto_select_mirror(client: &reqwest::Client) -> Option<GithubMirrorSource> {
+    let probes = GithubMirrorSource::BUILT_IN.into_iter().map(|source| async move {
+        let config = GithubMirrorConfig { enabled: true, source: source.clone(), custom_template: None };
+        (source, probe_mirror(client, &config, AUTO_SELECT_TIMEOUT).await)
+    });
+
+    futures_util::future::join_all(probes)
+        .await
+        .into_iter()
+        .filter(|(_, result)| result.ok)
+        .min_by_key(|(_, result)| result.latency_ms)
+        .map(|(source, _)| source)
+}
+
 /// 从配置文件读取 GitHub 镜像配置
 pub fn read_mirror_config(exe_dir: &Path) -> GithubMirrorConfig {
-    let config_path = exe_dir.join("data").join("config").join("config.json");
+    let config_path = super::config::resolve_data_dir(exe_dir).join("config").join("config.json");
     if !config_path.exists() {
         return GithubMirrorConfig::default();
     }
@@ -98,6 +235,56 @@ mod tests {
         assert_eq!(config.transform_url(url), expected);
     }
 
+    #[test]
+    fn test_url_kind() {
+        assert_eq!(url_kind("https://github.com/o/r/releases/download/v1/a.exe"), UrlKind::Release);
+        assert_eq!(url_kind("https://raw.githubusercontent.com/o/r/master/a.json"), UrlKind::Raw);
+        assert_eq!(url_kind("https://github.com/o/r"), UrlKind::Other);
+    }
+
+    #[test]
+    fn test_transform_raw_url() {
+        let config = GithubMirrorConfig {
+            enabled: true,
+            source: GithubMirrorSource::GhProxyCf,
+            custom_template: None,
+        };
+        assert_eq!(
+            config.transform_url("https://raw.githubusercontent.com/o/r/master/dir/a.json"),
+            "https://gh-proxy.org/https://github.com/o/r/raw/master/dir/a.json"
+        );
+
+        let custom = GithubMirrorConfig {
+            enabled: true,
+            source: GithubMirrorSource::Custom,
+            custom_template: Some("https://my-proxy.com/{path}".to_string()),
+        };
+        assert_eq!(
+            custom.transform_url("https://raw.githubusercontent.com/o/r/master/a.json"),
+            "https://my-proxy.com/raw.githubusercontent.com/o/r/master/a.json"
+        );
+    }
+
+    #[test]
+    fn test_validate_custom_template() {
+        let mut config = GithubMirrorConfig {
+            enabled: true,
+            source: GithubMirrorSource::Custom,
+            custom_template: Some("https://my-proxy.com/".to_string()),
+        };
+        assert!(config.validate().is_err());
+
+        config.custom_template = None;
+        assert!(config.validate().is_err());
+
+        config.custom_template = Some("https://my-proxy.com/{url}".to_string());
+        assert!(config.validate().is_ok());
+
+        config.source = GithubMirrorSource::Ghfast;
+        config.custom_template = None;
+        assert!(config.validate().is_ok());
+    }
+
     #[test]
     fn test_transform_url_custom() {
         let config = GithubMirrorConfig {
@@ -1,5 +1,12 @@
+pub mod backup;
 pub mod config;
+pub mod health;
+pub mod http;
+pub mod import;
+pub mod logging;
 pub mod metadata;
 pub mod mirror;
 pub mod release;
+pub mod reset;
+pub mod shutdown;
 pub mod update;
@@ -1,5 +1,17 @@
 use reqwest::StatusCode;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use super::metadata::normalize_sha256;
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub size: u64,
+    pub download_url: String,
+    /// GitHub `digest` as published, e.g. `sha256:<hex>`
+    pub digest: Option<String>,
+}
 
 #[derive(Serialize, Clone)]
 pub struct LatestRelease {
@@ -7,13 +19,205 @@ pub struct LatestRelease {
     pub name: Option<String>,
     pub html_url: Option<String>,
     pub download_url: Option<String>,
+    /// SHA256 of the `download_url` asset (GitHub `digest`, without the `sha256:` prefix).
+    pub checksum: Option<String>,
     pub body: Option<String>,
+    /// Every asset of the release (installers, msi, portable zip...)
+    pub assets: Vec<ReleaseAsset>,
+}
+
+/// Release channel used by the update check (config `updateChannel`)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateChannel {
+    #[default]
+    Stable,
+    Beta,
+}
+
+pub fn read_update_channel(exe_dir: &Path) -> UpdateChannel {
+    super::config::read_config_field(exe_dir, "updateChannel").unwrap_or_default()
+}
+
+/// GitHub repository the update check reads releases from (config `repoOwner` / `repoName`),
+/// so forks and self-hosted builds can point it at their own repo.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReleaseRepo {
+    pub owner: String,
+    pub name: String,
+}
+
+impl Default for ReleaseRepo {
+    fn default() -> Self {
+        Self { owner: "BoxCatTeam".to_owned(), name: "endfield-cat".to_owned() }
+    }
+}
+
+/// GitHub owner / repo names: ASCII letters, digits, `-`, `_` and `.`
+fn valid_repo_segment(value: &str) -> bool {
+    !value.is_empty()
+        && value != "."
+        && value != ".."
+        && value.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+impl ReleaseRepo {
+    fn from_fields(owner: Option<String>, name: Option<String>) -> Self {
+        let default = Self::default();
+        let pick = |value: Option<String>, fallback: String| {
+            value
+                .map(|v| v.trim().to_owned())
+                .filter(|v| valid_repo_segment(v))
+                .unwrap_or(fallback)
+        };
+        Self { owner: pick(owner, default.owner), name: pick(name, default.name) }
+    }
+
+    /// Missing or invalid values fall back to the upstream repository.
+    pub fn from_config(exe_dir: &Path) -> Self {
+        Self::from_fields(
+            super::config::read_config_field(exe_dir, "repoOwner"),
+            super::config::read_config_field(exe_dir, "repoName"),
+        )
+    }
+
+    fn latest_release_api(&self) -> String {
+        format!("https://api.github.com/repos/{}/{}/releases/latest", self.owner, self.name)
+    }
+
+    fn releases_api(&self) -> String {
+        format!("https://api.github.com/repos/{}/{}/releases?per_page=20", self.owner, self.name)
+    }
+
+    fn package_json_cdn(&self) -> String {
+        format!("https://cdn.jsdelivr.net/gh/{}/{}@master/package.json", self.owner, self.name)
+    }
+
+    fn releases_page(&self) -> String {
+        format!("https://github.com/{}/{}/releases", self.owner, self.name)
+    }
+}
+
+/// Release lookup failure, serialized as `{ kind, message }` so the frontend can
+/// tell "no release yet" from "rate limited"
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ReleaseError {
+    NotFound { message: String },
+    RateLimited { message: String },
+    Network { message: String },
+    /// Connectivity probe failed before any request was made
+    Offline { message: String },
+}
+
+impl ReleaseError {
+    fn network(message: impl Into<String>) -> Self {
+        Self::Network { message: message.into() }
+    }
+
+    pub fn offline() -> Self {
+        Self::Offline { message: "No network connection".to_owned() }
+    }
+
+    fn from_status(status: StatusCode) -> Self {
+        let message = format!("GitHub API status {}", status);
+        match status {
+            StatusCode::NOT_FOUND => Self::NotFound { message },
+            StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS => Self::RateLimited { message },
+            _ => Self::Network { message },
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            Self::NotFound { message }
+            | Self::RateLimited { message }
+            | Self::Network { message }
+            | Self::Offline { message } => message,
+        }
+    }
+}
+
+impl std::fmt::Display for ReleaseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+fn parse_version(v: &str) -> Option<semver::Version> {
+    let v = v.trim();
+    semver::Version::parse(v.strip_prefix(['v', 'V']).unwrap_or(v)).ok()
 }
 
-#[derive(Debug)]
-struct FetchReleaseError {
-    message: String,
-    status: Option<StatusCode>,
+/// Hex timestamp of preview builds tagged `x.y.z-pre.<hex>`
+fn pre_hex_timestamp(v: &semver::Version) -> Option<u128> {
+    let mut parts = v.pre.as_str().split('.');
+    if parts.next()? != "pre" {
+        return None;
+    }
+    u128::from_str_radix(parts.next()?, 16).ok()
+}
+
+/// Whether `latest` is a newer version than `current` (leading `v` allowed).
+/// Prereleases sort below their release (`1.2.0-beta.3 < 1.2.0`) and above the
+/// previous one; preview builds of the same core version compare by their hex
+/// timestamp. Unparsable versions never report an update.
+pub fn is_update_available(current: &str, latest: &str) -> bool {
+    let (Some(current), Some(latest)) = (parse_version(current), parse_version(latest)) else {
+        return false;
+    };
+
+    let same_core = (current.major, current.minor, current.patch)
+        == (latest.major, latest.minor, latest.patch);
+    if same_core {
+        if let (Some(cur_ts), Some(new_ts)) = (pre_hex_timestamp(&current), pre_hex_timestamp(&latest)) {
+            return new_ts > cur_ts;
+        }
+    }
+
+    latest > current
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateCheck {
+    pub current: String,
+    pub latest: String,
+    pub update_available: bool,
+    pub download_url: Option<String>,
+    /// SHA256 of `download_url`, to pass on to `download_and_apply_update`
+    pub checksum: Option<String>,
+}
+
+fn release_assets(json: &serde_json::Value) -> Vec<ReleaseAsset> {
+    json.get("assets")
+        .and_then(|v| v.as_array())
+        .map(|assets| {
+            assets
+                .iter()
+                .filter_map(|asset| {
+                    Some(ReleaseAsset {
+                        name: asset.get("name")?.as_str()?.to_string(),
+                        size: asset.get("size").and_then(|v| v.as_u64()).unwrap_or(0),
+                        download_url: asset.get("browser_download_url")?.as_str()?.to_string(),
+                        digest: asset.get("digest").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Asset the self-updater swaps in: the portable `.exe` rather than an installer,
+/// falling back to the first `.exe`.
+fn pick_updater_asset(assets: &[ReleaseAsset]) -> Option<&ReleaseAsset> {
+    let exes = || assets.iter().filter(|a| a.name.to_lowercase().ends_with(".exe"));
+    exes()
+        .find(|a| {
+            let name = a.name.to_lowercase();
+            !name.contains("setup") && !name.contains("installer")
+        })
+        .or_else(|| exes().next())
 }
 
 fn latest_release_from_json(json: &serde_json::Value) -> Result<LatestRelease, String> {
@@ -42,133 +246,120 @@ fn latest_release_from_json(json: &serde_json::Value) -> Result<LatestRelease, S
         .and_then(|v| v.as_str())
         .map(|s| s.to_string());
 
-    let download_url = if cfg!(target_os = "windows") {
-        json.get("assets")
-            .and_then(|v| v.as_array())
-            .and_then(|assets| {
-                assets.iter().find_map(|asset| {
-                    let name = asset.get("name").and_then(|v| v.as_str())?;
-                    if name.ends_with(".exe") {
-                        asset
-                            .get("browser_download_url")
-                            .and_then(|v| v.as_str())
-                            .map(|s| s.to_string())
-                    } else {
-                        None
-                    }
-                })
-            })
+    let assets = release_assets(json);
+
+    let exe_asset = if cfg!(target_os = "windows") {
+        pick_updater_asset(&assets)
     } else {
         None
     };
 
+    let download_url = exe_asset.map(|asset| asset.download_url.clone());
+    let checksum = exe_asset
+        .and_then(|asset| asset.digest.as_deref())
+        .filter(|digest| digest.starts_with("sha256:"))
+        .map(normalize_sha256);
+
     Ok(LatestRelease {
         tag_name,
         name,
         html_url,
         download_url,
+        checksum,
         body,
+        assets,
     })
 }
 
-pub async fn fetch_latest_release(client: &reqwest::Client) -> Result<LatestRelease, String> {
+pub async fn fetch_latest_release(client: &reqwest::Client, repo: &ReleaseRepo) -> Result<LatestRelease, ReleaseError> {
     async fn fetch(
         client: &reqwest::Client,
         url: &str,
-    ) -> Result<LatestRelease, FetchReleaseError> {
+    ) -> Result<LatestRelease, ReleaseError> {
         let resp = client
             .get(url)
             .header("Accept", "application/vnd.github+json")
             .header("User-Agent", "endfield-cat/tauri")
             .send()
             .await
-            .map_err(|e| FetchReleaseError {
-                message: e.to_string(),
-                status: None,
-            })?;
+            .map_err(|e| ReleaseError::network(e.to_string()))?;
 
         let status = resp.status();
         if !status.is_success() {
-            return Err(FetchReleaseError {
-                message: format!("GitHub API status {}", status),
-                status: Some(status),
-            });
+            return Err(ReleaseError::from_status(status));
         }
 
-        let json: serde_json::Value = resp.json().await.map_err(|e| FetchReleaseError {
-            message: e.to_string(),
-            status: None,
-        })?;
+        let json: serde_json::Value = resp
+            .json()
+            .await
+            .map_err(|e| ReleaseError::network(e.to_string()))?;
 
-        latest_release_from_json(&json).map_err(|message| FetchReleaseError {
-            message,
-            status: None,
-        })
+        latest_release_from_json(&json).map_err(ReleaseError::network)
     }
 
-    let primary = "https://api.github.com/repos/BoxCatTeam/endfield-cat/releases/latest";
-    match fetch(client, primary).await {
+    let primary = repo.latest_release_api();
+    match fetch(client, &primary).await {
         Ok(res) => Ok(res),
-        Err(err)
-            if matches!(
-                err.status,
-                Some(StatusCode::FORBIDDEN) | Some(StatusCode::TOO_MANY_REQUESTS)
-            ) =>
-        {
+        Err(err @ ReleaseError::RateLimited { .. }) => {
             // Fallback: use jsDelivr to read package.json for version to avoid GitHub API limits
-            let fallback_url =
-                "https://cdn.jsdelivr.net/gh/BoxCatTeam/endfield-cat@master/package.json";
-            let resp = client
-                .get(fallback_url)
+            let fallback_url = repo.package_json_cdn();
+            let Ok(resp) = client
+                .get(&fallback_url)
                 .header("User-Agent", "endfield-cat/tauri")
                 .send()
                 .await
-                .map_err(|e| e.to_string())?;
+            else {
+                return Err(err);
+            };
 
             if !resp.status().is_success() {
-                return Err(err.message);
+                return Err(err);
             }
 
-            let pkg: serde_json::Value = resp.json().await.map_err(|e| e.to_string())?;
+            let Ok(pkg) = resp.json::<serde_json::Value>().await else {
+                return Err(err);
+            };
             if let Some(ver) = pkg.get("version").and_then(|v| v.as_str()) {
                 let tag_name = format!("v{}", ver);
                 return Ok(LatestRelease {
                     tag_name,
                     name: None,
-                    html_url: Some(
-                        "https://github.com/BoxCatTeam/endfield-cat/releases".to_string(),
-                    ),
+                    html_url: Some(repo.releases_page()),
                     download_url: None,
+                    checksum: None,
                     body: None,
+                    assets: Vec::new(),
                 });
             }
 
-            Err(err.message)
+            Err(err)
         }
-        Err(err) if err.status == Some(StatusCode::NOT_FOUND) => Err(err.message),
-        Err(err) => Err(err.message),
+        Err(err) => Err(err),
     }
 }
 
-pub async fn fetch_latest_prerelease(client: &reqwest::Client) -> Result<LatestRelease, String> {
-    let url = "https://api.github.com/repos/BoxCatTeam/endfield-cat/releases?per_page=20";
+pub async fn fetch_latest_prerelease(client: &reqwest::Client, repo: &ReleaseRepo) -> Result<LatestRelease, ReleaseError> {
+    let url = repo.releases_api();
     let resp = client
-        .get(url)
+        .get(&url)
         .header("Accept", "application/vnd.github+json")
         .header("User-Agent", "endfield-cat/tauri")
         .send()
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| ReleaseError::network(e.to_string()))?;
 
     let status = resp.status();
     if !status.is_success() {
-        return Err(format!("GitHub API status {}", status));
+        return Err(ReleaseError::from_status(status));
     }
 
-    let json: serde_json::Value = resp.json().await.map_err(|e| e.to_string())?;
+    let json: serde_json::Value = resp
+        .json()
+        .await
+        .map_err(|e| ReleaseError::network(e.to_string()))?;
     let releases = json
         .as_array()
-        .ok_or("Invalid GitHub response: expected array")?;
+        .ok_or_else(|| ReleaseError::network("Invalid GitHub response: expected array"))?;
 
     let target = releases.iter().find(|r| {
         r.get("draft").and_then(|v| v.as_bool()) == Some(false)
@@ -176,8 +367,112 @@ pub async fn fetch_latest_prerelease(client: &reqwest::Client) -> Result<LatestR
     });
 
     let Some(target) = target else {
-        return Err("No prerelease found".to_string());
+        return Err(ReleaseError::NotFound {
+            message: "No prerelease found".to_string(),
+        });
     };
 
-    latest_release_from_json(target)
+    latest_release_from_json(target).map_err(ReleaseError::network)
+}
+
+/// Newer of the latest stable release and the latest prerelease; stable wins ties.
+fn newer_release(
+    stable: Result<LatestRelease, ReleaseError>,
+    prerelease: Result<LatestRelease, ReleaseError>,
+) -> Result<LatestRelease, ReleaseError> {
+    match (stable, prerelease) {
+        (Ok(stable), Ok(pre)) if is_update_available(&stable.tag_name, &pre.tag_name) => Ok(pre),
+        (Ok(stable), _) => Ok(stable),
+        (Err(_), Ok(pre)) => Ok(pre),
+        (Err(e), Err(_)) => Err(e),
+    }
+}
+
+/// Latest release for the given channel. The beta channel offers whichever of the latest
+/// prerelease and the latest stable release is newer, like the frontend updater.
+pub async fn fetch_release_for_channel(
+    client: &reqwest::Client,
+    repo: &ReleaseRepo,
+    channel: UpdateChannel,
+) -> Result<LatestRelease, ReleaseError> {
+    match channel {
+        UpdateChannel::Stable => fetch_latest_release(client, repo).await,
+        UpdateChannel::Beta => {
+            let (stable, prerelease) =
+                tokio::join!(fetch_latest_release(client, repo), fetch_latest_prerelease(client, repo));
+            newer_release(stable, prerelease)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn release_assets_lists_all_and_prefers_portable_exe() {
+        let json = serde_json::json!({
+            "tag_name": "v1.0.0",
+            "assets": [
+                { "name": "EndCat_1.0.0_x64-setup.exe", "size": 10, "browser_download_url": "https://x/setup.exe" },
+                { "name": "EndCat_1.0.0_x64_en-US.msi", "size": 11, "browser_download_url": "https://x/a.msi" },
+                { "name": "endfield-cat.exe", "size": 12, "browser_download_url": "https://x/portable.exe", "digest": "sha256:ab" },
+                { "name": "broken" }
+            ]
+        });
+        let assets = release_assets(&json);
+        assert_eq!(assets.len(), 3);
+        assert_eq!(assets[1].name, "EndCat_1.0.0_x64_en-US.msi");
+        assert_eq!(pick_updater_asset(&assets).map(|a| a.download_url.as_str()), Some("https://x/portable.exe"));
+        assert_eq!(pick_updater_asset(&assets[..2]).map(|a| a.download_url.as_str()), Some("https://x/setup.exe"));
+        assert_eq!(pick_updater_asset(&assets[1..2]), None);
+    }
+
+    #[test]
+    fn release_repo_builds_urls_and_rejects_bad_names() {
+        let repo = ReleaseRepo::from_fields(Some(" me ".into()), Some("my-fork".into()));
+        assert_eq!(repo.latest_release_api(), "https://api.github.com/repos/me/my-fork/releases/latest");
+        assert_eq!(repo.package_json_cdn(), "https://cdn.jsdelivr.net/gh/me/my-fork@master/package.json");
+        assert_eq!(repo.releases_page(), "https://github.com/me/my-fork/releases");
+
+        let fallback = ReleaseRepo::from_fields(Some("a/b".into()), Some("..".into()));
+        assert_eq!(fallback, ReleaseRepo::default());
+        assert_eq!(ReleaseRepo::from_fields(None, None), ReleaseRepo::default());
+    }
+
+    #[test]
+    fn is_update_available_handles_prefix_and_prerelease() {
+        assert!(is_update_available("0.2.1", "v0.3.0"));
+        assert!(!is_update_available("v0.3.0", "0.3.0"));
+        assert!(!is_update_available("1.2.0", "v1.2.0-beta.3"));
+        assert!(is_update_available("1.2.0-beta.3", "v1.2.0"));
+        assert!(is_update_available("1.1.0", "v1.2.0-beta.3"));
+        assert!(is_update_available("1.2.0-beta.2", "v1.2.0-beta.10"));
+        assert!(is_update_available("0.3.0-pre.9a", "0.3.0-pre.1f0"));
+        assert!(!is_update_available("0.3.0", "not-a-version"));
+    }
+
+    #[test]
+    fn newer_release_prefers_newer_stable_over_older_prerelease() {
+        let release = |tag: &str| {
+            latest_release_from_json(&serde_json::json!({ "tag_name": tag, "assets": [] })).unwrap()
+        };
+        let tag = |r: Result<LatestRelease, ReleaseError>| r.unwrap().tag_name;
+        assert_eq!(tag(newer_release(Ok(release("v1.3.1")), Ok(release("v1.3.0-beta.2")))), "v1.3.1");
+        assert_eq!(tag(newer_release(Ok(release("v1.3.0")), Ok(release("v1.4.0-beta.1")))), "v1.4.0-beta.1");
+        assert_eq!(tag(newer_release(Err(ReleaseError::offline()), Ok(release("v1.4.0-beta.1")))), "v1.4.0-beta.1");
+        assert_eq!(tag(newer_release(Ok(release("v1.3.0")), Err(ReleaseError::offline()))), "v1.3.0");
+        assert!(newer_release(Err(ReleaseError::offline()), Err(ReleaseError::offline())).is_err());
+    }
+
+    #[test]
+    fn release_error_classifies_status_and_serializes_kind() {
+        assert!(matches!(ReleaseError::from_status(StatusCode::NOT_FOUND), ReleaseError::NotFound { .. }));
+        assert!(matches!(ReleaseError::from_status(StatusCode::FORBIDDEN), ReleaseError::RateLimited { .. }));
+        assert!(matches!(ReleaseError::from_status(StatusCode::BAD_GATEWAY), ReleaseError::Network { .. }));
+
+        let json = serde_json::to_value(ReleaseError::from_status(StatusCode::TOO_MANY_REQUESTS)).unwrap();
+        assert_eq!(json["kind"], "rateLimited");
+        assert_eq!(json["message"], "GitHub API status 429 Too Many Requests");
+    }
 }
@@ -0,0 +1,106 @@
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use super::config;
+use crate::database::DbPool;
+
+/// How long a confirm token from [`ResetTokens::issue`] stays valid
+const CONFIRM_TOKEN_TTL: Duration = Duration::from_secs(60);
+
+/// Data sub-directories wiped by a factory reset
+const RESET_DIRS: &[&str] = &["database", "metadata", "config"];
+
+/// 恢复出厂设置的一次性确认令牌；managed as Tauri state.
+#[derive(Default)]
+pub struct ResetTokens(Mutex<Option<(String, Instant)>>);
+
+impl ResetTokens {
+    /// Issue a fresh token, replacing any earlier one.
+    pub fn issue(&self) -> Result<String, String> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let seed = format!("{nanos}-{}-{:p}", std::process::id(), self);
+        let token: String = Sha256::digest(seed.as_bytes())
+            .iter()
+            .take(8)
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        *self.0.lock().map_err(|e| e.to_string())? = Some((token.clone(), Instant::now()));
+        Ok(token)
+    }
+
+    /// Check `token` against the issued one. The issued token is used up either way,
+    /// so a wrong guess needs a new token from the UI.
+    pub fn consume(&self, token: &str) -> Result<(), String> {
+        let issued = self.0.lock().map_err(|e| e.to_string())?.take();
+        match issued {
+            Some((expected, at)) if expected == token.trim() && at.elapsed() <= CONFIRM_TOKEN_TTL => Ok(()),
+            Some((expected, _)) if expected == token.trim() => Err("确认令牌已过期，请重新确认".to_string()),
+            _ => Err("确认令牌无效".to_string()),
+        }
+    }
+}
+
+/// Delete the database, metadata and config under `data_dir`, then recreate the empty
+/// directories. Returns the removed paths.
+fn wipe_data_dir(data_dir: &Path) -> Result<Vec<String>, String> {
+    let mut removed = Vec::new();
+    for name in RESET_DIRS {
+        let dir = data_dir.join(name);
+        if dir.exists() {
+            fs::remove_dir_all(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+            removed.push(dir.to_string_lossy().to_string());
+        }
+        fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    }
+    Ok(removed)
+}
+
+/// 关闭连接池后清空数据目录下的数据库、元数据与配置。连接池关闭后应用需重启才能继续使用数据库。
+pub async fn factory_reset(pool: &DbPool, exe_dir: &Path) -> Result<Vec<String>, String> {
+    let data_dir = config::resolve_data_dir(exe_dir);
+    pool.close().await;
+    let removed = wipe_data_dir(&data_dir)?;
+    tracing::warn!("[reset] factory reset removed {} path(s) under {}", removed.len(), data_dir.display());
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn confirm_token_is_single_use() {
+        let tokens = ResetTokens::default();
+        assert!(tokens.consume("anything").is_err());
+
+        let token = tokens.issue().unwrap();
+        assert!(tokens.consume("wrong").is_err());
+        assert!(tokens.consume(&token).is_err(), "a failed attempt uses the token up");
+
+        let token = tokens.issue().unwrap();
+        assert!(tokens.consume(&token).is_ok());
+        assert!(tokens.consume(&token).is_err());
+    }
+
+    #[test]
+    fn wipe_data_dir_removes_and_recreates() {
+        let dir = std::env::temp_dir().join(format!("endcat-reset-{}", std::process::id()));
+        fs::create_dir_all(dir.join("database")).unwrap();
+        fs::write(dir.join("database").join("endcat.db"), b"db").unwrap();
+        fs::create_dir_all(dir.join("logs")).unwrap();
+
+        let removed = wipe_data_dir(&dir).unwrap();
+        assert_eq!(removed, vec![dir.join("database").to_string_lossy().to_string()]);
+        assert!(!dir.join("database").join("endcat.db").exists());
+        assert!(dir.join("metadata").is_dir() && dir.join("config").is_dir());
+        assert!(dir.join("logs").exists(), "unrelated directories are kept");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,89 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tauri::{AppHandle, Manager};
+
+use crate::database::DbPool;
+
+/// How long quitting waits for running syncs before closing the pool anyway
+const SYNC_DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+const SYNC_DRAIN_POLL: Duration = Duration::from_millis(100);
+
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+
+/// Count of in-flight sync commands, managed as app state
+#[derive(Clone, Default)]
+pub struct ActiveTasks(Arc<AtomicUsize>);
+
+/// Marks a task as running until dropped
+pub struct TaskGuard(Arc<AtomicUsize>);
+
+impl ActiveTasks {
+    pub fn begin(&self) -> TaskGuard {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        TaskGuard(self.0.clone())
+    }
+
+    pub fn active(&self) -> usize {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Wait until no task is running, up to `timeout`. Returns whether it drained.
+    pub async fn wait_idle(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        while self.active() > 0 {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            tokio::time::sleep(SYNC_DRAIN_POLL).await;
+        }
+        true
+    }
+}
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+pub fn is_shutting_down() -> bool {
+    SHUTTING_DOWN.load(Ordering::SeqCst)
+}
+
+/// 等待进行中的同步结束（最多数秒），关闭数据库连接池后退出，避免中途退出损坏 WAL
+pub async fn shutdown(app: &AppHandle) {
+    if SHUTTING_DOWN.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    if let Some(tasks) = app.try_state::<ActiveTasks>() {
+        if !tasks.wait_idle(SYNC_DRAIN_TIMEOUT).await {
+            tracing::warn!("[shutdown] {} sync task(s) still running, closing anyway", tasks.active());
+        }
+    }
+    if let Some(pool) = app.try_state::<DbPool>() {
+        pool.close().await;
+    }
+
+    app.exit(0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn wait_idle_tracks_guards() {
+        let tasks = ActiveTasks::default();
+        assert!(tasks.wait_idle(Duration::ZERO).await);
+
+        let guard = tasks.begin();
+        assert_eq!(tasks.active(), 1);
+        assert!(!tasks.wait_idle(Duration::from_millis(10)).await);
+
+        drop(guard);
+        assert!(tasks.wait_idle(Duration::ZERO).await);
+    }
+}
@@ -2,6 +2,8 @@ use serde::Serialize;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use super::metadata::{compute_sha256, normalize_sha256};
+
 #[derive(Clone, Serialize)]
 pub struct UpdateProgress {
     pub stage: String,
@@ -31,48 +33,215 @@ pub fn prepare_paths(exe_name: &std::ffi::OsStr) -> Result<UpdatePaths, String>
     })
 }
 
-pub async fn download_new_exe<F>(
+/// Attempts made by `download_new_exe` before giving up; later attempts resume via `Range`.
+const MAX_DOWNLOAD_ATTEMPTS: u32 = 5;
+
+/// Upper bound for a self-update download; anything larger is a broken or hostile mirror.
+const MAX_DOWNLOAD_BYTES: u64 = 500 * 1024 * 1024;
+
+#[derive(Debug)]
+struct DownloadError {
+    message: String,
+    retryable: bool,
+}
+
+impl DownloadError {
+    fn retryable(message: impl Into<String>) -> Self {
+        Self { message: message.into(), retryable: true }
+    }
+
+    fn fatal(message: impl Into<String>) -> Self {
+        Self { message: message.into(), retryable: false }
+    }
+}
+
+/// Fail before (announced size) or while (received bytes) exceeding [`MAX_DOWNLOAD_BYTES`].
+fn check_download_limit(total_size: Option<u64>, downloaded: u64) -> Result<(), DownloadError> {
+    let size = total_size.unwrap_or(0).max(downloaded);
+    if size > MAX_DOWNLOAD_BYTES {
+        return Err(DownloadError::fatal(format!(
+            "Download aborted: {} MB exceeds the {} MB limit for updates",
+            size / 1024 / 1024,
+            MAX_DOWNLOAD_BYTES / 1024 / 1024
+        )));
+    }
+    Ok(())
+}
+
+/// `<dest>.part`, where the download is written until it is complete.
+fn part_path(dest: &Path) -> PathBuf {
+    let mut name = dest.as_os_str().to_owned();
+    name.push(".part");
+    PathBuf::from(name)
+}
+
+/// Total length from a `Content-Range: bytes <start>-<end>/<total>` header.
+fn total_from_content_range(value: &str) -> Option<u64> {
+    let (_, total) = value.trim().strip_prefix("bytes")?.rsplit_once('/')?;
+    total.trim().parse().ok()
+}
+
+fn report_progress<F: FnMut(u32)>(on_progress: &mut F, downloaded: u64, total_size: Option<u64>) {
+    if let Some(total) = total_size.filter(|t| *t > 0) {
+        let progress = ((downloaded as f64 / total as f64) * 100.0) as u32;
+        on_progress(progress.min(100));
+    }
+}
+
+async fn download_attempt<F>(
     client: &reqwest::Client,
     download_url: &str,
-    dest: &Path,
-    mut on_progress: F,
-) -> Result<(), String>
+    part: &Path,
+    downloaded: &mut u64,
+    total_size: &mut Option<u64>,
+    on_progress: &mut F,
+) -> Result<(), DownloadError>
 where
     F: FnMut(u32),
 {
     use futures_util::StreamExt;
+    use reqwest::{header, StatusCode};
     use std::io::Write;
 
-    let resp = client
-        .get(download_url)
+    let mut req = client.get(download_url);
+    if *downloaded > 0 {
+        req = req.header(header::RANGE, format!("bytes={}-", downloaded));
+    }
+
+    let resp = req
         .send()
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| DownloadError::retryable(e.to_string()))?;
+    let status = resp.status();
 
-    if !resp.status().is_success() {
-        return Err(format!("Download failed: HTTP {}", resp.status()));
+    let mut file = if *downloaded > 0 && status == StatusCode::PARTIAL_CONTENT {
+        if total_size.is_none() {
+            *total_size = resp
+                .headers()
+                .get(header::CONTENT_RANGE)
+                .and_then(|v| v.to_str().ok())
+                .and_then(total_from_content_range);
+        }
+        fs::OpenOptions::new()
+            .append(true)
+            .open(part)
+            .map_err(|e| DownloadError::fatal(e.to_string()))?
+    } else if *downloaded > 0 && status == StatusCode::RANGE_NOT_SATISFIABLE && *total_size == Some(*downloaded) {
+        // Everything was already received before the connection dropped.
+        return Ok(());
+    } else if status.is_success() {
+        // Server ignored the range (200): start over from the beginning.
+        *downloaded = 0;
+        *total_size = resp.content_length();
+        fs::File::create(part).map_err(|e| DownloadError::fatal(e.to_string()))?
+    } else if status.is_server_error() {
+        return Err(DownloadError::retryable(format!("Download failed: HTTP {}", status)));
+    } else {
+        return Err(DownloadError::fatal(format!("Download failed: HTTP {}", status)));
+    };
+    check_download_limit(*total_size, *downloaded)?;
+
+    let mut stream = resp.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| DownloadError::retryable(e.to_string()))?;
+        check_download_limit(*total_size, *downloaded + chunk.len() as u64)?;
+        file.write_all(&chunk).map_err(|e| DownloadError::fatal(e.to_string()))?;
+        *downloaded += chunk.len() as u64;
+        report_progress(on_progress, *downloaded, *total_size);
     }
+    file.flush().map_err(|e| DownloadError::fatal(e.to_string()))?;
 
-    let total_size = resp.content_length().unwrap_or(0);
-    let mut downloaded: u64 = 0;
+    if let Some(total) = *total_size {
+        if *downloaded < total {
+            return Err(DownloadError::retryable(format!(
+                "Download interrupted: received {} of {} bytes",
+                downloaded, total
+            )));
+        }
+    }
 
-    let mut file = fs::File::create(dest).map_err(|e| e.to_string())?;
-    let mut stream = resp.bytes_stream();
+    Ok(())
+}
 
-    while let Some(chunk) = stream.next().await {
-        let chunk = chunk.map_err(|e| e.to_string())?;
-        file.write_all(&chunk).map_err(|e| e.to_string())?;
-        downloaded += chunk.len() as u64;
+/// Download `download_url` to `dest`, resuming with HTTP `Range` requests when the
+/// connection drops. Data goes to `<dest>.part` and is only renamed to `dest` once complete
+/// and, when `expected_sha256` is given, verified.
+pub async fn download_new_exe<F>(
+    client: &reqwest::Client,
+    download_url: &str,
+    dest: &Path,
+    expected_sha256: Option<&str>,
+    mut on_progress: F,
+) -> Result<(), String>
+where
+    F: FnMut(u32),
+{
+    let part = part_path(dest);
+    if part.exists() {
+        fs::remove_file(&part).map_err(|e| e.to_string())?;
+    }
+
+    let mut downloaded: u64 = 0;
+    let mut total_size: Option<u64> = None;
+    let mut attempt = 0;
 
-        if total_size > 0 {
-            let progress = ((downloaded as f64 / total_size as f64) * 100.0) as u32;
-            on_progress(progress);
+    loop {
+        attempt += 1;
+        match download_attempt(client, download_url, &part, &mut downloaded, &mut total_size, &mut on_progress).await {
+            Ok(()) => break,
+            Err(err) if err.retryable && attempt < MAX_DOWNLOAD_ATTEMPTS => {
+                tokio::time::sleep(std::time::Duration::from_secs(attempt as u64)).await;
+            }
+            Err(err) => {
+                let _ = fs::remove_file(&part);
+                return Err(err.message);
+            }
         }
     }
 
+    if let Some(expected) = expected_sha256.map(normalize_sha256).filter(|s| !s.is_empty()) {
+        let actual = compute_sha256(&part)?;
+        if actual != expected {
+            let _ = fs::remove_file(&part);
+            return Err(format!(
+                "Checksum mismatch for downloaded update: expected {}, got {}",
+                expected, actual
+            ));
+        }
+    }
+
+    if dest.exists() {
+        fs::remove_file(dest).map_err(|e| e.to_string())?;
+    }
+    fs::rename(&part, dest).map_err(|e| e.to_string())?;
+
     Ok(())
 }
 
+/// Fetch the `<download_url>.sha256` sidecar published next to a release asset, if any.
+/// Accepts both a bare hash and `sha256sum` output (`<hash>  <file>`).
+pub async fn fetch_sha256_sidecar(client: &reqwest::Client, download_url: &str) -> Option<String> {
+    let resp = client
+        .get(format!("{}.sha256", download_url))
+        .send()
+        .await
+        .ok()?;
+    if !resp.status().is_success() {
+        return None;
+    }
+    let text = resp.text().await.ok()?;
+    parse_sha256_sidecar(&text)
+}
+
+fn parse_sha256_sidecar(text: &str) -> Option<String> {
+    let hash = normalize_sha256(text.split_whitespace().next()?);
+    if hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        Some(hash)
+    } else {
+        None
+    }
+}
+
 pub fn build_updater_batch(
     exe_name: &str,
     new_exe: &Path,
@@ -122,6 +291,17 @@ exit /b 0
 mod tests {
     use super::*;
 
+    #[test]
+    fn check_download_limit_rejects_announced_and_received_overflow() {
+        assert!(check_download_limit(Some(10 * 1024 * 1024), 0).is_ok());
+        assert!(check_download_limit(None, MAX_DOWNLOAD_BYTES).is_ok());
+
+        let err = check_download_limit(Some(MAX_DOWNLOAD_BYTES + 1), 0).unwrap_err();
+        assert!(!err.retryable);
+        assert!(err.message.contains("limit"));
+        assert!(check_download_limit(None, MAX_DOWNLOAD_BYTES + 1).is_err());
+    }
+
     #[test]
     fn build_updater_batch_uses_powershell_literalpath_cleanup() {
         let content = build_updater_batch(
@@ -148,4 +328,27 @@ mod tests {
 
         assert!(content.contains(r#""C:\Temp\A&B\endfield-cat-update""#));
     }
+
+    #[test]
+    fn total_from_content_range_parses_total() {
+        assert_eq!(total_from_content_range("bytes 100-999/1000"), Some(1000));
+        assert_eq!(total_from_content_range("bytes 100-999/*"), None);
+        assert_eq!(total_from_content_range("garbage"), None);
+    }
+
+    #[test]
+    fn parse_sha256_sidecar_accepts_sha256sum_output() {
+        let hash = "ab".repeat(32);
+        assert_eq!(parse_sha256_sidecar(&format!("{}  endfield-cat.exe\n", hash)), Some(hash.to_uppercase()));
+        assert_eq!(parse_sha256_sidecar(&format!("sha256:{}", hash)), Some(hash.to_uppercase()));
+        assert_eq!(parse_sha256_sidecar("<html>Not Found</html>"), None);
+    }
+
+    #[test]
+    fn part_path_appends_suffix() {
+        assert_eq!(
+            part_path(Path::new("C:\\Temp\\endfield-cat.exe")),
+            PathBuf::from("C:\\Temp\\endfield-cat.exe.part")
+        );
+    }
 }
@@ -5,6 +5,8 @@ export type HgProvider = "hypergryph" | "gryphline";
 export type FetchMetadataArgs = {
   baseUrl: string;
   version: string;
+  /** Probe connectivity first and reject with "offline" when there is none */
+  checkOnline?: boolean;
 };
 
 export type HgBindingEntry = {
@@ -35,16 +37,119 @@ export function getAppVersion() {
   return invoke<string>("get_app_version");
 }
 
-export function fetchLatestRelease<T = unknown>() {
-  return invoke<T>("fetch_latest_release");
+/** Rejection value of fetchLatestRelease / fetchLatestPrerelease / fetchChannelRelease / checkForUpdate */
+export type ReleaseError = {
+  kind: "notFound" | "rateLimited" | "network" | "offline";
+  message: string;
+};
+
+export function fetchLatestRelease<T = unknown>(checkOnline = false) {
+  return invoke<T>("fetch_latest_release", { checkOnline });
+}
+
+export function isOnline() {
+  return invoke<boolean>("is_online");
 }
 
 export function fetchLatestPrerelease<T = unknown>() {
   return invoke<T>("fetch_latest_prerelease");
 }
 
-export function downloadAndApplyUpdate(downloadUrl: string) {
-  return invoke("download_and_apply_update", { downloadUrl });
+export function fetchChannelRelease<T = unknown>(checkOnline = false) {
+  return invoke<T>("fetch_channel_release", { checkOnline });
+}
+
+export type UpdateCheck = {
+  current: string;
+  latest: string;
+  updateAvailable: boolean;
+  downloadUrl: string | null;
+  checksum: string | null;
+};
+
+export function checkForUpdate(checkOnline = false) {
+  return invoke<UpdateCheck>("check_for_update", { checkOnline });
+}
+
+export function downloadAndApplyUpdate(downloadUrl: string, checksum?: string | null) {
+  return invoke("download_and_apply_update", { downloadUrl, checksum: checksum ?? null });
+}
+
+export type StoragePaths = {
+  config: string;
+  database: string;
+  isPortable: boolean;
+};
+
+export function getStoragePaths() {
+  return invoke<StoragePaths>("get_storage_paths");
+}
+
+export type AppHealth = {
+  dbOk: boolean;
+  dbVersion: number;
+  metadataOk: boolean;
+  metadataVersion: string | null;
+  accountCount: number;
+  configPath: string;
+  dataDir: string;
+  appVersion: string;
+  errors: string[];
+};
+
+/** One-shot diagnostics summary for support requests. */
+export function appHealth() {
+  return invoke<AppHealth>("app_health");
+}
+
+/** One-time token for `factoryReset`, valid for 60 seconds. */
+export function factoryResetToken() {
+  return invoke<string>("factory_reset_token");
+}
+
+/** Delete the database, metadata and config; returns removed paths. Restart the app afterwards. */
+export function factoryReset(confirmToken: string) {
+  return invoke<string[]>("factory_reset", { confirmToken });
+}
+
+/** Takes effect after restart */
+export function relocateDataDir(newPath: string, overwrite = false) {
+  return invoke<StoragePaths>("relocate_data_dir", { newPath, overwrite });
+}
+
+export type BackupManifest = {
+  schemaVersion: number;
+  appVersion: string;
+  dbVersion: number;
+  metadataVersion: string | null;
+  createdAt: number;
+};
+
+export function exportBackupBundle(destZip: string) {
+  return invoke<BackupManifest>("export_backup_bundle", { destZip });
+}
+
+export function importBackupBundle(srcZip: string) {
+  return invoke<BackupManifest>("import_backup_bundle", { srcZip });
+}
+
+/** CSV header names for each record field; the optional ones may be omitted. */
+export type GachaColumnMapping = {
+  itemName: string;
+  rarity: string;
+  pulledAt: string;
+  poolType: string;
+  seqId?: string;
+  itemId?: string;
+  poolId?: string;
+  poolName?: string;
+};
+
+export type GenericImportResult = { imported: number; synthesizedSeqIds: number };
+
+/** Import gacha records for `uid` from a CSV file using a column mapping. */
+export function importGachaGeneric(uid: string, path: string, mapping: GachaColumnMapping) {
+  return invoke<GenericImportResult>("import_gacha_generic", { uid, path, mapping });
 }
 
 export function readConfig<T = any>() {
@@ -55,16 +160,53 @@ export function saveConfig(config: any) {
   return invoke("save_config", { config });
 }
 
+export function resetConfig<T = any>() {
+  return invoke<T>("reset_config");
+}
+
 export function fetchMetadataManifest<T = unknown>(args: FetchMetadataArgs) {
   return invoke<T>("fetch_metadata_manifest", args);
 }
 
+export type ManifestEntry = { path: string; size: number | null; checksum: string | null };
+export type MetadataEntries = { packageVersion: string | null; entries: ManifestEntry[]; totalSize: number };
+
+/** File list of the remote manifest, to preview an update before downloading it. */
+export function fetchMetadataEntries(args: FetchMetadataArgs) {
+  return invoke<MetadataEntries>("fetch_metadata_entries", args);
+}
+
+export type MetadataUpdateCheck = {
+  local: string | null;
+  remote: string | null;
+  updateAvailable: boolean;
+  itemCount: number | null;
+  totalSize: number | null;
+};
+
+export function metadataUpdateAvailable(args: FetchMetadataArgs) {
+  return invoke<MetadataUpdateCheck>("metadata_update_available", args);
+}
+
+export type MetadataDiff = { added: string[]; removed: string[]; changed: string[] };
+
+export function metadataDiff(args: { baseUrl?: string; oldVersion: string; newVersion: string }) {
+  return invoke<MetadataDiff>("metadata_diff", args);
+}
+
 export function resetMetadata(args: FetchMetadataArgs) {
   return invoke("reset_metadata", args);
 }
 
-export function updateMetadata(baseUrl: string) {
-  return invoke("update_metadata", { baseUrl });
+export function updateMetadata(baseUrl: string, checkOnline = false) {
+  return invoke("update_metadata", { baseUrl, checkOnline });
+}
+
+export type FileChecksum = { path: string; checksum: string; size: number };
+
+/** Relative paths resolve against the metadata directory */
+export function computeFileChecksum(path: string) {
+  return invoke<FileChecksum>("compute_file_checksum", { path });
 }
 
 export function checkMetadata<T = unknown>() {
@@ -76,9 +218,45 @@ export function testGithubMirror(mirrorUrlTemplate: string) {
   return invoke<number>("test_github_mirror", { mirrorUrlTemplate });
 }
 
+export type GithubMirrorSource = "gh-proxy-cf" | "gh-proxy-fastly" | "gh-proxy-edgeone" | "ghfast" | "custom";
+
+export type GithubMirrorConfig = {
+  enabled: boolean;
+  source: GithubMirrorSource;
+  customTemplate?: string | null;
+};
+
+// 校验镜像配置（自定义模板必须包含 {url} 或 {path}）
+export function validateMirrorConfig(config: GithubMirrorConfig) {
+  return invoke<void>("validate_mirror_config", { config });
+}
+
+export type MirrorTestResult = {
+  ok: boolean;
+  latencyMs: number;
+  status: number | null;
+};
+
+// 按镜像配置测试连通性
+export function testMirror(config: GithubMirrorConfig) {
+  return invoke<MirrorTestResult>("test_mirror", { config });
+}
+
+// 并发探测内置镜像并返回最快的一个（全部失败时为 null）
+export function autoSelectMirror() {
+  return invoke<GithubMirrorSource | null>("auto_select_mirror");
+}
+
 // 明日方舟终末地相关命令
-export function openHgTokenWebview(provider?: HgProvider) {
-  return invoke("hg_open_token_webview", provider ? { provider } : {});
+export type HgLoginChannel = "official" | "bilibili";
+
+export function openHgTokenWebview(provider?: HgProvider, channel?: HgLoginChannel) {
+  return invoke("hg_open_token_webview", { provider, channel });
+}
+
+// 打开登录窗口并等待 token（超时或取消时 reject）
+export function waitForHgToken(params: { provider?: HgProvider; channel?: HgLoginChannel; timeoutMs?: number } = {}) {
+  return invoke<string>("hg_wait_for_token", params);
 }
 
 export function exchangeHgUserToken(token: string, provider?: HgProvider) {
@@ -110,6 +288,12 @@ export function hgGachaAuthFromLog(params?: { logPath?: string }) {
   });
 }
 
+export function hgListLogGachaUrls(params?: { logPath?: string }) {
+  return invoke<HgLogGachaAuth[]>("hg_list_log_gacha_urls", {
+    logPath: params?.logPath,
+  });
+}
+
 export type HgRoleListResult = {
   uid: string;
   roles: Array<{ roleId: string; nickName: string; serverName?: string | null }>;
@@ -133,38 +317,55 @@ export type HgApiGachaRecord = {
   is_new: boolean;
 };
 
+export type HgGachaFetchResult = {
+  records: HgApiGachaRecord[];
+  truncated: boolean;
+};
+
+// hg_fetch_char_records / hg_fetch_weapon_records 每拉取一页触发的 "gacha-page-progress" 事件
+export type GachaPageProgress = {
+  poolType: string;
+  recordsSoFar: number;
+  page: number;
+};
+
 export type HgWeaponPool = {
   pool_id: string;
   pool_name: string;
 };
 
+/** Omit `maxRecords` to fetch the pool's whole history. */
 export function hgFetchCharRecords(params: {
   token: string;
   serverId: string;
   poolType: string;
   lastSeqIdStop?: string;
   provider?: HgProvider;
+  maxRecords?: number;
 }) {
-  return invoke<HgApiGachaRecord[]>("hg_fetch_char_records", params);
+  return invoke<HgGachaFetchResult>("hg_fetch_char_records", params);
 }
 
 export function hgFetchWeaponPools(params: { token: string; serverId: string; provider?: HgProvider }) {
   return invoke<HgWeaponPool[]>("hg_fetch_weapon_pools", params);
 }
 
+/** Omit `maxRecords` to fetch the pool's whole history. */
 export function hgFetchWeaponRecords(params: {
   token: string;
   serverId: string;
   poolId: string;
+  poolName?: string;
   lastSeqIdStop?: string;
   provider?: HgProvider;
+  maxRecords?: number;
 }) {
-  return invoke<HgApiGachaRecord[]>("hg_fetch_weapon_records", params);
+  return invoke<HgGachaFetchResult>("hg_fetch_weapon_records", params);
 }
 
 // 数据库相关命令
-export function dbListAccounts<T = unknown>() {
-  return invoke<T>("db_list_accounts");
+export function dbListAccounts<T = unknown>(includeArchived = false) {
+  return invoke<T>("db_list_accounts", { includeArchived });
 }
 
 export function dbUpsertAccount(args: {
@@ -193,41 +394,292 @@ export function dbDeleteAccount(uid: string) {
   return invoke("db_delete_account", { uid });
 }
 
+export function dbRestoreAccount(uid: string) {
+  return invoke("db_restore_account", { uid });
+}
+
+export function dbPurgeAccount(uid: string, deleteRecords = false) {
+  return invoke<number>("db_purge_account", { uid, deleteRecords });
+}
+
+export function dbReorderAccounts(uids: string[]) {
+  return invoke("db_reorder_accounts", { uids });
+}
+
+export function dbSetAccountAlias(uid: string, alias: string | null) {
+  return invoke("db_set_account_alias", { uid, alias });
+}
+
+export function dbDeleteAccountRecords(uid: string) {
+  return invoke<number>("db_delete_account_records", { uid });
+}
+
+export function dbDeletePoolRecords(uid: string, poolType: string) {
+  return invoke<number>("db_delete_pool_records", { uid, poolType });
+}
+
+export type ManualPullInput = {
+  uid: string;
+  poolType: string;
+  itemName: string;
+  rarity: number;
+  pulledAt: number;
+  bannerName: string;
+};
+
+/** Insert a hand-entered record; it gets a `manual_` seq_id and `source: "manual"`. */
+export function dbInsertManualPull<T = unknown>(input: ManualPullInput) {
+  return invoke<T>("db_insert_manual_pull", input);
+}
+
+export function dbBackfillItemNames(uid: string, lang?: string) {
+  return invoke<number>("db_backfill_item_names", { uid, lang });
+}
+
 export function dbGetAccountTokens<T = unknown>(uid: string) {
   return invoke<T>("db_get_account_tokens", { uid });
 }
 
+export type SyncLogEntry = {
+  id: number;
+  uid: string | null;
+  startedAt: number;
+  finishedAt: number;
+  mode: string;
+  count: number | null;
+  success: boolean;
+  errorMessage: string | null;
+};
+
+export function dbListSyncLog(params: { uid?: string; limit?: number } = {}) {
+  return invoke<SyncLogEntry[]>("db_list_sync_log", params);
+}
+
+/** seq_id the next incremental sync stops at, keyed by pool_type (weapon banners by pool id) */
+export function dbIncrementalCursors(uid: string) {
+  return invoke<Record<string, string>>("db_incremental_cursors", { uid });
+}
+
+export type InvalidRecordsDeleted = { zeroPulledAt: number; zeroRarity: number };
+
 export function dbDeleteInvalidGachaRecords(uid: string) {
-  return invoke("db_delete_invalid_gacha_records", { uid });
+  return invoke<InvalidRecordsDeleted>("db_delete_invalid_gacha_records", { uid });
+}
+
+export type OrphanCleanup = { deletedRows: number; deletedTags: number; freedBytes: number };
+
+export function dbCleanupOrphans() {
+  return invoke<OrphanCleanup>("db_cleanup_orphans");
 }
 
-export function dbSaveGachaRecords(uid: string, records: any[]) {
-  return invoke("db_save_gacha_records", { uid, records });
+export type DbStats = {
+  dbSizeBytes: number;
+  walSizeBytes: number;
+  accountCount: number;
+  totalPulls: number;
+  oldestPull: number | null;
+  newestPull: number | null;
+};
+
+export function dbStats() {
+  return invoke<DbStats>("db_stats");
+}
+
+/** Where a stored record came from; `unknown` marks rows saved before this was tracked. */
+export type GachaRecordSource = "token" | "log" | "import" | "manual" | "unknown";
+
+/** `source` defaults to `token` on the backend. */
+export function dbSaveGachaRecords(uid: string, records: any[], source?: Exclude<GachaRecordSource, "manual" | "unknown">) {
+  return invoke("db_save_gacha_records", { uid, records, source });
 }
 
-export function dbListGachaPulls<T = unknown>(uid: string, limit = 200) {
-  return invoke<T>("db_list_gacha_pulls", { uid, limit });
+/**
+ * Pass `tzOffsetSeconds` (e.g. 28800 for UTC+8) to also get `pulledAtLocal` ISO strings.
+ * `lang` picks item names stored by a multi-language sync, falling back to `itemName`.
+ */
+export function dbListGachaPulls<T = unknown>(uid: string, limit = 200, tzOffsetSeconds?: number, lang?: string) {
+  return invoke<T>("db_list_gacha_pulls", { uid, limit, tzOffsetSeconds, lang });
+}
+
+export function dbListGachaPullsWithTags<T = unknown>(uid: string, limit = 200) {
+  return invoke<(T & { tags: string[] })[]>("db_list_gacha_pulls_with_tags", { uid, limit });
+}
+
+export type GachaTagTarget = { uid: string; seqId: string; poolType: string; tag: string };
+export type GachaTag = { seqId: string; poolType: string; tag: string; createdAt: number };
+
+export function dbTagPull(params: GachaTagTarget) {
+  return invoke<void>("db_tag_pull", params);
+}
+
+export function dbUntagPull(params: GachaTagTarget) {
+  return invoke<boolean>("db_untag_pull", params);
+}
+
+export function dbListTags(uid: string) {
+  return invoke<GachaTag[]>("db_list_tags", { uid });
+}
+
+export function dbSearchGacha<T = unknown>(uid: string, query: string, limit = 200) {
+  return invoke<T[]>("db_search_gacha", { uid, query, limit });
+}
+
+export function dbGachaAfter<T = unknown>(uid: string, afterPulledAt: number) {
+  return invoke<T[]>("db_gacha_after", { uid, afterPulledAt });
+}
+
+export type GachaPullCursor = { pulledAt: number; id: number };
+
+export type GachaPullPage<T = unknown> = { items: T[]; nextCursor: GachaPullCursor | null };
+
+export function dbListGachaPullsPage<T = unknown>(uid: string, cursor: GachaPullCursor | null = null, limit = 200) {
+  return invoke<GachaPullPage<T>>("db_list_gacha_pulls_page", {
+    uid,
+    beforePulledAt: cursor?.pulledAt ?? null,
+    beforeId: cursor?.id ?? null,
+    limit,
+  });
+}
+
+export type PoolSummary = {
+  bannerId: string;
+  bannerName: string;
+  poolType: string | null;
+  count: number;
+  firstPull: number;
+  lastPull: number;
+};
+
+export function dbListPools(uid: string) {
+  return invoke<PoolSummary[]>("db_list_pools", { uid });
+}
+
+export type TimelineBucket = { bucketStart: number; count: number; sixStarCount: number };
+
+export function dbGachaTimeline(params: { uid: string; bucket: "day" | "week" | "month"; tzOffsetSeconds?: number }) {
+  return invoke<TimelineBucket[]>("db_gacha_timeline", params);
+}
+
+export type ReportSixStar = {
+  itemName: string;
+  itemId: string | null;
+  bannerName: string;
+  pulledAt: number;
+  pity: number;
+};
+
+export type ReportPool = {
+  poolKey: string;
+  poolType: string | null;
+  bannerName: string;
+  totalPulls: number;
+  currentPity: number;
+  sixStars: ReportSixStar[];
+};
+
+export type GachaReport = {
+  uid: string;
+  totalPulls: number;
+  pullCost: number;
+  spendEstimate: number;
+  firstPull: number | null;
+  lastPull: number | null;
+  pools: ReportPool[];
+  luckiest: ReportSixStar | null;
+  unluckiest: ReportSixStar | null;
+};
+
+export function dbGachaReport(uid: string) {
+  return invoke<GachaReport>("db_gacha_report", { uid });
+}
+
+export type LimitedOutcome = "won" | "lost" | "guaranteed";
+
+export type LimitedAnalysis = {
+  bannerId: string;
+  rateUp: string[];
+  wins: number;
+  losses: number;
+  guaranteed: number;
+  nextIsGuaranteed: boolean;
+  sixStars: Array<{ itemName: string; itemId: string | null; pulledAt: number; outcome: LimitedOutcome }>;
+};
+
+export function dbLimitedAnalysis(uid: string, bannerId: string) {
+  return invoke<LimitedAnalysis>("db_limited_analysis", { uid, bannerId });
 }
 
 // ───────────────────────────────────────────────────────────────────────────
 // 同步相关命令 (后端直接处理 DB)
 // ───────────────────────────────────────────────────────────────────────────
 
-export type SyncResult = { count: number; accountUpdated: boolean };
+export type SyncPreview = { newRecords: number; updatedRecords: number; unchanged: number };
+export type SyncResult = {
+  count: number;
+  accountUpdated: boolean;
+  truncated: boolean;
+  preview: SyncPreview | null;
+  recovered: number | null;
+};
 
-export function syncGachaByToken(params: { uid: string; mode: "incremental" | "full" }) {
+// sync_gacha_by_token 失败时的错误对象
+export type SyncError =
+  | { kind: "alreadyRunning"; uid: string; message: string }
+  | { kind: "offline"; message: string }
+  | { kind: "failed"; message: string };
+
+/**
+ * `langs` fetches names in each language (first one is primary) and stores them side by side.
+ * `serverId` syncs that server instead of the stored one and, unless `dryRun`, becomes the account's default.
+ */
+export function syncGachaByToken(params: { uid: string; mode: "incremental" | "full" | "repair"; dryRun?: boolean; checkOnline?: boolean; langs?: string[]; serverId?: string }) {
   return invoke<SyncResult>("sync_gacha_by_token", params);
 }
 
-export type LogSyncResult = { uid: string; count: number };
+export type AccountSyncOutcome = { uid: string; count: number; error: string | null };
+export type SyncAllProgress = { uid: string; index: number; total: number };
+
+export function syncAllAccounts(params: { mode: "incremental" | "full" }) {
+  return invoke<AccountSyncOutcome[]>("sync_all_accounts", params);
+}
+
+export type U8TokenRefreshOutcome = { uid: string; fetchedAt: number | null; error: string | null };
+
+export function refreshAllU8Tokens() {
+  return invoke<U8TokenRefreshOutcome[]>("refresh_all_u8_tokens");
+}
+
+export type LogSyncResult = { uid: string; count: number; truncated: boolean };
 
 export function syncGachaFromLog(params: { logPath?: string; mode: "incremental" | "full" }) {
   return invoke<LogSyncResult>("sync_gacha_from_log", params);
 }
 
-export type AddedAccount = { uid: string; roleId: string; nickName: string; serverId: string };
+export type AddedAccount = {
+  uid: string;
+  roleId: string;
+  nickName: string;
+  serverId: string;
+  channelId: number | null;
+  provider: HgProvider;
+};
 export type AddAccountResult = { accounts: AddedAccount[] };
 
 export function addAccountByToken(params: { userToken: string; provider?: HgProvider }) {
   return invoke<AddAccountResult>("add_account_by_token", params);
 }
+
+export type ClipboardImport =
+  | { kind: "userToken"; accounts: AddedAccount[] }
+  | { kind: "gachaUrl"; result: LogSyncResult };
+
+/** Import a user token or a gacha record URL (with `u8_token`) found on the clipboard. */
+export function hgImportTokenFromClipboard(params: { provider?: HgProvider; mode?: "incremental" | "full" } = {}) {
+  return invoke<ClipboardImport>("hg_import_token_from_clipboard", params);
+}
+
+export type TokenValidation = { valid: boolean; message: string | null };
+
+export function hgValidateToken(params: { token: string; provider?: HgProvider }) {
+  return invoke<TokenValidation>("hg_validate_token", params);
+}
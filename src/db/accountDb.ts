@@ -1,5 +1,15 @@
 import { isSqliteAvailable } from "./db";
-import { dbDeleteAccount, dbGetAccountTokens, dbListAccounts, dbUpsertAccount } from "../api/tauriCommands";
+import {
+  dbDeleteAccount,
+  dbDeleteAccountRecords,
+  dbGetAccountTokens,
+  dbListAccounts,
+  dbPurgeAccount,
+  dbReorderAccounts,
+  dbRestoreAccount,
+  dbSetAccountAlias,
+  dbUpsertAccount,
+} from "../api/tauriCommands";
 
 export type Account = {
   uid: string;
@@ -8,6 +18,11 @@ export type Account = {
   serverId: string | null;
   channelId: number | null;
   updatedAt: number;
+  lastSyncedAt: number | null;  // 上次成功同步时间（秒）
+  alias: string | null;  // 用户自定义备注名
+  displayName: string | null;  // 有备注名时为备注名，否则为 nickName
+  sortOrder: number | null;  // 用户排序位置，未设置时按更新时间排在后面
+  archived: boolean;  // 已移除但保留记录
 };
 
 export type AccountWithTokens = {
@@ -19,11 +34,12 @@ export type AccountWithTokens = {
   userToken: string | null;
   oauthToken: string | null;
   u8Token: string | null;
+  u8TokenFetchedAt: number | null;
 };
 
-export async function listAccounts(): Promise<Account[]> {
+export async function listAccounts(includeArchived = false): Promise<Account[]> {
   if (!isSqliteAvailable()) return [];
-  return await dbListAccounts<Account[]>();
+  return await dbListAccounts<Account[]>(includeArchived);
 }
 
 export async function upsertAccount(args: {
@@ -39,10 +55,31 @@ export async function upsertAccount(args: {
   await dbUpsertAccount(args);
 }
 
+export async function reorderAccounts(uids: string[]) {
+  await dbReorderAccounts(uids);
+}
+
+export async function setAccountAlias(uid: string, alias: string | null) {
+  await dbSetAccountAlias(uid, alias);
+}
+
+// 默认仅归档：从列表隐藏但保留抽卡记录，重新添加或 restoreAccount 即可恢复
 export async function deleteAccount(uid: string) {
   await dbDeleteAccount(uid);
 }
 
+export async function restoreAccount(uid: string) {
+  await dbRestoreAccount(uid);
+}
+
+export async function purgeAccount(uid: string, deleteRecords = false) {
+  return await dbPurgeAccount(uid, deleteRecords);
+}
+
+export async function deleteAccountRecords(uid: string) {
+  return await dbDeleteAccountRecords(uid);
+}
+
 export async function getAccountTokens(uid: string): Promise<AccountWithTokens | null> {
   return await dbGetAccountTokens<AccountWithTokens | null>(uid);
 }
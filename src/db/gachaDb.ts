@@ -1,5 +1,5 @@
 import { isSqliteAvailable } from "./db";
-import { dbDeleteInvalidGachaRecords, dbListGachaPulls, dbSaveGachaRecords } from "../api/tauriCommands";
+import { dbDeleteInvalidGachaRecords, dbDeletePoolRecords, dbInsertManualPull, dbListGachaPulls, dbListGachaPullsPage, dbListGachaPullsWithTags, dbListTags, dbSaveGachaRecords, dbSearchGacha, dbGachaAfter, dbTagPull, dbUntagPull, type GachaPullCursor, type GachaRecordSource, type ManualPullInput } from "../api/tauriCommands";
 
 export { isSqliteAvailable };
 
@@ -13,6 +13,8 @@ export type GachaPull = {
   seqId?: string;
   itemId?: string;
   poolType?: string;
+  pulledAtLocal?: string;
+  source: GachaRecordSource;
 };
 
 export interface ApiGachaRecord {
@@ -27,7 +29,15 @@ export interface ApiGachaRecord {
 }
 
 export async function deleteInvalidGachaRecords(uid: string) {
-  await dbDeleteInvalidGachaRecords(uid);
+  return await dbDeleteInvalidGachaRecords(uid);
+}
+
+export async function deletePoolRecords(uid: string, poolType: string) {
+  return await dbDeletePoolRecords(uid, poolType);
+}
+
+export async function insertManualPull(input: ManualPullInput): Promise<GachaPull> {
+  return await dbInsertManualPull<GachaPull>(input);
 }
 
 export async function saveGachaRecords(uid: string, records: any[]) {
@@ -43,6 +53,34 @@ export async function saveGachaPulls(_pulls: GachaPull[]) {
   console.warn("saveGachaPulls 已弃用，前端不再实现");
 }
 
-export async function listGachaPulls(uid: string, limit = 200): Promise<GachaPull[]> {
-  return await dbListGachaPulls<GachaPull[]>(uid, limit);
+export async function listGachaPulls(uid: string, limit = 200, tzOffsetSeconds?: number, lang?: string): Promise<GachaPull[]> {
+  return await dbListGachaPulls<GachaPull[]>(uid, limit, tzOffsetSeconds, lang);
+}
+
+export async function listGachaPullsWithTags(uid: string, limit = 200) {
+  return await dbListGachaPullsWithTags<GachaPull>(uid, limit);
+}
+
+export async function tagPull(uid: string, seqId: string, poolType: string, tag: string) {
+  await dbTagPull({ uid, seqId, poolType, tag });
+}
+
+export async function untagPull(uid: string, seqId: string, poolType: string, tag: string) {
+  return await dbUntagPull({ uid, seqId, poolType, tag });
+}
+
+export async function listTags(uid: string) {
+  return await dbListTags(uid);
+}
+
+export async function listGachaPullsPage(uid: string, cursor: GachaPullCursor | null = null, limit = 200) {
+  return await dbListGachaPullsPage<GachaPull>(uid, cursor, limit);
+}
+
+export async function searchGachaPulls(uid: string, query: string, limit = 200) {
+  return await dbSearchGacha<GachaPull>(uid, query, limit);
+}
+
+export async function listGachaPullsAfter(uid: string, afterPulledAt: number) {
+  return await dbGachaAfter<GachaPull>(uid, afterPulledAt);
 }
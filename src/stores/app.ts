@@ -8,6 +8,7 @@ const METADATA_MIRROR_TEMPLATE = 'https://cdn.jsdmirror.com/gh/BoxCatTeam/endfie
 const DEFAULT_METADATA_VERSION = 'latest'
 
 export type MetadataSourceType = 'cdn' | 'mirror' | 'custom'
+export type UpdateChannel = 'stable' | 'beta'
 export type GithubMirrorSourceType = 'gh-proxy-cf' | 'gh-proxy-fastly' | 'gh-proxy-edgeone' | 'ghfast' | 'custom'
 
 export const GITHUB_MIRROR_TEMPLATES: Record<GithubMirrorSourceType, string> = {
@@ -18,6 +19,7 @@ export const GITHUB_MIRROR_TEMPLATES: Record<GithubMirrorSourceType, string> = {
   'custom': '{url}',
 }
 type RemoteManifest = {
+  schemaVersion?: number
   packageVersion?: string
   metadataChecksum?: string
   itemCount?: number
@@ -29,6 +31,11 @@ type MetadataStatus = {
   fileCount: number
   hasManifest: boolean
   currentVersion?: string
+  mirror?: string
+  metadataChecksum?: string
+  matchesManifest: boolean
+  skippedCleanup: number
+  redownloaded: string[]
   remote?: RemoteManifest
 }
 
@@ -37,6 +44,11 @@ export type MetadataUpdateProgress = {
   current: number
   total: number
   path: string
+} | {
+  phase: 'failed'
+  path: string
+  url: string
+  status: number | null
 }
 
 
@@ -60,6 +72,9 @@ export const useAppStore = defineStore('app', () => {
   const githubMirrorSource = ref<GithubMirrorSourceType>('gh-proxy-cf')
   const githubMirrorCustomTemplate = ref('')
 
+  // 更新通道：beta 会同时检查预发布版本
+  const updateChannel = ref<UpdateChannel>('stable')
+
   // 初次加载时避免写回配置
   const isLoaded = ref(false)
   const metadataStatus = ref<MetadataStatus | null>(null)
@@ -67,7 +82,7 @@ export const useAppStore = defineStore('app', () => {
   // 元数据更新相关状态
   const showMetadataUpdateDialog = ref(false)
   const isMetadataUpdating = ref(false)
-  const metadataUpdateProgress = ref<MetadataUpdateProgress | null>(null)
+  const metadataUpdateProgress = ref<Exclude<MetadataUpdateProgress, { phase: 'failed' }> | null>(null)
 
   const isMetadataOutdated = computed(() => {
     if (!metadataStatus.value || !metadataStatus.value.remote || !metadataStatus.value.currentVersion) return false
@@ -128,6 +143,9 @@ export const useAppStore = defineStore('app', () => {
         githubMirrorSource.value = config.githubMirror.source ?? 'gh-proxy-cf'
         githubMirrorCustomTemplate.value = config.githubMirror.customTemplate ?? ''
       }
+      if (config?.updateChannel === 'beta' || config?.updateChannel === 'stable') {
+        updateChannel.value = config.updateChannel
+      }
 
       isLoaded.value = true
     } catch (error) {
@@ -161,7 +179,8 @@ export const useAppStore = defineStore('app', () => {
           enabled: githubMirrorEnabled.value,
           source: githubMirrorSource.value,
           customTemplate: githubMirrorCustomTemplate.value,
-        }
+        },
+        updateChannel: updateChannel.value,
       }
       configCache.value = nextConfig
       await saveConfigCommand(nextConfig)
@@ -171,7 +190,7 @@ export const useAppStore = defineStore('app', () => {
   }
 
   // 监听变更自动保存
-  watch([theme, background, language, metadataCustomBase, firstRun, acknowledgedAppVersion, pendingPostUpdateVersion, needsPostUpdateGuide, githubMirrorEnabled, githubMirrorSource, githubMirrorCustomTemplate], () => {
+  watch([theme, background, language, metadataCustomBase, firstRun, acknowledgedAppVersion, pendingPostUpdateVersion, needsPostUpdateGuide, githubMirrorEnabled, githubMirrorSource, githubMirrorCustomTemplate, updateChannel], () => {
     void saveConfig()
   })
 
@@ -265,6 +284,10 @@ export const useAppStore = defineStore('app', () => {
 
       // 监听更新进度事件
       unlisten = await listen<MetadataUpdateProgress>('metadata-update-progress', (event) => {
+        if (event.payload.phase === 'failed') {
+          console.error('Metadata file failed:', event.payload.url, event.payload.status)
+          return
+        }
         metadataUpdateProgress.value = event.payload
       })
 
@@ -317,6 +340,7 @@ export const useAppStore = defineStore('app', () => {
     githubMirrorSource,
     githubMirrorCustomTemplate,
     getGithubMirrorTemplate,
+    updateChannel,
     // 元数据更新相关
     showMetadataUpdateDialog,
     isMetadataUpdating,
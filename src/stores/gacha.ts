@@ -270,7 +270,7 @@ export const useGachaStore = defineStore("gacha", () => {
     const currentNickname = computed(() => {
         if (!uid.value) return "";
         const acc = accountsList.value.find(a => a.uid === uid.value);
-        return acc?.nickName || "";
+        return acc?.displayName || acc?.nickName || "";
     });
 
     const bannerSummary = computed(() =>
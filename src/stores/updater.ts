@@ -4,14 +4,24 @@ import { openUrl } from "@tauri-apps/plugin-opener";
 import { Snackbar } from "@varlet/ui";
 import * as semver from "semver";
 import i18n from "../i18n";
+import { useAppStore } from "./app";
 import { downloadAndApplyUpdate, fetchLatestPrerelease, fetchLatestRelease, getAppVersion } from "../api/tauriCommands";
 
+export type ReleaseAsset = {
+  name: string;
+  size: number;
+  download_url: string;
+  digest?: string | null;
+};
+
 export type LatestRelease = {
   tag_name: string;
   name?: string;
   html_url?: string;
   download_url?: string;
+  checksum?: string | null;
   body?: string;
+  assets: ReleaseAsset[];
 };
 
 type UpdateTarget = "primary" | "alt";
@@ -80,8 +90,9 @@ export const useUpdaterStore = defineStore("updater", () => {
     try {
       localVersion.value = await getAppVersion();
       const isPreviewBuild = normalizeVersion(localVersion.value).toLowerCase().includes("-pre");
+      const isBetaChannel = useAppStore().updateChannel === "beta";
 
-      if (isPreviewBuild) {
+      if (isPreviewBuild || isBetaChannel) {
         const [stableRes, preRes] = await Promise.allSettled([
           fetchLatestRelease<LatestRelease>(),
           fetchLatestPrerelease<LatestRelease>(),
@@ -139,7 +150,7 @@ export const useUpdaterStore = defineStore("updater", () => {
 
     isUpdating.value = true;
     try {
-      await downloadAndApplyUpdate(info.download_url);
+      await downloadAndApplyUpdate(info.download_url, info.checksum);
     } catch (error) {
       console.error("Update failed:", error);
       Snackbar.error(i18n.global.t("settings.update.installFailed") || "Install failed");